@@ -0,0 +1,102 @@
+use criterion::measurement::WallTime;
+use criterion::{
+    criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion,
+};
+
+use dreamervm::core::asm::assemble;
+use dreamervm::core::code::Code;
+use dreamervm::core::machine::{Machine, MachineError};
+use dreamervm::core::state::State;
+
+const STEPS: [u64; 2] = [1_000, 10_000];
+
+/* increments mem[0] forever, touching a single cell */
+const LOOP: &str = "
+            Set 0
+            Push
+    loop:   Pop         ; discard the jump target
+            Set 0
+            Push
+            Load
+            Set 1
+            Push
+            Add
+            Set 0
+            Push
+            Store
+            Set loop
+            Push
+            Jump
+";
+
+/* as LOOP, but also stores each count at its own address */
+const MEMORY: &str = "
+            Set 0
+            Push
+    loop:   Pop
+            Set 0
+            Push
+            Load
+            Set 1
+            Push
+            Add
+            Set 0
+            Push
+            Store
+            Set 0       ; mem[mem[0]] = mem[0], a fresh cell every time
+            Push
+            Load
+            Set 0
+            Push
+            Load
+            Store
+            Set loop
+            Push
+            Jump
+";
+
+/* `Machine::run`, which steps the state in place */
+fn run(code: &Code, steps: u64) -> Result<State, MachineError> {
+    Machine::new(code.clone()).with_step_limit(steps).run()
+}
+
+/*
+ * `Machine::run_callback`, which copies the state before every step so
+ * the callback can see it, as every step used to
+ */
+fn run_callback(code: &Code, steps: u64) -> Result<State, MachineError> {
+    Machine::new(code.clone())
+        .with_step_limit(steps)
+        .run_callback(&mut |_, _, _| Ok(()))
+}
+
+fn bench(c: &mut Criterion, name: &str, source: &str) {
+    let code: Code = assemble(source).expect("benchmark assembles");
+    let mut group: BenchmarkGroup<WallTime> = c.benchmark_group(name);
+
+    for steps in STEPS {
+        group.bench_with_input(
+            BenchmarkId::new("run", steps),
+            &steps,
+            |b, t| b.iter(|| run(&code, *t)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("run_callback", steps),
+            &steps,
+            |b, t| b.iter(|| run_callback(&code, *t)),
+        );
+    }
+
+    group.finish();
+}
+
+fn loop_heavy(c: &mut Criterion) {
+    bench(c, "loop", LOOP);
+}
+
+fn memory_heavy(c: &mut Criterion) {
+    bench(c, "memory", MEMORY);
+}
+
+criterion_group!(benches, loop_heavy, memory_heavy);
+criterion_main!(benches);
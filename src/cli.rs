@@ -1,15 +1,292 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, Parser)]
 #[clap(about, version, author)]
 pub enum Opts {
-    #[clap(override_help = "Executes a Dreamer program")]
-    Run {
+    #[clap(about = "Executes a Dreamer program")]
+    Run(RunOpts),
+    #[clap(subcommand)]
+    Trace(TraceCommand),
+    #[clap(about = "Runs a program over a range of initial register values")]
+    Sweep(SweepOpts),
+    #[clap(about = "Runs many programs independently, in parallel")]
+    Batch(BatchOpts),
+    #[clap(
+        about = "Runs programs in turn, each starting from the last's memory"
+    )]
+    Pipeline(PipelineOpts),
+    #[clap(about = "Checks two programs behave alike over shared inputs")]
+    Equiv(EquivOpts),
+    #[clap(about = "Searches for a cheaper equivalent of a short program")]
+    Superopt(SuperoptOpts),
+    #[clap(about = "Searches for initial inputs that make a program fault")]
+    FuzzInputs(FuzzInputsOpts),
+    #[clap(about = "Scores a program against an exercise's expected outputs")]
+    Grade(GradeOpts),
+    #[clap(
+        about = "Runs random programs on every engine, checking they agree"
+    )]
+    Torture(TortureOpts),
+    #[clap(about = "Statically checks a program for possible faults")]
+    Verify(VerifyOpts),
+    #[clap(about = "Shows the instruction-level differences between programs")]
+    Bindiff { old: PathBuf, new: PathBuf },
+    #[clap(about = "Assembles a textual listing into a program")]
+    Asm {
+        source: PathBuf,
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+    #[clap(about = "Lists a program's instructions with their offsets")]
+    Disasm { path: PathBuf },
+    #[clap(about = "Prints a crash dump written by run --crash-dump")]
+    InspectDump { path: PathBuf },
+    #[clap(about = "Queries a run started with --inspect-socket")]
+    Attach {
+        socket: PathBuf,
+        #[clap(long, multiple_values = false)]
+        mem: Vec<String>,
+    },
+    #[clap(about = "Explains an error by name or exit code")]
+    ExplainError { error: String },
+    #[clap(about = "Prints the instruction set reference")]
+    Isa {
+        #[clap(long, default_value = "md")]
+        format: String,
+        #[clap(long)]
+        cost_model: Option<PathBuf>,
+    },
+    #[clap(about = "Prints the JSON Schema for one of the JSON outputs")]
+    Schema { name: Option<String> },
+}
+
+#[derive(Clone, Debug, Subcommand)]
+#[clap(about = "Inspects trace files written by run")]
+pub enum TraceCommand {
+    #[clap(about = "Finds the segment of a rotated trace holding a step")]
+    Segment { index: PathBuf, step: u64 },
+    #[clap(about = "Prints per-opcode statistics for a trace file")]
+    Stats { path: PathBuf },
+    #[clap(about = "Runs a query over the steps of a trace file")]
+    Query { path: PathBuf, query: String },
+    #[clap(about = "Exports the steps (or statistics) of a trace as CSV")]
+    Export {
         path: PathBuf,
+        #[clap(long)]
+        stats: bool,
         #[clap(long, short)]
-        trace: bool,
         output: Option<PathBuf>,
     },
 }
+
+#[derive(Clone, Debug, Args)]
+pub struct RunOpts {
+    pub path: PathBuf,
+    #[clap(long)]
+    pub lenient: bool,
+    #[clap(long, conflicts_with = "lenient")]
+    pub lazy: bool,
+    #[clap(long)]
+    pub init_stack: Option<String>,
+    #[clap(long)]
+    pub init_reg: Option<String>,
+    #[clap(long, short)]
+    pub trace: bool,
+    #[clap(long)]
+    pub trace_filter: Option<String>,
+    #[clap(long)]
+    pub trace_file: Option<PathBuf>,
+    #[clap(long, conflicts_with = "trace-file")]
+    pub trace_sink: Option<String>,
+    #[clap(long, default_value = "jsonl")]
+    pub trace_format: String,
+    #[clap(long, default_value = "block")]
+    pub trace_overflow: String,
+    #[clap(long, requires = "trace-file")]
+    pub trace_rotate: Option<String>,
+    #[clap(long)]
+    pub trace_from_marker: bool,
+    #[clap(long, multiple_values = false)]
+    pub watch_expr: Vec<String>,
+    #[clap(long)]
+    pub profile_sample: Option<usize>,
+    #[clap(long)]
+    pub cost_model: Option<PathBuf>,
+    #[clap(long)]
+    pub energy_table: Option<PathBuf>,
+    #[clap(long)]
+    pub cache: Option<String>,
+    #[clap(long)]
+    pub pipeline: Option<usize>,
+    #[clap(long)]
+    pub pipeline_timeline: bool,
+    #[clap(long)]
+    pub check_invariants: bool,
+    #[clap(long)]
+    pub max_steps: Option<u64>,
+    #[clap(long)]
+    pub memory_limit: Option<usize>,
+    #[clap(long)]
+    pub rate_limit: Option<String>,
+    #[clap(long)]
+    pub limits: Option<String>,
+    #[clap(long, requires = "limits")]
+    pub limits_config: Option<PathBuf>,
+    #[clap(long)]
+    pub deny_ops: Option<String>,
+    #[clap(long)]
+    pub teach: bool,
+    #[clap(long)]
+    pub lifecycle: bool,
+    #[clap(long)]
+    pub progress: bool,
+    #[clap(long)]
+    pub inspect_socket: Option<PathBuf>,
+    #[clap(long, conflicts_with = "lazy")]
+    pub dry_run: bool,
+    #[clap(long)]
+    pub snapshot_on_interrupt: Option<PathBuf>,
+    #[clap(long)]
+    pub regions: Option<PathBuf>,
+    #[clap(long)]
+    pub stdio: bool,
+    #[clap(long, requires = "stdio")]
+    pub io_format: Option<String>,
+    #[clap(long, requires = "stdio")]
+    pub eof: Option<String>,
+    #[clap(long, requires = "stdio")]
+    pub io_in: Option<PathBuf>,
+    #[clap(long, requires = "stdio")]
+    pub io_out: Option<PathBuf>,
+    #[clap(long, conflicts_with = "stdio")]
+    pub events: Option<PathBuf>,
+    #[clap(long)]
+    pub state_file: Option<PathBuf>,
+    #[clap(long, requires = "state-file")]
+    pub state_stack: bool,
+    #[clap(long)]
+    pub crash_dump: Option<PathBuf>,
+    #[clap(long, requires = "crash-dump")]
+    pub crash_history: Option<usize>,
+    #[clap(long)]
+    pub manifest: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct SweepOpts {
+    pub path: PathBuf,
+    #[clap(long)]
+    pub init_reg: String,
+    #[clap(long)]
+    pub init_stack: Option<String>,
+    #[clap(long, short)]
+    pub jobs: Option<usize>,
+    #[clap(long)]
+    pub max_steps: Option<u64>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct BatchOpts {
+    #[clap(required = true)]
+    pub paths: Vec<PathBuf>,
+    #[clap(long, short)]
+    pub jobs: Option<usize>,
+    #[clap(long)]
+    pub max_steps: Option<u64>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct PipelineOpts {
+    #[clap(required = true)]
+    pub paths: Vec<PathBuf>,
+    #[clap(long)]
+    pub max_steps: Option<u64>,
+    #[clap(long)]
+    pub memory_limit: Option<usize>,
+    #[clap(long)]
+    pub regions: Option<PathBuf>,
+    #[clap(long, requires = "regions")]
+    pub carry: Option<String>,
+    #[clap(long)]
+    pub trace_file: Option<PathBuf>,
+    #[clap(long, default_value = "jsonl")]
+    pub trace_format: String,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct EquivOpts {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    #[clap(long, default_value = "random:1000")]
+    pub inputs: String,
+    #[clap(long)]
+    pub step_limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct SuperoptOpts {
+    pub path: PathBuf,
+    #[clap(long, default_value = "3")]
+    pub max_len: usize,
+    #[clap(long, default_value = "random:256")]
+    pub inputs: String,
+    #[clap(long)]
+    pub cost_model: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct FuzzInputsOpts {
+    pub path: PathBuf,
+    #[clap(long, default_value = "2")]
+    pub stack_args: usize,
+    #[clap(long, default_value = "10000")]
+    pub iterations: u64,
+    #[clap(long, default_value = "1")]
+    pub seed: u64,
+    #[clap(long)]
+    pub step_limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct GradeOpts {
+    pub path: PathBuf,
+    #[clap(long)]
+    pub spec: PathBuf,
+    #[clap(long)]
+    pub reference: Option<PathBuf>,
+    #[clap(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct TortureOpts {
+    #[clap(long, default_value = "1")]
+    pub minutes: u64,
+    #[clap(long)]
+    pub cases: Option<u64>,
+    #[clap(long, default_value = "1")]
+    pub seed: u64,
+    #[clap(long)]
+    pub step_limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct VerifyOpts {
+    pub path: PathBuf,
+    #[clap(long, default_value = "0")]
+    pub stack_args: usize,
+    #[clap(long)]
+    pub annotate: bool,
+    #[clap(long)]
+    pub warnings: bool,
+    #[clap(long, default_value = "text", conflicts_with = "annotate")]
+    pub format: String,
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+    #[clap(long)]
+    pub deny_ops: Option<String>,
+}
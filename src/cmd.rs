@@ -1,20 +1,222 @@
 use std::boxed::Box;
+use std::collections::HashSet;
 use std::fs;
 use std::fs::File;
 use std::io;
-use std::io::Write;
-use std::path::Path;
+use std::io::{BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::core::code::{Code, CodeParseError};
-use crate::core::instruction::Instruction;
-use crate::core::machine::Machine;
+use crate::cli::{
+    BatchOpts, EquivOpts, FuzzInputsOpts, GradeOpts, PipelineOpts, RunOpts,
+    SuperoptOpts, SweepOpts, TortureOpts, VerifyOpts,
+};
+use crate::common::config::{Config, ConfigError};
+use crate::common::hash::fnv1a;
+use crate::common::pool::{default_jobs, parallel_map};
+use crate::common::signal::INTERRUPTED;
+use crate::common::types::{parse_word, word_to_usize, Word};
+use crate::core::analysis::{
+    analyse, denied_findings, warnings, AbstractState, Analysis,
+};
+use crate::core::asm::{assemble, disassemble, AsmError};
+use crate::core::cache::{Cache, CacheConfig, CacheConfigError};
+use crate::core::code::{
+    Code, CodeParseError, CodeReadError, LazyCode, ParseMode, Program,
+};
+use crate::core::cost::{CostModel, CostModelError, EnergyTable};
+use crate::core::device::{
+    Device, EofPolicy, EofPolicyError, Framing, FramingError, PollRead,
+    StdinReader, StreamDevice,
+};
+use crate::core::diagnostic::{
+    offsets, Diagnostic, Report, VerifyConfig, VerifyConfigError,
+    REPORT_VERSION,
+};
+use crate::core::diff::{diff, Edit};
+use crate::core::dump::{
+    read_dump, CrashDump, DumpError, History, DEFAULT_DUMP_HISTORY,
+    DUMP_VERSION,
+};
+use crate::core::equiv::{
+    compare, Difference, Input, InputSpec, InputSpecError,
+    DEFAULT_EQUIV_STEP_LIMIT,
+};
+use crate::core::error::DreamerError;
+use crate::core::event::EventLog;
+use crate::core::explain::explain;
+use crate::core::expr::{ExprParseError, Watch};
+use crate::core::filter::{Filter, FilterParseError};
+use crate::core::fuzz::{Crash, InputFuzzer};
+use crate::core::grade;
+use crate::core::grade::{read_spec, ScoreReport, Spec, SpecError};
+use crate::core::inspect;
+use crate::core::inspect::{
+    InspectError, InspectReply, InspectRequest, InspectServer,
+};
+use crate::core::instruction::{
+    Instruction, OpcodeSet, OpcodeSetError, ISA_VERSION,
+};
+use crate::core::invariant;
+use crate::core::invariant::InvariantFailure;
+use crate::core::isa::IsaReference;
+use crate::core::lifecycle::{Lifecycle, Observer};
+use crate::core::limits::{LimitPresets, Limits, LimitsError};
+use crate::core::machine::{Machine, MachineError};
+use crate::core::manifest::{Manifest, MANIFEST_VERSION};
+use crate::core::memory::{LinearlyAddressable, Memory};
+use crate::core::pipeline::{Pipeline, PipelineError, DEFAULT_PIPELINE_STAGES};
+use crate::core::profile::Profile;
+use crate::core::progress::Progress;
+use crate::core::query::{Query, QueryResult};
+use crate::core::region::{Region, RegionError, Regions};
+use crate::core::schema;
+use crate::core::schema::SCHEMAS;
+use crate::core::snapshot::{read_snapshot, write_snapshot, SnapshotError};
+use crate::core::stack::Stack;
 use crate::core::state::State;
+use crate::core::stats::ProgramStats;
+use crate::core::superopt;
+use crate::core::superopt::{Candidate, SuperoptError};
+use crate::core::teach;
+use crate::core::teach::{Provenance, TEACH_STEP_LIMIT};
+use crate::core::throttle::{RateLimit, RateLimitError, Throttle};
+use crate::core::torture;
+use crate::core::torture::{Summary, DEFAULT_TORTURE_STEP_LIMIT};
+use crate::core::trace::{
+    find_segment, read_index, read_trace, summarise, write_stats_csv,
+    write_steps_csv, BackgroundTraceWriter, OverflowPolicy, Rotation, Segment,
+    TraceEntry, TraceError, TraceFormat, TraceRecord, TraceSink, TraceSummary,
+    TraceWriter, TRACE_BUFFER_BYTES,
+};
 
 #[derive(Debug)]
 pub enum CommandError {
     FileError,
+    AsmError(AsmError),
+    OpcodeSetError(OpcodeSetError),
+    LimitsError(LimitsError),
+    DumpError(DumpError),
+    CacheError(CacheConfigError),
     CodeError(CodeParseError),
+    ConfigError(ConfigError),
+    CostModelError(CostModelError),
+    FilterError(FilterParseError),
+    SpecError(SpecError),
+    Inequivalent(usize),
+    InputError(InputSpecError),
+    InspectError(InspectError),
     IOError(io::Error),
+    InvalidArgument(String),
+    MachineError(MachineError),
+    UnknownError(String),
+    VerifyConfigError(VerifyConfigError),
+    EofPolicyError(EofPolicyError),
+    FramingError(FramingError),
+    PipelineError(PipelineError),
+    RateLimitError(RateLimitError),
+    RegionError(RegionError),
+    SnapshotError(SnapshotError),
+    SuperoptError(SuperoptError),
+    TraceError(TraceError),
+    TortureFailed(usize),
+    Unverified(usize),
+}
+
+impl CommandError {
+    /* the program error behind this one, if it is one */
+    pub fn dreamer_error(&self) -> Option<DreamerError> {
+        match self {
+            Self::CodeError(e) => Some((*e).into()),
+            Self::MachineError(e) => Some((*e).into()),
+            _ => None,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.dreamer_error().map_or(1, |t| t.code())
+    }
+}
+
+impl From<SpecError> for CommandError {
+    fn from(value: SpecError) -> Self {
+        Self::SpecError(value)
+    }
+}
+
+impl From<InspectError> for CommandError {
+    fn from(value: InspectError) -> Self {
+        Self::InspectError(value)
+    }
+}
+
+impl From<RateLimitError> for CommandError {
+    fn from(value: RateLimitError) -> Self {
+        Self::RateLimitError(value)
+    }
+}
+
+impl From<SnapshotError> for CommandError {
+    fn from(value: SnapshotError) -> Self {
+        Self::SnapshotError(value)
+    }
+}
+
+impl From<DumpError> for CommandError {
+    fn from(value: DumpError) -> Self {
+        Self::DumpError(value)
+    }
+}
+
+impl From<LimitsError> for CommandError {
+    fn from(value: LimitsError) -> Self {
+        Self::LimitsError(value)
+    }
+}
+
+impl From<OpcodeSetError> for CommandError {
+    fn from(value: OpcodeSetError) -> Self {
+        Self::OpcodeSetError(value)
+    }
+}
+
+impl From<AsmError> for CommandError {
+    fn from(value: AsmError) -> Self {
+        Self::AsmError(value)
+    }
+}
+
+impl From<CacheConfigError> for CommandError {
+    fn from(value: CacheConfigError) -> Self {
+        Self::CacheError(value)
+    }
+}
+
+impl From<VerifyConfigError> for CommandError {
+    fn from(value: VerifyConfigError) -> Self {
+        Self::VerifyConfigError(value)
+    }
+}
+
+impl From<EofPolicyError> for CommandError {
+    fn from(value: EofPolicyError) -> Self {
+        Self::EofPolicyError(value)
+    }
+}
+
+impl From<FramingError> for CommandError {
+    fn from(value: FramingError) -> Self {
+        Self::FramingError(value)
+    }
+}
+
+impl From<RegionError> for CommandError {
+    fn from(value: RegionError) -> Self {
+        Self::RegionError(value)
+    }
 }
 
 impl From<CodeParseError> for CommandError {
@@ -23,54 +225,1628 @@ impl From<CodeParseError> for CommandError {
     }
 }
 
+impl From<CodeReadError> for CommandError {
+    fn from(value: CodeReadError) -> Self {
+        match value {
+            CodeReadError::IOError(e) => Self::IOError(e),
+            CodeReadError::ParseError(e) => Self::CodeError(e),
+        }
+    }
+}
+
+impl From<ConfigError> for CommandError {
+    fn from(value: ConfigError) -> Self {
+        Self::ConfigError(value)
+    }
+}
+
+impl From<CostModelError> for CommandError {
+    fn from(value: CostModelError) -> Self {
+        Self::CostModelError(value)
+    }
+}
+
+impl From<FilterParseError> for CommandError {
+    fn from(value: FilterParseError) -> Self {
+        Self::FilterError(value)
+    }
+}
+
+impl From<InputSpecError> for CommandError {
+    fn from(value: InputSpecError) -> Self {
+        Self::InputError(value)
+    }
+}
+
 impl From<io::Error> for CommandError {
     fn from(value: io::Error) -> Self {
         Self::IOError(value)
     }
 }
 
-pub fn run<P: AsRef<Path>>(
-    program_path: P,
-    trace: bool,
-    output: Option<P>,
-) -> Result<(), CommandError> {
-    let mut outfile: Box<dyn Write> = match output {
+impl From<MachineError> for CommandError {
+    fn from(value: MachineError) -> Self {
+        Self::MachineError(value)
+    }
+}
+
+impl From<PipelineError> for CommandError {
+    fn from(value: PipelineError) -> Self {
+        Self::PipelineError(value)
+    }
+}
+
+impl From<SuperoptError> for CommandError {
+    fn from(value: SuperoptError) -> Self {
+        Self::SuperoptError(value)
+    }
+}
+
+impl From<TraceError> for CommandError {
+    fn from(value: TraceError) -> Self {
+        Self::TraceError(value)
+    }
+}
+
+/* prints a run's lifecycle events to stderr, one JSON object per line */
+struct LifecyclePrinter;
+
+impl Observer for LifecyclePrinter {
+    fn notify(&mut self, event: &Lifecycle) {
+        if let Ok(t) = serde_json::to_string(event) {
+            eprintln!("{}", t);
+        }
+    }
+}
+
+/* optional per-step observers attached to a run */
+#[derive(Default)]
+struct Instruments {
+    steps: u64,
+    tracing: bool,
+    trace_paused: bool,
+    filter: Option<Filter>,
+    trace_writer: Option<BackgroundTraceWriter>,
+    profile: Option<Profile>,
+    progress: Option<Progress>,
+    inspect: Option<InspectServer>,
+    throttle: Option<Throttle>,
+    cost_model: Option<CostModel>,
+    cycles: u64,
+    energy_table: Option<EnergyTable>,
+    energy: u64,
+    cache: Option<Cache>,
+    pipeline: Option<Pipeline>,
+    pipeline_timeline: bool,
+    check_invariants: bool,
+    code_len: Option<usize>,
+    invariant_failure: Option<InvariantFailure>,
+    history: Option<History>,
+    provenance: Option<Provenance>,
+    watches: Vec<Watch>,
+}
+
+impl Instruments {
+    fn active(&self) -> bool {
+        self.tracing
+            || self.trace_writer.is_some()
+            || self.profile.is_some()
+            || self.progress.is_some()
+            || self.inspect.is_some()
+            || self.throttle.is_some()
+            || self.cost_model.is_some()
+            || self.energy_table.is_some()
+            || self.cache.is_some()
+            || self.pipeline.is_some()
+            || self.check_invariants
+            || self.history.is_some()
+            || self.provenance.is_some()
+            || !self.watches.is_empty()
+    }
+
+    fn observe(
+        &mut self,
+        old: &State,
+        instruction: Instruction,
+        new: &State,
+    ) -> Result<(), MachineError> {
+        if self.check_invariants {
+            if let Err(violation) =
+                invariant::check(old, instruction, new, self.code_len)
+            {
+                self.invariant_failure = Some(InvariantFailure {
+                    violation,
+                    step: self.steps,
+                    instruction,
+                    before: old.clone(),
+                    after: new.clone(),
+                });
+                return Err(MachineError::InvariantViolation);
+            }
+        }
+
+        /* TraceOn and TraceOff bracket the steps worth tracing */
+        let marker: bool = match instruction {
+            Instruction::TraceOn => {
+                self.trace_paused = false;
+                true
+            }
+            Instruction::TraceOff => {
+                self.trace_paused = true;
+                true
+            }
+            _ => false,
+        };
+
+        let record: TraceRecord =
+            TraceRecord::new(self.steps, old, instruction, new);
+        let selected: bool = !self.trace_paused
+            && !marker
+            && match &self.filter {
+                Some(t) => t.matches(&record),
+                None => true,
+            };
+
+        if self.tracing && selected {
+            clbk(new, instruction);
+        }
+
+        if let (Some(t), true) = (&mut self.trace_writer, selected) {
+            t.write(&record);
+        }
+
+        if let Some(t) = &mut self.history {
+            t.record(record);
+        }
+
+        if let Some(t) = &mut self.provenance {
+            t.record(old, instruction, new);
+        }
+
+        for t in &mut self.watches {
+            if t.update(new) {
+                eprintln!(
+                    "watch step {} pc {}: {}",
+                    self.steps,
+                    old.pc,
+                    t.describe()
+                );
+            }
+        }
+
+        self.steps += 1;
+
+        if let Some(t) = &mut self.profile {
+            t.record(old.pc);
+        }
+
+        if let Some(t) = &mut self.progress {
+            t.record();
+        }
+
+        if let Some(t) = &mut self.inspect {
+            t.poll(new, self.steps);
+        }
+
+        if let Some(t) = &mut self.throttle {
+            t.record();
+        }
+
+        if let Some(t) = &self.cost_model {
+            self.cycles += t.cost(old, instruction, new);
+        }
+
+        if let Some(t) = &self.energy_table {
+            self.energy += t.cost(instruction);
+        }
+
+        if let Some(t) = &mut self.cache {
+            if instruction.touches_memory() {
+                if let Some(address) = old.stack.peek() {
+                    t.access(address);
+                }
+            }
+        }
+
+        if let Some(t) = &mut self.pipeline {
+            t.issue(old, instruction, new);
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), TraceError> {
+        if let Some(t) = &mut self.progress {
+            t.finish();
+        }
+
+        if let Some(t) = self.trace_writer.take() {
+            if t.dropped() > 0 {
+                eprintln!(
+                    "warning: dropped {} trace records (sink too slow)",
+                    t.dropped()
+                );
+            }
+
+            t.finish()?;
+        }
+
+        Ok(())
+    }
+
+    fn report(&self, code: &dyn Program) {
+        if let Some(t) = &self.invariant_failure {
+            eprint!("{}", t.render());
+        }
+
+        if let Some(t) = &self.profile {
+            eprint!("{}", t.report(code));
+        }
+
+        if self.cost_model.is_some() {
+            eprintln!("cycles: {}", self.cycles);
+        }
+
+        if let Some(t) = &self.energy_table {
+            match &t.unit {
+                Some(unit) => eprintln!("energy: {} {}", self.energy, unit),
+                None => eprintln!("energy: {}", self.energy),
+            }
+        }
+
+        if let Some(t) = &self.cache {
+            eprintln!("{}", t.report());
+        }
+
+        if let Some(t) = &self.throttle {
+            eprintln!("{}", t.report());
+        }
+
+        if let Some(t) = &self.pipeline {
+            if self.pipeline_timeline {
+                eprint!("{}", t.timeline());
+            }
+
+            eprintln!("{}", t.report());
+        }
+    }
+}
+
+pub fn run(opts: RunOpts) -> Result<(), CommandError> {
+    let initial: State = initial_state(&opts)?;
+
+    if opts.dry_run {
+        return dry_run(&opts, &initial);
+    }
+
+    let mut outfile: Box<dyn Write> = match &opts.output {
         Some(t) => match File::create(t) {
             Ok(f) => Box::new(f) as Box<dyn Write>,
             Err(e) => return Err(e.into()),
         },
+        /* standard output belongs to the program when it is a filter */
+        None if opts.stdio && opts.io_out.is_none() => {
+            Box::new(io::sink()) as Box<dyn Write>
+        }
         None => Box::new(io::stdout()) as Box<dyn Write>,
     };
 
-    let file_contents: Vec<u8> = match fs::read(program_path) {
+    let framing: Framing = match &opts.io_format {
+        Some(t) => Framing::try_from(t.as_str())?,
+        None => Framing::default(),
+    };
+    let eof_policy: EofPolicy = match &opts.eof {
+        Some(t) => EofPolicy::try_from(t.as_str())?,
+        None => EofPolicy::default(),
+    };
+    let device: Option<Box<dyn Device>> = match (opts.stdio, &opts.events) {
+        (true, _) => Some(io_device(&opts, framing, eof_policy)?),
+        (false, Some(t)) => {
+            Some(Box::new(EventLog::new(BufWriter::new(File::create(t)?))))
+        }
+        (false, None) => None,
+    };
+
+    let limits: Limits = match (&opts.limits, &opts.limits_config) {
+        (Some(name), Some(t)) => {
+            LimitPresets::try_from(&read_config(t)?)?.get(name)?
+        }
+        (Some(name), None) => LimitPresets::default().get(name)?,
+        (None, _) => Limits::default(),
+    };
+    let denied: OpcodeSet = match &opts.deny_ops {
+        Some(t) => OpcodeSet::try_from(t.as_str())?,
+        None => OpcodeSet::default(),
+    };
+    /* limits given on their own take precedence over the preset's */
+    let step_limit: Option<u64> = opts
+        .max_steps
+        .or(limits.steps)
+        .or(opts.teach.then_some(TEACH_STEP_LIMIT));
+
+    let mut instruments: Instruments = Instruments {
+        filter: match &opts.trace_filter {
+            Some(t) => Some(Filter::try_from(t.as_str())?),
+            None => None,
+        },
+        profile: opts.profile_sample.map(Profile::new),
+        progress: opts.progress.then(|| Progress::new(step_limit)),
+        inspect: match &opts.inspect_socket {
+            Some(t) => Some(InspectServer::bind(t)?),
+            None => None,
+        },
+        throttle: match &opts.rate_limit {
+            Some(t) => Some(Throttle::new(RateLimit::try_from(t.as_str())?)),
+            None => None,
+        },
+        cost_model: match &opts.cost_model {
+            Some(t) => Some(CostModel::try_from(&read_config(t)?)?),
+            None => None,
+        },
+        energy_table: match &opts.energy_table {
+            Some(t) => Some(EnergyTable::try_from(&read_config(t)?)?),
+            None => None,
+        },
+        cache: match &opts.cache {
+            Some(t) => Some(Cache::new(CacheConfig::try_from(t.as_str())?)?),
+            None => None,
+        },
+        pipeline: match (opts.pipeline, opts.pipeline_timeline) {
+            (Some(t), false) => Some(Pipeline::new(t)?),
+            (Some(t), true) => Some(Pipeline::new(t)?.with_timeline()),
+            (None, true) => {
+                Some(Pipeline::new(DEFAULT_PIPELINE_STAGES)?.with_timeline())
+            }
+            (None, false) => None,
+        },
+        pipeline_timeline: opts.pipeline_timeline,
+        trace_paused: opts.trace_from_marker,
+        check_invariants: opts.check_invariants,
+        history: opts.crash_dump.as_ref().map(|_| {
+            History::new(opts.crash_history.unwrap_or(DEFAULT_DUMP_HISTORY))
+        }),
+        provenance: opts.teach.then(|| Provenance::new(&initial)),
+        watches: opts
+            .watch_expr
+            .iter()
+            .map(|t| Watch::new(t, &initial))
+            .collect::<Result<Vec<Watch>, ExprParseError>>()?,
+        ..Default::default()
+    };
+    for t in &instruments.watches {
+        eprintln!("watch initial: {}", t.describe());
+    }
+
+    instruments.tracing = opts.trace
+        || (instruments.filter.is_some()
+            && opts.trace_file.is_none()
+            && opts.trace_sink.is_none());
+    let trace_format: TraceFormat =
+        TraceFormat::try_from(opts.trace_format.as_str())?;
+    instruments.trace_writer = match (&opts.trace_file, &opts.trace_rotate) {
+        (Some(t), Some(rotation)) => Some(BackgroundTraceWriter::new(
+            TraceWriter::rotating(
+                t,
+                trace_format,
+                Rotation::try_from(rotation.as_str())?,
+            )?,
+            OverflowPolicy::try_from(opts.trace_overflow.as_str())?,
+        )),
+        (Some(t), None) => Some(BackgroundTraceWriter::new(
+            TraceWriter::new(
+                Box::new(BufWriter::new(File::create(t)?)),
+                trace_format,
+            )?,
+            OverflowPolicy::try_from(opts.trace_overflow.as_str())?,
+        )),
+        (None, _) => match &opts.trace_sink {
+            Some(t) => Some(BackgroundTraceWriter::new(
+                TraceWriter::new(
+                    TraceSink::try_from(t.as_str())?.open()?,
+                    trace_format,
+                )?,
+                OverflowPolicy::try_from(opts.trace_overflow.as_str())?,
+            )),
+            None => None,
+        },
+    };
+
+    let regions: Option<Regions> = match &opts.regions {
+        Some(t) => Some(Regions::try_from(&read_config(t)?)?),
+        None => None,
+    };
+
+    /* trace buffers are reserved out of the ceiling before the run starts */
+    let memory_limit: Option<usize> =
+        opts.memory_limit.or(limits.memory).map(|t| {
+            match instruments.trace_writer {
+                Some(_) => t.saturating_sub(TRACE_BUFFER_BYTES),
+                None => t,
+            }
+        });
+
+    let setup: RunSetup = RunSetup {
+        initial,
+        limits: Limits {
+            steps: step_limit,
+            memory: memory_limit,
+            stack: limits.stack,
+        },
+        run_limits: Limits {
+            steps: step_limit,
+            memory: opts.memory_limit.or(limits.memory),
+            stack: limits.stack,
+        },
+        denied,
+        device,
+        regions,
+    };
+
+    let (result, prog, manifest): RunOutcome = if opts.lazy {
+        let bytes: Vec<u8> = read_bytes(&opts.path)?;
+        let program_hash: u64 = fnv1a(&bytes);
+
+        execute(
+            Machine::new(LazyCode::new(bytes)),
+            program_hash,
+            setup,
+            &opts,
+            &mut instruments,
+        )?
+    } else {
+        let mode: ParseMode = if opts.lenient {
+            ParseMode::Lenient
+        } else {
+            ParseMode::Strict
+        };
+        let (code, tail_error): (Code, Option<CodeParseError>) =
+            read_code(&opts.path, mode)?;
+
+        if let Some(e) = tail_error {
+            eprintln!(
+                "warning: ignoring undecodable bytes from offset {} ({:?})",
+                e.position(),
+                e.error()
+            );
+        }
+
+        instruments.code_len = Some(code.0.len());
+        let program_hash: u64 = fnv1a(&code.to_bytes());
+
+        execute(
+            Machine::new(code),
+            program_hash,
+            setup,
+            &opts,
+            &mut instruments,
+        )?
+    };
+
+    match &result {
+        Ok(t) if instruments.tracing => write!(outfile, "{:?}", t)?,
+        Ok(t) => write!(outfile, "{}", t)?,
+        Err(_) => {}
+    };
+
+    /* a faulting run leaves the state file as it was */
+    if let (Ok(t), Some(path)) = (&result, &opts.state_file) {
+        save_state_file(path, t)?;
+    }
+
+    if let (Some(t), Some(path)) = (&manifest, &opts.manifest) {
+        fs::write(
+            path,
+            serde_json::to_string_pretty(t)
+                .map_err(|e| CommandError::IOError(e.into()))?,
+        )?;
+    }
+
+    instruments.finish()?;
+    instruments.report(prog.as_ref());
+
+    result?;
+    Ok(())
+}
+
+/*
+ * Runs each program in turn from a fresh stack and register, handing the
+ * memory one leaves behind to the next, or only the cells within the
+ * `--carry` region. Limits apply to each stage separately, and a trace
+ * file records every stage, with step numbers counting on across them.
+ */
+pub fn pipeline(opts: PipelineOpts) -> Result<(), CommandError> {
+    let carry: Option<Region> = match (&opts.regions, &opts.carry) {
+        (Some(path), Some(name)) => Some(
+            Regions::try_from(&read_config(path)?)?
+                .named(name)
+                .cloned()
+                .ok_or_else(|| {
+                    CommandError::InvalidArgument(format!(
+                        "no region named {}",
+                        name
+                    ))
+                })?,
+        ),
+        _ => None,
+    };
+
+    let mut instruments: Instruments = Instruments {
+        trace_writer: match &opts.trace_file {
+            Some(t) => Some(BackgroundTraceWriter::new(
+                TraceWriter::new(
+                    Box::new(BufWriter::new(File::create(t)?)),
+                    TraceFormat::try_from(opts.trace_format.as_str())?,
+                )?,
+                OverflowPolicy::Block,
+            )),
+            None => None,
+        },
+        ..Default::default()
+    };
+    let mut memory: Memory = Memory::default();
+    let mut result: Result<State, CommandError> = Ok(State::default());
+
+    for (i, path) in opts.paths.iter().enumerate() {
+        let (code, _): (Code, _) = read_code(path, ParseMode::Strict)?;
+        let mut machine: Machine = Machine::new(code)
+            .with_state(State {
+                memory,
+                ..State::default()
+            })
+            .with_interrupt(&INTERRUPTED);
+        machine = machine.with_limits(Limits {
+            steps: opts.max_steps,
+            memory: opts.memory_limit,
+            stack: None,
+        });
+
+        result =
+            run_machine(&mut machine, &mut instruments).map_err(Into::into);
+
+        if result.is_err() {
+            eprintln!("stage {} ({}) faulted", i + 1, path.display());
+            break;
+        }
+
+        memory = match &carry {
+            Some(region) => {
+                let mut carried: Memory = Memory::default();
+
+                for (address, data) in machine.state.memory.cells() {
+                    if region.contains(address) {
+                        carried.write(address, data);
+                    }
+                }
+
+                carried
+            }
+            None => machine.state.memory,
+        };
+    }
+
+    instruments.finish()?;
+
+    println!("{}", result?);
+    Ok(())
+}
+
+/* decodes the program and reports on it without executing anything */
+fn dry_run(opts: &RunOpts, initial: &State) -> Result<(), CommandError> {
+    let mode: ParseMode = if opts.lenient {
+        ParseMode::Lenient
+    } else {
+        ParseMode::Strict
+    };
+    let (code, tail_error): (Code, Option<CodeParseError>) =
+        read_code(&opts.path, mode)?;
+    let model: Option<CostModel> = match &opts.cost_model {
+        Some(t) => Some(CostModel::try_from(&read_config(t)?)?),
+        None => None,
+    };
+
+    if let Some(e) = tail_error {
+        println!(
+            "undecodable bytes from offset {} ({:?})",
+            e.position(),
+            e.error()
+        );
+    }
+
+    print!("{}", ProgramStats::new(&code.0, model.as_ref()).render());
+    println!("initial state: {}", initial);
+    Ok(())
+}
+
+/*
+ * How `run` sets up its machine, which is the same whether the program is
+ * decoded up front or on demand
+ */
+struct RunSetup {
+    initial: State,
+    /* the machine's own, with trace buffers taken out of the memory limit */
+    limits: Limits,
+    /* as asked for, for the manifest */
+    run_limits: Limits,
+    denied: OpcodeSet,
+    device: Option<Box<dyn Device>>,
+    regions: Option<Regions>,
+}
+
+/* the result of a run, the program it ran and its manifest, if asked for */
+type RunOutcome = (
+    Result<State, CommandError>,
+    Box<dyn Program>,
+    Option<Manifest>,
+);
+
+/* runs `machine` as set up by `run`, and reports on it once it stops */
+fn execute<P: Program + 'static>(
+    machine: Machine<P>,
+    program_hash: u64,
+    setup: RunSetup,
+    opts: &RunOpts,
+    instruments: &mut Instruments,
+) -> Result<RunOutcome, CommandError> {
+    let initial_hash: u64 = setup.initial.digest();
+    let mut machine: Machine<P> = machine
+        .with_state(setup.initial)
+        .with_interrupt(&INTERRUPTED)
+        .with_limits(setup.limits)
+        .with_denied(setup.denied);
+
+    if let Some(t) = setup.device {
+        machine = machine.with_device(t);
+    }
+
+    if opts.lifecycle {
+        machine = machine.with_observer(Box::new(LifecyclePrinter));
+    }
+
+    let result: Result<State, MachineError> =
+        run_machine(&mut machine, instruments);
+    snapshot_if_interrupted(
+        &result,
+        &machine.state,
+        opts.snapshot_on_interrupt.as_deref(),
+    )?;
+    report_fault(&result, &mut machine, setup.regions.as_ref());
+    teach_if_faulted(&result, &machine, instruments);
+    dump_if_faulted(
+        &result,
+        &machine.state,
+        instruments,
+        opts.crash_dump.as_deref(),
+        (&opts.path, program_hash),
+    )?;
+    let manifest: Option<Manifest> = match opts.manifest {
+        Some(_) => Some(manifest(
+            opts,
+            setup.run_limits,
+            (program_hash, initial_hash),
+            &result,
+            &machine,
+            instruments,
+        )?),
+        None => None,
+    };
+
+    /* surface decoding failures as such, with their offset */
+    let result: Result<State, CommandError> =
+        match (result, machine.prog.last_error()) {
+            (Err(MachineError::UndecodableInstruction), Some(e)) => {
+                Err(e.into())
+            }
+            (result, _) => result.map_err(Into::into),
+        };
+
+    Ok((result, Box::new(machine.prog), manifest))
+}
+
+/* saves the state an interrupted run stopped in, if asked to */
+fn snapshot_if_interrupted(
+    result: &Result<State, MachineError>,
+    state: &State,
+    path: Option<&Path>,
+) -> Result<(), CommandError> {
+    if let (Err(MachineError::Interrupted), Some(t)) = (result, path) {
+        write_snapshot(BufWriter::new(File::create(t)?), state)?;
+        eprintln!("interrupted; state saved to {}", t.display());
+    }
+
+    Ok(())
+}
+
+/* describes a finished run for --manifest */
+fn manifest<P: Program>(
+    opts: &RunOpts,
+    limits: Limits,
+    (program_hash, initial_hash): (u64, u64),
+    result: &Result<State, MachineError>,
+    machine: &Machine<P>,
+    instruments: &Instruments,
+) -> Result<Manifest, CommandError> {
+    /* only regular files can be read again without consuming the input */
+    let input_hash: Option<String> = match &opts.io_in {
+        Some(t) if fs::metadata(t)?.is_file() => {
+            Some(format!("{:016x}", fnv1a(&fs::read(t)?)))
+        }
+        _ => None,
+    };
+
+    Ok(Manifest {
+        version: MANIFEST_VERSION,
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        isa_version: ISA_VERSION,
+        program: opts.path.display().to_string(),
+        program_hash: format!("{:016x}", program_hash),
+        engine: if opts.lazy { "lazy" } else { "eager" }.to_string(),
+        limits,
+        denied: opts.deny_ops.clone(),
+        initial_state_hash: format!("{:016x}", initial_hash),
+        input_hash,
+        fault: result.as_ref().err().copied(),
+        final_state_hash: format!("{:016x}", machine.state.digest()),
+        steps: machine.steps(),
+        cycles: instruments.cost_model.as_ref().map(|_| instruments.cycles),
+        energy: instruments
+            .energy_table
+            .as_ref()
+            .map(|_| instruments.energy),
+    })
+}
+
+/* writes a crash dump into `dir` if the run faulted */
+fn dump_if_faulted(
+    result: &Result<State, MachineError>,
+    state: &State,
+    instruments: &Instruments,
+    dir: Option<&Path>,
+    (program, program_hash): (&Path, u64),
+) -> Result<(), CommandError> {
+    let (fault, dir): (MachineError, &Path) = match (result, dir) {
+        (Err(MachineError::Interrupted), _) => return Ok(()),
+        (Err(e), Some(t)) => (*e, t),
+        _ => return Ok(()),
+    };
+    let time: u64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_secs())
+        .unwrap_or_default();
+    let dump: CrashDump = CrashDump {
+        version: DUMP_VERSION,
+        program: program.display().to_string(),
+        program_hash: format!("{:016x}", program_hash),
+        time,
+        fault,
+        steps: instruments.steps,
+        state: state.clone(),
+        history: instruments
+            .history
+            .as_ref()
+            .map(History::records)
+            .unwrap_or_default(),
+    };
+    let path: PathBuf =
+        dir.join(format!("crash-{}-{}.json", time, process::id()));
+
+    fs::write(
+        &path,
+        serde_json::to_string_pretty(&dump)
+            .map_err(|e| CommandError::IOError(e.into()))?,
+    )?;
+    eprintln!("crash dump written to {}", path.display());
+    Ok(())
+}
+
+/*
+ * Reports where a run faulted and, for instructions that address memory,
+ * the address involved, relative to its named region if there is one
+ */
+fn report_fault<P: Program>(
+    result: &Result<State, MachineError>,
+    machine: &mut Machine<P>,
+    regions: Option<&Regions>,
+) {
+    let (error, regions): (&MachineError, &Regions) = match (result, regions) {
+        (Err(e), Some(t)) => (e, t),
+        _ => return,
+    };
+    let pc: Word = machine.state.pc;
+    let instruction: Option<Instruction> = word_to_usize(pc)
+        .and_then(|t| machine.prog.fetch(t).ok())
+        .flatten();
+
+    match (instruction, machine.state.stack.peek()) {
+        (Some(t), Some(address)) if t.touches_memory() => eprintln!(
+            "fault: {:?} at pc {} ({:?} of {})",
+            error,
+            pc,
+            t,
+            regions.symbolise(address)
+        ),
+        (Some(t), _) => eprintln!("fault: {:?} at pc {} ({:?})", error, pc, t),
+        (None, _) => eprintln!("fault: {:?} at pc {}", error, pc),
+    }
+}
+
+/* prints guidance on a fault for runs under --teach */
+fn teach_if_faulted<P: Program>(
+    result: &Result<State, MachineError>,
+    machine: &Machine<P>,
+    instruments: &Instruments,
+) {
+    if let (Err(e), Some(t)) = (result, &instruments.provenance) {
+        if *e != MachineError::Interrupted {
+            eprint!("\n{}", teach::hint(*e, &machine.state, t, &machine.prog));
+        }
+    }
+}
+
+/* Read and Write over standard input and output, or files in their place */
+fn io_device(
+    opts: &RunOpts,
+    framing: Framing,
+    eof_policy: EofPolicy,
+) -> Result<Box<dyn Device>, CommandError> {
+    let reader: Box<dyn PollRead> = match &opts.io_in {
+        Some(t) => Box::new(io::BufReader::new(File::open(t)?)),
+        None => Box::new(StdinReader::new()),
+    };
+    let writer: Box<dyn Write> = match &opts.io_out {
+        Some(t) => Box::new(BufWriter::new(File::create(t)?)),
+        None => Box::new(io::stdout()),
+    };
+
+    Ok(Box::new(
+        StreamDevice::new(reader, writer)
+            .with_framing(framing)
+            .with_eof_policy(eof_policy),
+    ))
+}
+
+fn initial_state(opts: &RunOpts) -> Result<State, CommandError> {
+    let mut state: State = match &opts.state_file {
+        Some(t) => load_state_file(t, opts.state_stack)?,
+        None => State::default(),
+    };
+
+    if let Some(t) = &opts.init_reg {
+        state.reg = parse_word_arg(t)?;
+    }
+
+    if let Some(t) = &opts.init_stack {
+        state.stack = Stack::try_from(parse_words_arg(t)?).map_err(|_| {
+            CommandError::InvalidArgument("initial stack too deep".to_string())
+        })?;
+    }
+
+    Ok(state)
+}
+
+/*
+ * The state carried over from the previous run against a state file: its
+ * memory, and its register and stack too if asked for. Every run starts
+ * from the first instruction. A missing file is an empty state.
+ */
+fn load_state_file(path: &Path, stack: bool) -> Result<State, CommandError> {
+    let file: File = match File::open(path) {
         Ok(t) => t,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(State::default())
+        }
         Err(e) => return Err(e.into()),
     };
-    let code: Code = match Code::try_from(file_contents) {
+    let saved: State = match read_snapshot(file) {
         Ok(t) => t,
+        Err(SnapshotError::Malformed) => {
+            return Err(CommandError::InvalidArgument(format!(
+                "malformed state file {}",
+                path.display()
+            )))
+        }
         Err(e) => return Err(e.into()),
     };
 
-    let mut machine: Machine = Machine::new(code);
+    Ok(match stack {
+        true => State {
+            pc: 0,
+            wrap: false,
+            ..saved
+        },
+        false => State {
+            memory: saved.memory,
+            ..State::default()
+        },
+    })
+}
 
-    if trace {
-        /* print initial machine state */
-        println!("{:?}", machine.state.clone());
+/* replaces the state file whole, so an interrupted save loses nothing */
+fn save_state_file(path: &Path, state: &State) -> Result<(), CommandError> {
+    let mut staging: PathBuf = path.to_path_buf();
+    staging.as_mut_os_string().push(".tmp");
+    write_snapshot(BufWriter::new(File::create(&staging)?), state)?;
+    fs::rename(&staging, path)?;
+    Ok(())
+}
 
-        match machine.run_callback(&clbk) {
-            Ok(t) => write!(outfile, "{:?}", t)?,
-            Err(e) => eprintln!("{:?}", e),
-        };
+fn parse_word_arg(arg: &str) -> Result<Word, CommandError> {
+    parse_word(arg)
+        .ok_or_else(|| CommandError::InvalidArgument(arg.to_string()))
+}
+
+/* parses a comma-separated list of words, e.g. `1,2,0x3` */
+fn parse_words_arg(arg: &str) -> Result<Vec<Word>, CommandError> {
+    if arg.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    arg.split(',').map(parse_word_arg).collect()
+}
+
+/* parses a half-open range of words, e.g. `0..100` */
+fn parse_range_arg(arg: &str) -> Result<Range<Word>, CommandError> {
+    match arg.split_once("..") {
+        Some((lo, hi)) => Ok(parse_word_arg(lo)?..parse_word_arg(hi)?),
+        None => Err(CommandError::InvalidArgument(arg.to_string())),
+    }
+}
+
+fn run_machine<P: Program>(
+    machine: &mut Machine<P>,
+    instruments: &mut Instruments,
+) -> Result<State, MachineError> {
+    if instruments.active() {
+        if instruments.tracing {
+            /* print initial machine state */
+            println!("{:?}", machine.state.clone());
+        }
+
+        machine.run_callback(&mut |old, instruction, new| {
+            instruments.observe(old, instruction, new)
+        })
     } else {
-        match machine.run() {
-            Ok(t) => write!(outfile, "{}", t)?,
-            Err(e) => eprintln!("{:?}", e),
+        machine.run()
+    }
+}
+
+/* the step budget of each run when a sweep is given no --max-steps */
+const DEFAULT_SWEEP_STEP_LIMIT: u64 = 1_000_000;
+
+/* inputs handed to the workers at a time, printed before the next begins */
+const SWEEP_CHUNK: usize = 4096;
+
+pub fn sweep(opts: SweepOpts) -> Result<(), CommandError> {
+    let mut inputs: Range<Word> = parse_range_arg(&opts.init_reg)?;
+    let stack: Stack = match &opts.init_stack {
+        Some(t) => Stack::try_from(parse_words_arg(t)?).map_err(|_| {
+            CommandError::InvalidArgument("initial stack too deep".to_string())
+        })?,
+        None => Stack::default(),
+    };
+    let (code, _): (Code, _) = read_code(&opts.path, ParseMode::Strict)?;
+    let step_limit: u64 = opts.max_steps.unwrap_or(DEFAULT_SWEEP_STEP_LIMIT);
+    let jobs: usize = opts.jobs.unwrap_or_else(default_jobs);
+
+    let mut runs: u64 = 0;
+    let mut faulted: u64 = 0;
+    let mut digests: HashSet<u64> = HashSet::new();
+
+    println!(
+        "{:>20}  {:>20}  {:>20}  {:>18}",
+        "init_reg", "reg", "top", "digest"
+    );
+
+    /* the range is consumed a chunk at a time, however large it is */
+    loop {
+        let chunk: Vec<Word> = inputs.by_ref().take(SWEEP_CHUNK).collect();
+
+        if chunk.is_empty() {
+            break;
+        }
+
+        let results: Vec<Result<State, MachineError>> =
+            parallel_map(&chunk, jobs, |reg| {
+                /* every run gets its own machine, and its own step budget */
+                Machine::new(code.clone())
+                    .with_reg(*reg)
+                    .with_stack(stack.clone())
+                    .with_step_limit(step_limit)
+                    .run()
+            });
+
+        for (input, result) in chunk.iter().zip(&results) {
+            runs += 1;
+
+            match result {
+                Ok(t) => {
+                    digests.insert(t.digest());
+                    println!(
+                        "{:>20}  {:>20}  {:>20}  {:#018x}",
+                        input,
+                        t.reg,
+                        t.stack
+                            .peek()
+                            .map_or("-".to_string(), |top| top.to_string()),
+                        t.digest()
+                    );
+                }
+                Err(e) => {
+                    faulted += 1;
+                    println!("{:>20}  fault: {:?}", input, e);
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} runs, {} faulted, {} distinct final states",
+        runs,
+        faulted,
+        digests.len()
+    );
+
+    Ok(())
+}
+
+/* the step budget of each run when a batch is given no --max-steps */
+const DEFAULT_BATCH_STEP_LIMIT: u64 = 1_000_000;
+
+pub fn batch(opts: BatchOpts) -> Result<(), CommandError> {
+    let step_limit: u64 = opts.max_steps.unwrap_or(DEFAULT_BATCH_STEP_LIMIT);
+    let jobs: usize = opts.jobs.unwrap_or_else(default_jobs);
+
+    /*
+     * Every program is read, decoded and run by its own task, so one that
+     * cannot be read fails alone rather than the whole batch
+     */
+    let results: Vec<Result<State, CommandError>> =
+        parallel_map(&opts.paths, jobs, |path| {
+            let (code, _): (Code, _) = read_code(path, ParseMode::Strict)?;
+            Ok(Machine::new(code).with_step_limit(step_limit).run()?)
+        });
+
+    let mut faulted: u64 = 0;
+    let mut unloaded: u64 = 0;
+    let mut digests: HashSet<u64> = HashSet::new();
+
+    println!("{:>20}  {:>20}  {:>18}  path", "reg", "top", "digest");
+
+    for (path, result) in opts.paths.iter().zip(&results) {
+        let outcome: String = match result {
+            Ok(t) => {
+                digests.insert(t.digest());
+                format!(
+                    "{:>20}  {:>20}  {:#018x}",
+                    t.reg,
+                    t.stack
+                        .peek()
+                        .map_or("-".to_string(), |top| top.to_string()),
+                    t.digest()
+                )
+            }
+            Err(CommandError::MachineError(e)) => {
+                faulted += 1;
+                format!("{:<62}", format!("fault: {:?}", e))
+            }
+            Err(e) => {
+                unloaded += 1;
+                format!("{:<62}", format!("error: {:?}", e))
+            }
         };
+
+        println!("{}  {}", outcome, path.display());
     }
 
+    println!(
+        "{} runs, {} faulted, {} not loaded, {} distinct final states",
+        results.len(),
+        faulted,
+        unloaded,
+        digests.len()
+    );
+
     Ok(())
 }
 
-fn clbk(state: State, instruction: Instruction) {
+pub fn equiv(opts: EquivOpts) -> Result<(), CommandError> {
+    let spec: InputSpec = InputSpec::try_from(opts.inputs.as_str())?;
+    let inputs: Vec<Input> = spec.inputs();
+    let (left, _): (Code, _) = read_code(&opts.left, ParseMode::Strict)?;
+    let (right, _): (Code, _) = read_code(&opts.right, ParseMode::Strict)?;
+
+    let differences: Vec<Difference> = compare(
+        &left,
+        &right,
+        &inputs,
+        opts.step_limit.unwrap_or(DEFAULT_EQUIV_STEP_LIMIT),
+    );
+
+    for difference in &differences {
+        println!(
+            "reg={} stack={:?}\n  left:  {:?}\n  right: {:?}",
+            difference.input.reg,
+            difference.input.stack,
+            difference.left,
+            difference.right
+        );
+    }
+
+    println!("{} inputs, {} differences", inputs.len(), differences.len());
+
+    match differences.len() {
+        0 => Ok(()),
+        n => Err(CommandError::Inequivalent(n)),
+    }
+}
+
+pub fn superopt(opts: SuperoptOpts) -> Result<(), CommandError> {
+    let inputs: Vec<Input> =
+        InputSpec::try_from(opts.inputs.as_str())?.inputs();
+    let (snippet, _): (Code, _) = read_code(&opts.path, ParseMode::Strict)?;
+    let model: CostModel = match opts.cost_model {
+        Some(t) => CostModel::try_from(&read_config(t)?)?,
+        None => CostModel::default(),
+    };
+
+    println!(
+        "original (cost {}): {:?}",
+        superopt::cost(&snippet.0, &model),
+        snippet.0
+    );
+
+    match superopt::search(&snippet.0, &inputs, &model, opts.max_len)? {
+        Some(Candidate { code, cost }) => {
+            println!("found (cost {}): {:?}", cost, code)
+        }
+        None => println!(
+            "no cheaper sequence of at most {} instructions",
+            opts.max_len
+        ),
+    }
+
+    Ok(())
+}
+
+pub fn fuzz_inputs(opts: FuzzInputsOpts) -> Result<(), CommandError> {
+    let (code, _): (Code, _) = read_code(&opts.path, ParseMode::Strict)?;
+    let mut fuzzer: InputFuzzer =
+        InputFuzzer::new(code, opts.stack_args, opts.seed);
+
+    if let Some(t) = opts.step_limit {
+        fuzzer = fuzzer.with_step_limit(t);
+    }
+
+    let crashes: Vec<Crash> = fuzzer.run(opts.iterations);
+
+    for crash in &crashes {
+        println!(
+            "{:?} at pc {} (run {}): stack={:?} memory={:?}",
+            crash.error,
+            crash.pc,
+            crash.found_after,
+            crash.input.stack,
+            crash.input.memory
+        );
+    }
+
+    println!("{} runs, {} distinct faults", fuzzer.runs(), crashes.len());
+
+    Ok(())
+}
+
+pub fn verify(opts: VerifyOpts) -> Result<(), CommandError> {
+    let (code, _): (Code, _) = read_code(&opts.path, ParseMode::Strict)?;
+    let config: VerifyConfig = match &opts.config {
+        Some(t) => VerifyConfig::try_from(&read_config(t)?)?,
+        None => VerifyConfig::default(),
+    };
+    let mut analysis: Analysis =
+        analyse(&code.0, AbstractState::unknown(opts.stack_args));
+
+    if let Some(t) = &opts.deny_ops {
+        let denied: OpcodeSet = OpcodeSet::try_from(t.as_str())?;
+        analysis
+            .findings
+            .extend(denied_findings(&code.0, &analysis, &denied));
+        analysis.findings.sort_unstable();
+    }
+
+    let offsets: Vec<usize> = offsets(&code.0);
+    let mut diagnostics: Vec<Diagnostic> = analysis
+        .findings
+        .iter()
+        .map(|t| Diagnostic::from_finding(t, &offsets))
+        .collect();
+
+    if opts.warnings || config.warnings {
+        diagnostics.extend(
+            warnings(&code.0, &analysis)
+                .iter()
+                .map(|t| Diagnostic::from_warning(t, &offsets)),
+        );
+    }
+
+    let diagnostics: Vec<Diagnostic> = config.apply(diagnostics);
+    let failures: usize =
+        diagnostics.iter().filter(|t| config.fails(t)).count();
+
+    match opts.format.as_str() {
+        "text" => {}
+        "json" => {
+            let report: Report = Report {
+                version: REPORT_VERSION,
+                verified: analysis.proven(),
+                passed: failures == 0,
+                diagnostics,
+            };
+
+            println!(
+                "{}",
+                serde_json::to_string(&report)
+                    .map_err(|e| CommandError::IOError(e.into()))?
+            );
+
+            return match failures {
+                0 => Ok(()),
+                n => Err(CommandError::Unverified(n)),
+            };
+        }
+        t => return Err(CommandError::InvalidArgument(t.to_string())),
+    }
+
+    if opts.annotate {
+        for (pc, (instruction, state)) in
+            code.0.iter().zip(&analysis.states).enumerate()
+        {
+            let ranges: String = match state {
+                Some(t) => format!(
+                    "reg={} stack=[{}]",
+                    t.reg,
+                    t.stack
+                        .iter()
+                        .map(|t| t.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+                None => "unreachable".to_string(),
+            };
+
+            println!(
+                "{:>6}  {:<24}  {}",
+                pc,
+                format!("{:?}", instruction),
+                ranges
+            );
+        }
+    }
+
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic.text);
+    }
+
+    match (failures, analysis.proven()) {
+        (0, true) => {
+            println!("verified: no run can fault");
+            Ok(())
+        }
+        (0, false) => {
+            println!("passed: nothing reported reaches the failure threshold");
+            Ok(())
+        }
+        (n, _) => Err(CommandError::Unverified(n)),
+    }
+}
+
+pub fn bindiff(old: PathBuf, new: PathBuf) -> Result<(), CommandError> {
+    let (old, _): (Code, _) = read_code(&old, ParseMode::Strict)?;
+    let (new, _): (Code, _) = read_code(&new, ParseMode::Strict)?;
+    let edits: Vec<Edit> = diff(&old.0, &new.0);
+    let mut changes: usize = 0;
+
+    /* positions are printed as instruction index @ byte offset */
+    for edit in &edits {
+        match edit {
+            Edit::Same { .. } => continue,
+            Edit::Deleted { old, instruction } => {
+                println!("- {}@{:#x}  {:?}", old.0, old.1, instruction)
+            }
+            Edit::Inserted { new, instruction } => {
+                println!("+ {}@{:#x}  {:?}", new.0, new.1, instruction)
+            }
+            Edit::Changed { old, new, from, to } => println!(
+                "~ {}@{:#x} -> {}@{:#x}  {:?} -> {:?}",
+                old.0, old.1, new.0, new.1, from, to
+            ),
+        }
+
+        changes += 1;
+    }
+
+    println!(
+        "{} instructions -> {} instructions, {} changes",
+        old.0.len(),
+        new.0.len(),
+        changes
+    );
+
+    Ok(())
+}
+
+/* names the segment of a rotated trace that holds `step` */
+pub fn trace_segment(index: PathBuf, step: u64) -> Result<(), CommandError> {
+    let segments: Vec<Segment> = read_index(File::open(index)?)?;
+
+    match find_segment(&segments, step) {
+        Some(t) => {
+            println!("{}", t.path.display());
+            Ok(())
+        }
+        None => Err(CommandError::InvalidArgument(format!(
+            "step {} is not in any retained segment",
+            step
+        ))),
+    }
+}
+
+pub fn trace_stats(path: PathBuf) -> Result<(), CommandError> {
+    let entries: Vec<TraceEntry> = read_trace(File::open(path)?)?;
+    let summary: TraceSummary = summarise(&entries);
+
+    println!("steps: {}", summary.steps);
+    println!("{:<10}  {:>12}  {:>7}", "opcode", "count", "%");
+
+    for (mnemonic, count) in summary.ranked() {
+        let percentage: f64 =
+            100.0 * (count as f64) / (summary.steps.max(1) as f64);
+        println!("{:<10}  {:>12}  {:>7.2}", mnemonic, count, percentage);
+    }
+
+    Ok(())
+}
+
+pub fn trace_export(
+    path: PathBuf,
+    stats: bool,
+    output: Option<PathBuf>,
+) -> Result<(), CommandError> {
+    let entries: Vec<TraceEntry> = read_trace(File::open(path)?)?;
+    let mut sink: Box<dyn Write> = match output {
+        Some(t) => Box::new(BufWriter::new(File::create(t)?)),
+        None => Box::new(io::stdout()),
+    };
+
+    if stats {
+        write_stats_csv(&mut sink, &summarise(&entries))?;
+    } else {
+        write_steps_csv(&mut sink, &entries)?;
+    }
+
+    sink.flush()?;
+    Ok(())
+}
+
+pub fn trace_query(path: PathBuf, query: String) -> Result<(), CommandError> {
+    let query: Query = Query::try_from(query.as_str())?;
+    let entries: Vec<TraceEntry> = read_trace(File::open(path)?)?;
+
+    match query.execute(&entries) {
+        QueryResult::Steps(records) => {
+            for record in records {
+                println!(
+                    "{}",
+                    serde_json::to_string(&record)
+                        .map_err(|e| CommandError::IOError(e.into()))?
+                );
+            }
+        }
+        QueryResult::Count(n) => println!("{}", n),
+    }
+
+    Ok(())
+}
+
+pub fn asm(
+    source: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<(), CommandError> {
+    let source: String =
+        String::from_utf8(read_bytes(&source)?).map_err(|_| {
+            CommandError::InvalidArgument("source is not UTF-8".into())
+        })?;
+    let bytes: Vec<u8> = assemble(&source)?.to_bytes();
+
+    match output {
+        Some(path) => fs::write(path, bytes)?,
+        None => io::stdout().lock().write_all(&bytes)?,
+    }
+
+    Ok(())
+}
+
+pub fn disasm(path: PathBuf) -> Result<(), CommandError> {
+    let (code, _): (Code, _) = read_code(&path, ParseMode::Strict)?;
+    print!("{}", disassemble(&code));
+    Ok(())
+}
+
+/*
+ * Grades a program against an exercise spec, writing the JSON score report
+ * to the output file or stdout and a one-line summary to stderr
+ */
+pub fn grade(opts: GradeOpts) -> Result<(), CommandError> {
+    let spec: Spec = read_spec(File::open(&opts.spec)?)?;
+    let (student, _): (Code, _) = read_code(&opts.path, ParseMode::Strict)?;
+    let reference: Option<Code> = match &opts.reference {
+        Some(t) => Some(read_code(t, ParseMode::Strict)?.0),
+        None => None,
+    };
+
+    let report: ScoreReport =
+        grade::grade(&student, reference.as_ref(), &spec)?;
+    let json: String = serde_json::to_string_pretty(&report)
+        .map_err(|e| CommandError::IOError(e.into()))?;
+
+    match &opts.output {
+        Some(t) => fs::write(t, json + "\n")?,
+        None => println!("{}", json),
+    }
+
+    eprintln!(
+        "{}: {}/{} ({} of {} cases passed)",
+        opts.path.display(),
+        report.score,
+        report.total,
+        report.cases.iter().filter(|t| t.passed).count(),
+        report.cases.len()
+    );
+    Ok(())
+}
+
+/*
+ * Checks a case per seed, counting up from `--seed`, until the time or
+ * case budget runs out or the run is interrupted. A failing case can be
+ * replayed alone with `--seed` set to its seed and `--cases 1`.
+ */
+pub fn torture(opts: TortureOpts) -> Result<(), CommandError> {
+    let step_limit: u64 = opts.step_limit.unwrap_or(DEFAULT_TORTURE_STEP_LIMIT);
+    let deadline: Instant =
+        Instant::now() + Duration::from_secs(opts.minutes.saturating_mul(60));
+    let mut summary: Summary = Summary::default();
+
+    while Instant::now() < deadline
+        && opts.cases.is_none_or(|t| summary.cases < t)
+        && !INTERRUPTED.load(Ordering::SeqCst)
+    {
+        let case: torture::Case =
+            torture::Case::generate(opts.seed.wrapping_add(summary.cases));
+        summary.record(torture::check(&case, step_limit));
+    }
+
+    print!("{}", summary.render());
+
+    match summary.failures.len() {
+        0 => Ok(()),
+        n => Err(CommandError::TortureFailed(n)),
+    }
+}
+
+pub fn inspect_dump(path: PathBuf) -> Result<(), CommandError> {
+    let dump: CrashDump = read_dump(File::open(path)?)?;
+    print!("{}", dump.render());
+    Ok(())
+}
+
+/* prints a running machine's state, with `START[:LEN]` memory ranges */
+pub fn attach(socket: PathBuf, mem: Vec<String>) -> Result<(), CommandError> {
+    let request: InspectRequest = InspectRequest {
+        memory: mem
+            .iter()
+            .map(|t| match t.split_once(':') {
+                Some((start, len)) => {
+                    Ok((parse_word_arg(start)?, parse_word_arg(len)?))
+                }
+                None => Ok((parse_word_arg(t)?, 1)),
+            })
+            .collect::<Result<Vec<(Word, Word)>, CommandError>>()?,
+    };
+    let reply: InspectReply = inspect::attach(&socket, &request)?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&reply)
+            .map_err(|e| CommandError::IOError(e.into()))?
+    );
+    Ok(())
+}
+
+pub fn explain_error(error: String) -> Result<(), CommandError> {
+    match explain(&error) {
+        Some(t) => {
+            print!("{}", t.render());
+            Ok(())
+        }
+        None => Err(CommandError::UnknownError(error)),
+    }
+}
+
+/* prints the instruction set reference, with costs from `cost_model` */
+pub fn isa(
+    format: String,
+    cost_model: Option<PathBuf>,
+) -> Result<(), CommandError> {
+    let model: CostModel = match &cost_model {
+        Some(t) => CostModel::try_from(&read_config(t)?)?,
+        None => CostModel::default(),
+    };
+    let reference: IsaReference = IsaReference::new(&model);
+
+    match format.as_str() {
+        "md" => print!("{}", reference.render_markdown()),
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&reference)
+                .map_err(|e| CommandError::IOError(e.into()))?
+        ),
+        t => return Err(CommandError::InvalidArgument(t.to_string())),
+    }
+
+    Ok(())
+}
+
+/* prints the named schema, or lists the schemas if none is named */
+pub fn schema(name: Option<String>) -> Result<(), CommandError> {
+    let name: String = match name {
+        Some(t) => t,
+        None => {
+            SCHEMAS.iter().for_each(|t| println!("{}", t));
+            return Ok(());
+        }
+    };
+
+    match schema::schema(&name) {
+        Some(t) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&t)
+                    .map_err(|e| CommandError::IOError(e.into()))?
+            );
+            Ok(())
+        }
+        None => Err(CommandError::InvalidArgument(format!(
+            "unknown schema {} (expected one of {})",
+            name,
+            SCHEMAS.join(", ")
+        ))),
+    }
+}
+
+/* reads a program from a file, or from standard input if the path is `-` */
+fn read_code(
+    path: &Path,
+    mode: ParseMode,
+) -> Result<(Code, Option<CodeParseError>), CommandError> {
+    if path == Path::new("-") {
+        Ok(Code::from_reader(io::stdin().lock(), mode)?)
+    } else {
+        Ok(Code::from_reader(File::open(path)?, mode)?)
+    }
+}
+
+fn read_bytes(path: &Path) -> Result<Vec<u8>, CommandError> {
+    if path == Path::new("-") {
+        let mut bytes: Vec<u8> = vec![];
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        Ok(fs::read(path)?)
+    }
+}
+
+fn read_config<P: AsRef<Path>>(path: P) -> Result<Config, CommandError> {
+    let contents: String = fs::read_to_string(path)?;
+    Ok(Config::try_from(contents.as_str())?)
+}
+
+fn clbk(state: &State, instruction: Instruction) {
     println!("[{:?}] {:?}", instruction, state);
 }
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+/*
+ * A reader for the subset of TOML used by our configuration files: tables
+ * (`[name]`), `key = value` pairs and `#` comments, where values are
+ * integers, booleans, basic strings or single-line arrays thereof. Keys
+ * appearing before the first table header belong to the root table, which
+ * is named by the empty string.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Integer(i128),
+    Boolean(bool),
+    String(String),
+    Array(Vec<Value>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    Syntax(usize),
+    DuplicateKey(usize, String),
+    DuplicateTable(usize, String),
+    InvalidValue(usize),
+}
+
+pub type Table = HashMap<String, Value>;
+
+#[derive(Clone, Debug, Default)]
+pub struct Config(pub HashMap<String, Table>);
+
+impl Config {
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.0.get(name)
+    }
+
+    pub fn get(&self, table: &str, key: &str) -> Option<&Value> {
+        self.table(table).and_then(|t| t.get(key))
+    }
+}
+
+impl Value {
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            Self::Integer(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(t) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&str> for Config {
+    type Error = ConfigError;
+
+    fn try_from(data: &str) -> Result<Self, Self::Error> {
+        let mut tables: HashMap<String, Table> = HashMap::new();
+        let mut current: String = String::new();
+
+        tables.insert(current.clone(), Table::new());
+
+        for (i, raw_line) in data.lines().enumerate() {
+            let line_number: usize = i + 1;
+            let line: &str = strip_comment(raw_line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            /* table header */
+            if let Some(header) = line.strip_prefix('[') {
+                let name: &str = match header.strip_suffix(']') {
+                    Some(t) => t.trim(),
+                    None => return Err(ConfigError::Syntax(line_number)),
+                };
+
+                if name.is_empty() {
+                    return Err(ConfigError::Syntax(line_number));
+                }
+
+                if tables.contains_key(name) {
+                    return Err(ConfigError::DuplicateTable(
+                        line_number,
+                        name.to_string(),
+                    ));
+                }
+
+                current = name.to_string();
+                tables.insert(current.clone(), Table::new());
+                continue;
+            }
+
+            /* key-value pair */
+            let (key, value): (&str, &str) = match line.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => return Err(ConfigError::Syntax(line_number)),
+            };
+            let key: String = key.trim_matches('"').to_string();
+
+            if key.is_empty() {
+                return Err(ConfigError::Syntax(line_number));
+            }
+
+            let value: Value = match parse_value(value) {
+                Some(t) => t,
+                None => return Err(ConfigError::InvalidValue(line_number)),
+            };
+
+            let table: &mut Table = tables.entry(current.clone()).or_default();
+
+            if table.contains_key(&key) {
+                return Err(ConfigError::DuplicateKey(line_number, key));
+            }
+
+            table.insert(key, value);
+        }
+
+        Ok(Self(tables))
+    }
+}
+
+/* removes a trailing comment, taking care not to split inside strings */
+fn strip_comment(line: &str) -> &str {
+    let mut in_string: bool = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    if let Some(inner) = value.strip_prefix('[') {
+        let inner: &str = inner.strip_suffix(']')?.trim();
+
+        if inner.is_empty() {
+            return Some(Value::Array(vec![]));
+        }
+
+        return split_array(inner)
+            .iter()
+            .map(|t| parse_value(t.trim()))
+            .collect::<Option<Vec<Value>>>()
+            .map(Value::Array);
+    }
+
+    if let Some(inner) = value.strip_prefix('"') {
+        let inner: &str = inner.strip_suffix('"')?;
+
+        if inner.contains('"') {
+            return None;
+        }
+
+        return Some(Value::String(inner.to_string()));
+    }
+
+    match value {
+        "true" => return Some(Value::Boolean(true)),
+        "false" => return Some(Value::Boolean(false)),
+        _ => {}
+    }
+
+    let digits: String = value.replace('_', "");
+    let (negative, digits): (bool, &str) = match digits.strip_prefix('-') {
+        Some(t) => (true, t),
+        None => (false, digits.strip_prefix('+').unwrap_or(&digits)),
+    };
+
+    let magnitude: i128 = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?,
+    };
+
+    Some(Value::Integer(if negative {
+        -magnitude
+    } else {
+        magnitude
+    }))
+}
+
+/* splits a comma-separated list, ignoring commas within strings */
+fn split_array(inner: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = vec![];
+    let mut in_string: bool = false;
+    let mut start: usize = 0;
+
+    for (i, c) in inner.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                parts.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    /* permit a trailing comma */
+    if !inner[start..].trim().is_empty() {
+        parts.push(&inner[start..]);
+    }
+
+    parts
+}
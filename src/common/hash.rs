@@ -0,0 +1,44 @@
+use crate::common::types::Word;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/*
+ * 64-bit FNV-1a. Unlike `std`'s default hasher this is stable across
+ * processes and releases, so digests can be stored and compared later.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Fnv1a {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn write_word(&mut self, word: Word) {
+        self.write(&word.to_be_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hasher: Fnv1a = Fnv1a::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
@@ -1 +1,6 @@
+pub mod config;
+pub mod hash;
+pub mod pool;
+pub mod rng;
+pub mod signal;
 pub mod types;
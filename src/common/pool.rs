@@ -0,0 +1,54 @@
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/* the number of threads to use when none is specified */
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |t| t.get())
+}
+
+/*
+ * Applies `task` to every item on `jobs` threads, returning the results in
+ * input order. Rather than being dealt a fixed share up front, each thread
+ * claims the next unclaimed item from a shared atomic cursor whenever it
+ * becomes free, so a few slow items cannot leave the other threads idle.
+ * There are no per-thread queues, so nothing is stolen: threads only ever
+ * take from the cursor. A panicking task is re-raised on the calling thread.
+ */
+pub fn parallel_map<T, R, F>(items: &[T], jobs: usize, task: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next: AtomicUsize = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..jobs.clamp(1, items.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done: Vec<(usize, R)> = vec![];
+
+                    loop {
+                        let i: usize = next.fetch_add(1, Ordering::Relaxed);
+
+                        match items.get(i) {
+                            Some(t) => done.push((i, task(t))),
+                            None => return done,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|t| match t.join() {
+                Ok(done) => done,
+                Err(e) => panic::resume_unwind(e),
+            })
+            .collect()
+    });
+
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, t)| t).collect()
+}
@@ -0,0 +1,38 @@
+use crate::common::types::Word;
+
+/*
+ * xorshift64*, which is plenty for generating test inputs. Seeded runs are
+ * reproducible across hosts and releases.
+ */
+#[derive(Clone, Debug)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        /* the all-zero state is a fixed point */
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /* a value in `0..bound`, or zero if the range is empty */
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64().checked_rem(bound).unwrap_or_default()
+    }
+
+    /*
+     * Half of all operands are small so that arithmetic is exercised
+     * without nearly every input overflowing.
+     */
+    pub fn operand(&mut self) -> Word {
+        match self.next_u64() % 2 {
+            0 => self.next_u64() % 16,
+            _ => self.next_u64(),
+        }
+    }
+}
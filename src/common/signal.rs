@@ -0,0 +1,40 @@
+#[cfg(unix)]
+use std::mem;
+#[cfg(unix)]
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/* raised by SIGINT or SIGTERM once `install` has been called */
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn handle(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/*
+ * Replaces the default (terminating) handlers for SIGINT and SIGTERM with
+ * one that merely raises `INTERRUPTED`, leaving it to the machine to stop
+ * between instructions so that output can be flushed. The handlers are
+ * installed without SA_RESTART, so a read blocked on input fails with
+ * EINTR instead of waiting for more.
+ */
+#[cfg(unix)]
+pub fn install() {
+    let handler: extern "C" fn(libc::c_int) = handle;
+
+    /* SAFETY: the handler only performs an atomic store, and `action` is
+     * fully initialised before it is installed */
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler as libc::sighandler_t;
+        action.sa_flags = 0;
+        libc::sigemptyset(&mut action.sa_mask);
+
+        libc::sigaction(libc::SIGINT, &action, ptr::null_mut());
+        libc::sigaction(libc::SIGTERM, &action, ptr::null_mut());
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
@@ -1,7 +1,63 @@
 pub type Word = u64;
+pub type SignedWord = i64;
 
 const BITS_PER_BYTE: usize = 8;
 
 pub fn word_bytes() -> usize {
     (Word::BITS as usize) / BITS_PER_BYTE
 }
+
+/* parses a decimal or `0x`-prefixed hexadecimal word */
+pub fn parse_word(literal: &str) -> Option<Word> {
+    let literal: &str = literal.trim();
+
+    match literal.strip_prefix("0x") {
+        Some(hex) => Word::from_str_radix(hex, 16).ok(),
+        None => literal.parse::<Word>().ok(),
+    }
+}
+
+/* a word as a host index, or None where usize is narrower than a word */
+pub fn word_to_usize(word: Word) -> Option<usize> {
+    usize::try_from(word).ok()
+}
+
+/* a word read as a two's-complement signed integer */
+pub fn to_signed(word: Word) -> SignedWord {
+    word as SignedWord
+}
+
+/* the two's-complement encoding of a signed integer as a word */
+pub fn from_signed(value: SignedWord) -> Word {
+    value as Word
+}
+
+/* double-width arithmetic, through a 128-bit intermediate */
+pub mod wide {
+    use super::Word;
+
+    type DoubleWord = u128;
+
+    /* the high word of `a * b` */
+    pub fn mul_hi(a: Word, b: Word) -> Word {
+        ((a as DoubleWord * b as DoubleWord) >> Word::BITS) as Word
+    }
+
+    /*
+     * The quotient and remainder of the double-width `hi:lo` by `divisor`,
+     * or None if the divisor is zero or the quotient needs more than a word
+     */
+    pub fn div_rem(hi: Word, lo: Word, divisor: Word) -> Option<(Word, Word)> {
+        if hi >= divisor {
+            return None;
+        }
+
+        let dividend: DoubleWord =
+            ((hi as DoubleWord) << Word::BITS) | lo as DoubleWord;
+
+        Some((
+            (dividend / divisor as DoubleWord) as Word,
+            (dividend % divisor as DoubleWord) as Word,
+        ))
+    }
+}
@@ -0,0 +1,101 @@
+use std::mem;
+
+use crate::common::types::{word_bytes, word_to_usize, Word};
+use crate::core::memory::{LinearlyAddressable, Memory};
+use crate::core::region::Region;
+
+/*
+ * The data-exchange ABI for byte buffers. A buffer at address `a` is a
+ * header word at `a` holding its length in bytes, followed by the bytes
+ * themselves packed big-endian into the words from `a + 1` onwards, the
+ * final word being padded with zeroes. This matches the byte order of
+ * `Set` literals, so a program sees the first byte of a buffer in the most
+ * significant byte of its first data word.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbiError {
+    OutOfSpace,
+    TooLong(Word),
+}
+
+/* the number of words a buffer of `len` bytes occupies, header included */
+pub fn buffer_words(len: usize) -> usize {
+    1 + len.div_ceil(word_bytes())
+}
+
+/* writes a buffer at `address`, which must have room for it */
+pub fn write_buffer(memory: &mut Memory, address: Word, bytes: &[u8]) {
+    memory.write(address, bytes.len() as Word);
+
+    for (i, chunk) in bytes.chunks(word_bytes()).enumerate() {
+        let mut word: [u8; mem::size_of::<Word>()] =
+            [0; mem::size_of::<Word>()];
+        word[..chunk.len()].copy_from_slice(chunk);
+        memory.write(
+            address.wrapping_add(1 + i as Word),
+            Word::from_be_bytes(word),
+        );
+    }
+}
+
+/*
+ * Reads the buffer at `address` back. Lengths come from the program, so
+ * anything longer than `max_len` bytes is refused rather than allocated.
+ */
+pub fn read_buffer(
+    memory: &Memory,
+    address: Word,
+    max_len: usize,
+) -> Result<Vec<u8>, AbiError> {
+    let header: Word = memory.read(address);
+    let len: usize = word_to_usize(header)
+        .filter(|t| *t <= max_len)
+        .ok_or(AbiError::TooLong(header))?;
+    let mut bytes: Vec<u8> = Vec::with_capacity(len);
+
+    for i in 0..buffer_words(len) as Word - 1 {
+        let word: Word = memory.read(address.wrapping_add(1 + i));
+        bytes.extend_from_slice(&word.to_be_bytes());
+    }
+
+    bytes.truncate(len);
+    Ok(bytes)
+}
+
+/*
+ * Hands out space for buffers from a named region, lowest addresses first.
+ * Space is never reclaimed; a fresh allocator starts the region over.
+ */
+#[derive(Clone, Debug)]
+pub struct BufferAllocator {
+    next: Word,
+    remaining: Word,
+}
+
+impl BufferAllocator {
+    pub fn new(region: &Region) -> Self {
+        Self {
+            next: region.start,
+            remaining: region.size,
+        }
+    }
+
+    /* copies `bytes` into the region, returning the buffer's address */
+    pub fn write(
+        &mut self,
+        memory: &mut Memory,
+        bytes: &[u8],
+    ) -> Result<Word, AbiError> {
+        let words: Word = buffer_words(bytes.len()) as Word;
+
+        if words > self.remaining {
+            return Err(AbiError::OutOfSpace);
+        }
+
+        let address: Word = self.next;
+        write_buffer(memory, address, bytes);
+        self.next = self.next.wrapping_add(words);
+        self.remaining -= words;
+        Ok(address)
+    }
+}
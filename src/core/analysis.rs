@@ -0,0 +1,1297 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::mem;
+
+use crate::common::types::{
+    from_signed, to_signed, wide, word_to_usize, SignedWord, Word,
+};
+use crate::core::instruction::{Instruction, OpcodeSet};
+use crate::core::stack::MAX_STACK_DEPTH;
+
+/* joins at a single pc before its intervals are widened to guarantee termination */
+const WIDEN_AFTER: usize = 8;
+
+/* an inclusive range of words */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval {
+    pub lo: Word,
+    pub hi: Word,
+}
+
+impl Interval {
+    pub const TOP: Self = Self {
+        lo: 0,
+        hi: Word::MAX,
+    };
+
+    pub fn new(lo: Word, hi: Word) -> Self {
+        Self { lo, hi }
+    }
+
+    pub fn exact(value: Word) -> Self {
+        Self::new(value, value)
+    }
+
+    pub fn as_exact(&self) -> Option<Word> {
+        (self.lo == self.hi).then_some(self.lo)
+    }
+
+    pub fn contains(&self, value: Word) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    pub fn join(&self, other: &Self) -> Self {
+        Self::new(self.lo.min(other.lo), self.hi.max(other.hi))
+    }
+
+    /* jumps any bound that is still moving straight to its extreme */
+    fn widen(&self, next: &Self) -> Self {
+        Self::new(
+            if next.lo < self.lo { 0 } else { self.lo },
+            if next.hi > self.hi {
+                Word::MAX
+            } else {
+                self.hi
+            },
+        )
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.as_exact(), *self == Self::TOP) {
+            (Some(t), _) => write!(f, "{}", t),
+            (None, true) => write!(f, "*"),
+            (None, false) => write!(f, "{}..={}", self.lo, self.hi),
+        }
+    }
+}
+
+/*
+ * Everything that may hold before an instruction executes. Stack depth is
+ * tracked exactly, so programs whose depth at some pc depends on the path
+ * taken are beyond this analysis. Memory not yet written reads as zero,
+ * unless a store to an unknown address has clobbered it.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbstractState {
+    pub reg: Interval,
+    pub stack: Vec<Interval>,
+    pub memory: BTreeMap<Word, Interval>,
+    pub memory_clobbered: bool,
+    /* whether a Wrap prefix applies to the next instruction */
+    pub wrap: bool,
+}
+
+impl AbstractState {
+    /* an unknown register over `depth` unknown stack words */
+    pub fn unknown(depth: usize) -> Self {
+        Self {
+            reg: Interval::TOP,
+            stack: vec![Interval::TOP; depth],
+            memory: BTreeMap::new(),
+            memory_clobbered: false,
+            wrap: false,
+        }
+    }
+
+    pub fn read(&self, address: Interval) -> Interval {
+        match (address.as_exact(), self.memory_clobbered) {
+            (Some(t), false) => {
+                self.memory.get(&t).copied().unwrap_or(Interval::exact(0))
+            }
+            (Some(t), true) => {
+                self.memory.get(&t).copied().unwrap_or(Interval::TOP)
+            }
+            (None, _) => Interval::TOP,
+        }
+    }
+
+    pub fn write(&mut self, address: Interval, data: Interval) {
+        match address.as_exact() {
+            Some(t) => {
+                self.memory.insert(t, data);
+            }
+            None => {
+                self.memory.clear();
+                self.memory_clobbered = true;
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Interval {
+        self.stack.pop().unwrap_or(Interval::TOP)
+    }
+
+    fn combine(
+        &self,
+        other: &Self,
+        f: fn(&Interval, &Interval) -> Interval,
+    ) -> Option<Self> {
+        if self.stack.len() != other.stack.len() {
+            return None;
+        }
+
+        let default: Interval =
+            match self.memory_clobbered || other.memory_clobbered {
+                true => Interval::TOP,
+                false => Interval::exact(0),
+            };
+        let mut memory: BTreeMap<Word, Interval> = BTreeMap::new();
+
+        for address in self.memory.keys().chain(other.memory.keys()) {
+            let a: Interval =
+                self.memory.get(address).copied().unwrap_or(default);
+            let b: Interval =
+                other.memory.get(address).copied().unwrap_or(default);
+            memory.insert(*address, f(&a, &b));
+        }
+
+        Some(Self {
+            reg: f(&self.reg, &other.reg),
+            stack: self
+                .stack
+                .iter()
+                .zip(&other.stack)
+                .map(|(a, b)| f(a, b))
+                .collect(),
+            memory,
+            memory_clobbered: self.memory_clobbered || other.memory_clobbered,
+            /* overflow is only ruled out if every path wraps */
+            wrap: self.wrap && other.wrap,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FindingKind {
+    Overflow,
+    DivisionByZero,
+    StackUnderflow,
+    StackOverflow,
+    IllegalInstruction,
+    UnresolvedJump,
+    InconsistentDepth,
+    EndOfInput,
+    DeniedInstruction,
+}
+
+/*
+ * A potential fault at a pc. `certain` findings occur on every execution
+ * that reaches the pc; the rest merely could not be ruled out.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Finding {
+    pub pc: usize,
+    pub kind: FindingKind,
+    pub certain: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Analysis {
+    pub states: Vec<Option<AbstractState>>,
+    pub findings: Vec<Finding>,
+}
+
+impl Analysis {
+    /* whether every run is free of faults (and the analysis was complete) */
+    pub fn proven(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+/*
+ * Interval-domain abstract interpretation over a decoded program. Jumps are
+ * only followed when their target is known exactly; an unresolved jump is
+ * reported as a finding, so a program is never proven safe on the strength
+ * of an incomplete exploration.
+ */
+pub fn analyse(code: &[Instruction], initial: AbstractState) -> Analysis {
+    let mut states: Vec<Option<AbstractState>> = vec![None; code.len()];
+    let mut visits: Vec<usize> = vec![0; code.len()];
+    let mut findings: BTreeMap<(usize, FindingKind), bool> = BTreeMap::new();
+    let mut worklist: VecDeque<usize> = VecDeque::new();
+
+    if !code.is_empty() {
+        states[0] = Some(initial);
+        worklist.push_back(0);
+    }
+
+    while let Some(pc) = worklist.pop_front() {
+        let state: AbstractState = match &states[pc] {
+            Some(t) => t.clone(),
+            None => continue,
+        };
+        let mut report = |kind: FindingKind, certain: bool| {
+            *findings.entry((pc, kind)).or_insert(certain) &= certain;
+        };
+
+        for (next, successor) in transfer(pc, code[pc], state, &mut report) {
+            /* running off the end of the program simply finishes it */
+            let next: usize = match word_to_usize(next) {
+                Some(t) if t < code.len() => t,
+                _ => continue,
+            };
+
+            let merged: Option<AbstractState> = match &states[next] {
+                None => Some(successor),
+                Some(old) => {
+                    visits[next] += 1;
+
+                    let joined: Option<AbstractState> =
+                        old.combine(&successor, Interval::join);
+
+                    match (joined, visits[next] > WIDEN_AFTER) {
+                        (None, _) => {
+                            findings
+                                .entry((next, FindingKind::InconsistentDepth))
+                                .or_insert(false);
+                            None
+                        }
+                        (Some(t), false) => Some(t),
+                        (Some(t), true) => old.combine(&t, Interval::widen),
+                    }
+                }
+            };
+
+            if let Some(t) = merged {
+                if states[next].as_ref() != Some(&t) {
+                    states[next] = Some(t);
+                    worklist.push_back(next);
+                }
+            }
+        }
+    }
+
+    Analysis {
+        states,
+        findings: findings
+            .into_iter()
+            .map(|((pc, kind), certain)| Finding { pc, kind, certain })
+            .collect(),
+    }
+}
+
+pub fn arity(instruction: Instruction) -> usize {
+    match instruction {
+        Instruction::Load
+        | Instruction::Pop
+        | Instruction::Jump
+        | Instruction::Write
+        | Instruction::Not
+        | Instruction::Popcnt
+        | Instruction::Clz
+        | Instruction::Ctz => 1,
+        Instruction::Store
+        | Instruction::Add
+        | Instruction::Sub
+        | Instruction::Mul
+        | Instruction::Div
+        | Instruction::Mod
+        | Instruction::Cmp
+        | Instruction::And
+        | Instruction::Or
+        | Instruction::Xor
+        | Instruction::FetchAdd
+        | Instruction::IAdd
+        | Instruction::ISub
+        | Instruction::IMul
+        | Instruction::IDiv
+        | Instruction::IMod
+        | Instruction::SLt
+        | Instruction::MulHi
+        | Instruction::Rotl
+        | Instruction::Rotr => 2,
+        Instruction::CasWord | Instruction::DivRem => 3,
+        _ => 0,
+    }
+}
+
+/* the successors of one instruction, mirroring `Machine::step` */
+fn transfer(
+    pc: usize,
+    instruction: Instruction,
+    mut state: AbstractState,
+    report: &mut dyn FnMut(FindingKind, bool),
+) -> Vec<(Word, AbstractState)> {
+    let next: Word = pc as Word + 1;
+
+    if state.stack.len() < arity(instruction) {
+        report(FindingKind::StackUnderflow, true);
+        return vec![];
+    }
+
+    let wrap: bool = mem::replace(&mut state.wrap, false);
+
+    match instruction {
+        Instruction::Nop | Instruction::TraceOn | Instruction::TraceOff => {}
+        Instruction::Halt => return vec![],
+        Instruction::Wrap => state.wrap = true,
+        Instruction::Set(x) => state.reg = Interval::exact(x),
+        Instruction::Push => {
+            if state.stack.len() == MAX_STACK_DEPTH {
+                report(FindingKind::StackOverflow, true);
+                return vec![];
+            }
+
+            state.stack.push(state.reg);
+        }
+        Instruction::Pop => state.reg = state.pop(),
+        Instruction::Load => {
+            let address: Interval = state.pop();
+            let data: Interval = state.read(address);
+            state.stack.push(data);
+        }
+        Instruction::Store => {
+            let address: Interval = state.pop();
+            let data: Interval = state.pop();
+            state.write(address, data);
+        }
+        Instruction::Jump => {
+            let target: Interval = state.stack[state.stack.len() - 1];
+
+            return match target.as_exact() {
+                Some(t) => vec![(t, state)],
+                None => {
+                    report(FindingKind::UnresolvedJump, false);
+                    vec![]
+                }
+            };
+        }
+        Instruction::Add
+        | Instruction::Sub
+        | Instruction::Mul
+        | Instruction::Div
+        | Instruction::Mod
+        | Instruction::Cmp
+        | Instruction::And
+        | Instruction::Or
+        | Instruction::Xor => {
+            let a: Interval = state.pop();
+            let b: Interval = state.pop();
+
+            match binary(instruction, a, b, wrap, report) {
+                Some(t) => state.stack.push(t),
+                None => return vec![],
+            }
+        }
+        Instruction::Not => {
+            let a: Interval = state.pop();
+            state.stack.push(Interval::new(!a.hi, !a.lo));
+        }
+        Instruction::IAdd
+        | Instruction::ISub
+        | Instruction::IMul
+        | Instruction::IDiv
+        | Instruction::IMod
+        | Instruction::SLt => {
+            let a: Interval = state.pop();
+            let b: Interval = state.pop();
+
+            match signed(instruction, a, b, wrap, report) {
+                Some(t) => state.stack.push(t),
+                None => return vec![],
+            }
+        }
+        Instruction::MulHi => {
+            let a: Interval = state.pop();
+            let b: Interval = state.pop();
+            state.stack.push(Interval::new(
+                wide::mul_hi(a.lo, b.lo),
+                wide::mul_hi(a.hi, b.hi),
+            ));
+        }
+        Instruction::Rotl | Instruction::Rotr => {
+            let a: Interval = state.pop();
+            let b: Interval = state.pop();
+            let amount = |t: Word| (t % Word::BITS as Word) as u32;
+
+            state
+                .stack
+                .push(match (a.as_exact(), b.as_exact(), instruction) {
+                    (Some(x), Some(y), Instruction::Rotl) => {
+                        Interval::exact(x.rotate_left(amount(y)))
+                    }
+                    (Some(x), Some(y), _) => {
+                        Interval::exact(x.rotate_right(amount(y)))
+                    }
+                    _ => Interval::TOP,
+                });
+        }
+        Instruction::Popcnt | Instruction::Ctz => {
+            let a: Interval = state.pop();
+
+            state.stack.push(match (a.as_exact(), instruction) {
+                (Some(x), Instruction::Popcnt) => {
+                    Interval::exact(x.count_ones() as Word)
+                }
+                (Some(x), _) => Interval::exact(x.trailing_zeros() as Word),
+                (None, _) => Interval::new(0, Word::BITS as Word),
+            });
+        }
+        Instruction::Clz => {
+            /* fewer leading zeros the larger the word */
+            let a: Interval = state.pop();
+            state.stack.push(Interval::new(
+                a.hi.leading_zeros() as Word,
+                a.lo.leading_zeros() as Word,
+            ));
+        }
+        Instruction::DivRem => {
+            let hi: Interval = state.pop();
+            let lo: Interval = state.pop();
+            let divisor: Interval = state.pop();
+
+            match div_rem(hi, lo, divisor, report) {
+                Some((quotient, remainder)) => {
+                    state.stack.push(remainder);
+                    state.stack.push(quotient);
+                }
+                None => return vec![],
+            }
+        }
+        Instruction::CasWord => {
+            let address: Interval = state.pop();
+            let expected: Interval = state.pop();
+            let new: Interval = state.pop();
+            let old: Interval = state.read(address);
+
+            /* the swap happens on some, all or none of the paths */
+            let stored: Interval = match (old.as_exact(), expected.as_exact()) {
+                (Some(x), Some(y)) if x == y => new,
+                _ if old.hi < expected.lo || expected.hi < old.lo => old,
+                _ => old.join(&new),
+            };
+
+            state.write(address, stored);
+            state.stack.push(old);
+        }
+        Instruction::FetchAdd => {
+            let address: Interval = state.pop();
+            let delta: Interval = state.pop();
+            let old: Interval = state.read(address);
+
+            match binary(Instruction::Add, old, delta, wrap, report) {
+                Some(t) => state.write(address, t),
+                None => return vec![],
+            }
+
+            state.stack.push(old);
+        }
+        /* input is unknown, and may run out */
+        Instruction::Read => {
+            if state.stack.len() == MAX_STACK_DEPTH {
+                report(FindingKind::StackOverflow, true);
+                return vec![];
+            }
+
+            report(FindingKind::EndOfInput, false);
+            state.stack.push(Interval::TOP);
+        }
+        Instruction::Write => {
+            state.pop();
+        }
+        Instruction::Poll => {
+            if state.stack.len() == MAX_STACK_DEPTH {
+                report(FindingKind::StackOverflow, true);
+                return vec![];
+            }
+
+            state.stack.push(Interval::new(0, 1));
+        }
+        Instruction::JumpIf => {
+            report(FindingKind::IllegalInstruction, true);
+            return vec![];
+        }
+    }
+
+    vec![(next, state)]
+}
+
+/*
+ * The range of `a op b` (where `a` was on top of the stack) over the
+ * executions that do not fault, reporting those that might. Returns None
+ * if every execution faults.
+ */
+fn binary(
+    instruction: Instruction,
+    a: Interval,
+    b: Interval,
+    wrap: bool,
+    report: &mut dyn FnMut(FindingKind, bool),
+) -> Option<Interval> {
+    if wrap
+        && matches!(
+            instruction,
+            Instruction::Add | Instruction::Sub | Instruction::Mul
+        )
+    {
+        return Some(wrapping(instruction, a, b));
+    }
+
+    if let (Some(x), Some(y)) = (a.as_exact(), b.as_exact()) {
+        let exact: Option<Word> = match instruction {
+            Instruction::Add => x.checked_add(y),
+            Instruction::Sub => x.checked_sub(y),
+            Instruction::Mul => x.checked_mul(y),
+            Instruction::Div => x.checked_div(y),
+            Instruction::Mod => x.checked_rem(y),
+            Instruction::Cmp => Some((x == y) as Word),
+            Instruction::And => Some(x & y),
+            Instruction::Or => Some(x | y),
+            _ => Some(x ^ y),
+        };
+
+        return match exact {
+            Some(t) => Some(Interval::exact(t)),
+            None => {
+                report(fault_kind(instruction), true);
+                None
+            }
+        };
+    }
+
+    match instruction {
+        Instruction::Add => {
+            if a.lo.checked_add(b.lo).is_none() {
+                report(FindingKind::Overflow, true);
+                return None;
+            }
+
+            if a.hi.checked_add(b.hi).is_none() {
+                report(FindingKind::Overflow, false);
+            }
+
+            Some(Interval::new(a.lo + b.lo, a.hi.saturating_add(b.hi)))
+        }
+        Instruction::Sub => {
+            if a.hi < b.lo {
+                report(FindingKind::Overflow, true);
+                return None;
+            }
+
+            if a.lo < b.hi {
+                report(FindingKind::Overflow, false);
+            }
+
+            Some(Interval::new(a.lo.saturating_sub(b.hi), a.hi - b.lo))
+        }
+        Instruction::Mul => {
+            if a.lo.checked_mul(b.lo).is_none() {
+                report(FindingKind::Overflow, true);
+                return None;
+            }
+
+            if a.hi.checked_mul(b.hi).is_none() {
+                report(FindingKind::Overflow, false);
+            }
+
+            Some(Interval::new(a.lo * b.lo, a.hi.saturating_mul(b.hi)))
+        }
+        Instruction::Div | Instruction::Mod => {
+            if b.hi == 0 {
+                report(FindingKind::DivisionByZero, true);
+                return None;
+            }
+
+            if b.lo == 0 {
+                report(FindingKind::DivisionByZero, false);
+            }
+
+            match instruction {
+                Instruction::Div => {
+                    Some(Interval::new(a.lo / b.hi, a.hi / b.lo.max(1)))
+                }
+                _ => Some(Interval::new(0, a.hi.min(b.hi - 1))),
+            }
+        }
+        Instruction::Cmp => Some(Interval::new(0, 1)),
+        Instruction::And => Some(Interval::new(0, a.hi.min(b.hi))),
+        Instruction::Or => {
+            Some(Interval::new(a.lo.max(b.lo), mask(a.hi.max(b.hi))))
+        }
+        _ => Some(Interval::new(0, mask(a.hi.max(b.hi)))),
+    }
+}
+
+/*
+ * As `binary`, for the signed instructions. Intervals are over unsigned
+ * words, so inexact operands give an unknown result, and any fault an
+ * inexact operand could cause is reported as possible.
+ */
+fn signed(
+    instruction: Instruction,
+    a: Interval,
+    b: Interval,
+    wrap: bool,
+    report: &mut dyn FnMut(FindingKind, bool),
+) -> Option<Interval> {
+    if let (Some(x), Some(y)) = (a.as_exact(), b.as_exact()) {
+        let (x, y): (SignedWord, SignedWord) = (to_signed(x), to_signed(y));
+        let exact: Option<SignedWord> = match (instruction, wrap) {
+            (Instruction::SLt, _) => Some((x < y) as SignedWord),
+            (Instruction::IDiv | Instruction::IMod, _) if y == 0 => {
+                report(FindingKind::DivisionByZero, true);
+                return None;
+            }
+            (Instruction::IMod, _) => Some(x.wrapping_rem(y)),
+            (Instruction::IAdd, true) => Some(x.wrapping_add(y)),
+            (Instruction::IAdd, false) => x.checked_add(y),
+            (Instruction::ISub, true) => Some(x.wrapping_sub(y)),
+            (Instruction::ISub, false) => x.checked_sub(y),
+            (Instruction::IMul, true) => Some(x.wrapping_mul(y)),
+            (Instruction::IMul, false) => x.checked_mul(y),
+            (_, true) => Some(x.wrapping_div(y)),
+            (_, false) => x.checked_div(y),
+        };
+
+        return match exact {
+            Some(t) => Some(Interval::exact(from_signed(t))),
+            None => {
+                report(FindingKind::Overflow, true);
+                None
+            }
+        };
+    }
+
+    match instruction {
+        Instruction::SLt => return Some(Interval::new(0, 1)),
+        Instruction::IDiv | Instruction::IMod if b.hi == 0 => {
+            report(FindingKind::DivisionByZero, true);
+            return None;
+        }
+        Instruction::IDiv | Instruction::IMod if b.lo == 0 => {
+            report(FindingKind::DivisionByZero, false);
+        }
+        _ => {}
+    }
+
+    /* only the most negative word divided by -1 overflows a division */
+    let overflows: bool = match instruction {
+        Instruction::IMod => false,
+        Instruction::IDiv => {
+            a.contains(from_signed(SignedWord::MIN)) && b.contains(Word::MAX)
+        }
+        _ => true,
+    };
+
+    if overflows && !wrap {
+        report(FindingKind::Overflow, false);
+    }
+
+    Some(Interval::TOP)
+}
+
+/* the ranges of the quotient and remainder of a double-width division */
+fn div_rem(
+    hi: Interval,
+    lo: Interval,
+    divisor: Interval,
+    report: &mut dyn FnMut(FindingKind, bool),
+) -> Option<(Interval, Interval)> {
+    if let (Some(x), Some(y), Some(z)) =
+        (hi.as_exact(), lo.as_exact(), divisor.as_exact())
+    {
+        return match wide::div_rem(x, y, z) {
+            Some((q, r)) => Some((Interval::exact(q), Interval::exact(r))),
+            None => {
+                match z {
+                    0 => report(FindingKind::DivisionByZero, true),
+                    _ => report(FindingKind::Overflow, true),
+                }
+                None
+            }
+        };
+    }
+
+    if divisor.hi == 0 {
+        report(FindingKind::DivisionByZero, true);
+        return None;
+    }
+
+    if divisor.lo == 0 {
+        report(FindingKind::DivisionByZero, false);
+    }
+
+    /* the quotient fits exactly when the high word is below the divisor */
+    if hi.lo >= divisor.hi {
+        report(FindingKind::Overflow, true);
+        return None;
+    }
+
+    if hi.hi >= divisor.lo {
+        report(FindingKind::Overflow, false);
+    }
+
+    Some((Interval::TOP, Interval::new(0, divisor.hi - 1)))
+}
+
+/* the range of a wrapping `a op b`, which never faults */
+fn wrapping(instruction: Instruction, a: Interval, b: Interval) -> Interval {
+    match (a.as_exact(), b.as_exact(), instruction) {
+        (Some(x), Some(y), Instruction::Add) => {
+            Interval::exact(x.wrapping_add(y))
+        }
+        (Some(x), Some(y), Instruction::Sub) => {
+            Interval::exact(x.wrapping_sub(y))
+        }
+        (Some(x), Some(y), _) => Interval::exact(x.wrapping_mul(y)),
+        _ => Interval::TOP,
+    }
+}
+
+/* the smallest all-ones word no less than `value` */
+fn mask(value: Word) -> Word {
+    match value.checked_next_power_of_two() {
+        Some(t) if t == value => value | (value - 1),
+        Some(t) => t - 1,
+        None => Word::MAX,
+    }
+}
+
+fn fault_kind(instruction: Instruction) -> FindingKind {
+    match instruction {
+        Instruction::Div | Instruction::Mod => FindingKind::DivisionByZero,
+        _ => FindingKind::Overflow,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WarningKind {
+    DeadStore,
+    UnusedValue,
+}
+
+/* code that is wasteful rather than faulty */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Warning {
+    pub pc: usize,
+    pub kind: WarningKind,
+}
+
+/* what is read again later, before a given instruction executes */
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Liveness {
+    reg: bool,
+    stack: Vec<bool>,
+    memory: BTreeSet<Word>,
+    all_memory: bool,
+}
+
+impl Liveness {
+    /* the register and stack are the result of a run, but memory is not */
+    fn exit(depth: usize) -> Self {
+        Self {
+            reg: true,
+            stack: vec![true; depth],
+            ..Default::default()
+        }
+    }
+
+    fn everything(depth: usize) -> Self {
+        Self {
+            all_memory: true,
+            ..Self::exit(depth)
+        }
+    }
+
+    fn read(&mut self, address: Interval) {
+        match address.as_exact() {
+            Some(t) => {
+                self.memory.insert(t);
+            }
+            None => self.all_memory = true,
+        }
+    }
+}
+
+/* words popped and pushed by an instruction */
+pub fn stack_effect(instruction: Instruction) -> (usize, usize) {
+    match instruction {
+        Instruction::Push | Instruction::Read | Instruction::Poll => (0, 1),
+        Instruction::Pop | Instruction::Write => (1, 0),
+        Instruction::Store => (2, 0),
+        Instruction::Load | Instruction::Not => (1, 1),
+        Instruction::CasWord => (3, 1),
+        Instruction::DivRem => (3, 2),
+        Instruction::Jump => (0, 0),
+        t => match arity(t) {
+            0 => (0, 0),
+            n => (n, 1),
+        },
+    }
+}
+
+/* every reachable instruction in `denied`, each of which would fault */
+pub fn denied_findings(
+    code: &[Instruction],
+    analysis: &Analysis,
+    denied: &OpcodeSet,
+) -> Vec<Finding> {
+    code.iter()
+        .zip(&analysis.states)
+        .enumerate()
+        .filter(|(_, (instruction, state))| {
+            state.is_some() && denied.contains(**instruction)
+        })
+        .map(|(pc, _)| Finding {
+            pc,
+            kind: FindingKind::DeniedInstruction,
+            certain: true,
+        })
+        .collect()
+}
+
+/*
+ * Finds stores to cells that are never loaded again, and values pushed
+ * from the register that are popped without ever being used, on every path
+ * through the program. Both are found by a backward liveness analysis over
+ * the control flow discovered by `analyse`, so they share its limits: a
+ * store to an unknown address never counts as dead, and an unresolved jump
+ * is assumed to use everything.
+ */
+pub fn warnings(code: &[Instruction], analysis: &Analysis) -> Vec<Warning> {
+    let depth = |pc: usize| analysis.states[pc].as_ref().map(|t| t.stack.len());
+    let mut before: Vec<Option<Liveness>> = vec![None; code.len()];
+
+    /* iterate to a fixpoint, since liveness flows backwards around loops */
+    loop {
+        let mut changed: bool = false;
+
+        for pc in (0..code.len()).rev() {
+            let d: usize = match depth(pc) {
+                Some(t) => t,
+                None => continue,
+            };
+            let after: Liveness = live_after(code, analysis, &before, pc, d);
+            let (live, _): (Liveness, Option<WarningKind>) =
+                live_before(code[pc], analysis, pc, d, after);
+
+            if before[pc].as_ref() != Some(&live) {
+                before[pc] = Some(live);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    (0..code.len())
+        .filter_map(|pc| {
+            let d: usize = depth(pc)?;
+            let after: Liveness = live_after(code, analysis, &before, pc, d);
+            let (_, warning): (Liveness, Option<WarningKind>) =
+                live_before(code[pc], analysis, pc, d, after);
+            warning.map(|kind| Warning { pc, kind })
+        })
+        .collect()
+}
+
+fn live_after(
+    code: &[Instruction],
+    analysis: &Analysis,
+    before: &[Option<Liveness>],
+    pc: usize,
+    depth: usize,
+) -> Liveness {
+    let instruction: Instruction = code[pc];
+    let (pops, pushes): (usize, usize) = stack_effect(instruction);
+    let depth_after: usize = (depth + pushes).saturating_sub(pops);
+    let state: Option<&AbstractState> = analysis.states[pc].as_ref();
+
+    let successor: Option<usize> = match instruction {
+        Instruction::Halt => None,
+        Instruction::Jump => {
+            let target: Option<Word> = state
+                .and_then(|t| t.stack.last())
+                .and_then(|t| t.as_exact());
+
+            match target {
+                Some(t) => word_to_usize(t),
+                None => return Liveness::everything(depth_after),
+            }
+        }
+        _ => Some(pc + 1),
+    };
+
+    match successor.and_then(|t| before.get(t)) {
+        Some(Some(t)) => t.clone(),
+        /* not yet visited on this pass; assume nothing until it is */
+        Some(None) => Liveness {
+            stack: vec![false; depth_after],
+            ..Default::default()
+        },
+        None => Liveness::exit(depth_after),
+    }
+}
+
+fn live_before(
+    instruction: Instruction,
+    analysis: &Analysis,
+    pc: usize,
+    depth: usize,
+    after: Liveness,
+) -> (Liveness, Option<WarningKind>) {
+    let (pops, _): (usize, usize) = stack_effect(instruction);
+    let kept: usize = depth.saturating_sub(pops);
+    let state: Option<&AbstractState> = analysis.states[pc].as_ref();
+    let operand = |i: usize| {
+        state
+            .and_then(|t| t.stack.len().checked_sub(i + 1))
+            .and_then(|i| state.map(|t| t.stack[i]))
+            .unwrap_or(Interval::TOP)
+    };
+
+    let mut live: Liveness = Liveness {
+        reg: after.reg,
+        stack: after.stack.iter().take(kept).copied().collect(),
+        memory: after.memory.clone(),
+        all_memory: after.all_memory,
+    };
+    live.stack.resize(depth, true);
+    let mut warning: Option<WarningKind> = None;
+
+    match instruction {
+        Instruction::Set(_) => live.reg = false,
+        Instruction::Push => {
+            let used: bool = after.stack.get(depth).copied().unwrap_or(true);
+            live.reg = after.reg || used;
+
+            if !used {
+                warning = Some(WarningKind::UnusedValue);
+            }
+        }
+        Instruction::Pop => {
+            live.reg = false;
+
+            if let Some(t) = live.stack.last_mut() {
+                *t = after.reg;
+            }
+        }
+        Instruction::Load => live.read(operand(0)),
+        Instruction::Jump => {
+            if let Some(t) = live.stack.last_mut() {
+                *t = true;
+            }
+        }
+        Instruction::Store => {
+            if let Some(address) = operand(0).as_exact() {
+                if !after.all_memory && !after.memory.contains(&address) {
+                    warning = Some(WarningKind::DeadStore);
+                }
+
+                live.memory.remove(&address);
+            }
+        }
+        Instruction::CasWord | Instruction::FetchAdd => live.read(operand(0)),
+        _ => {}
+    }
+
+    (live, warning)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANY: Interval = Interval::TOP;
+
+    fn exact(value: Word) -> Interval {
+        Interval::exact(value)
+    }
+
+    fn findings(analysis: &Analysis) -> Vec<(usize, FindingKind, bool)> {
+        analysis
+            .findings
+            .iter()
+            .map(|t| (t.pc, t.kind, t.certain))
+            .collect()
+    }
+
+    /*
+     * Analyses `instruction` over `stack`, listed top first, returning what
+     * it found and the stack (top first) afterwards, if execution goes on
+     */
+    fn step(
+        instruction: Instruction,
+        stack: &[Interval],
+    ) -> (Vec<(FindingKind, bool)>, Option<Vec<Interval>>) {
+        let initial: AbstractState = AbstractState {
+            stack: stack.iter().rev().copied().collect(),
+            ..AbstractState::unknown(0)
+        };
+        let analysis: Analysis =
+            analyse(&[instruction, Instruction::Halt], initial);
+
+        (
+            analysis
+                .findings
+                .iter()
+                .map(|t| (t.kind, t.certain))
+                .collect(),
+            analysis.states[1]
+                .as_ref()
+                .map(|t| t.stack.iter().rev().copied().collect()),
+        )
+    }
+
+    /* increments mem[16] forever, wrapping if `wrap` */
+    fn counting_loop(wrap: bool) -> Vec<Instruction> {
+        let mut code: Vec<Instruction> = vec![
+            Instruction::Set(0),
+            Instruction::Push,
+            /* the loop starts here, discarding the jump target */
+            Instruction::Pop,
+            Instruction::Set(16),
+            Instruction::Push,
+            Instruction::Load,
+            Instruction::Set(1),
+            Instruction::Push,
+            Instruction::Add,
+            Instruction::Set(16),
+            Instruction::Push,
+            Instruction::Store,
+            Instruction::Set(2),
+            Instruction::Push,
+            Instruction::Jump,
+        ];
+
+        if wrap {
+            code.insert(8, Instruction::Wrap);
+        }
+
+        code
+    }
+
+    /* the range of mem[16] at the head of `counting_loop` */
+    fn counter(analysis: &Analysis) -> Option<Interval> {
+        analysis.states[2]
+            .as_ref()
+            .and_then(|t| t.memory.get(&16))
+            .copied()
+    }
+
+    #[test]
+    fn test_safe_loop_is_proven() {
+        let analysis: Analysis =
+            analyse(&counting_loop(true), AbstractState::unknown(0));
+
+        assert!(analysis.proven(), "{:?}", analysis.findings);
+        /* the joins at the loop head only stop once the counter widens */
+        assert_eq!(counter(&analysis), Some(ANY));
+        assert_eq!(analysis.states[2].as_ref().map(|t| t.stack.len()), Some(1));
+    }
+
+    #[test]
+    fn test_loop_widens_to_a_possible_overflow() {
+        let analysis: Analysis =
+            analyse(&counting_loop(false), AbstractState::unknown(0));
+
+        assert_eq!(
+            findings(&analysis),
+            vec![(8, FindingKind::Overflow, false)]
+        );
+        assert_eq!(counter(&analysis).map(|t| t.hi), Some(Word::MAX));
+    }
+
+    #[test]
+    fn test_reachable_division_by_zero() {
+        let code: Vec<Instruction> = vec![
+            Instruction::Set(0),
+            Instruction::Push,
+            Instruction::Set(5),
+            Instruction::Push,
+            Instruction::Div,
+            /* never reached, so never reported */
+            Instruction::Set(0),
+            Instruction::Push,
+            Instruction::Set(5),
+            Instruction::Push,
+            Instruction::Div,
+        ];
+        let analysis: Analysis = analyse(&code, AbstractState::unknown(0));
+
+        assert!(!analysis.proven());
+        assert_eq!(
+            findings(&analysis),
+            vec![(4, FindingKind::DivisionByZero, true)]
+        );
+    }
+
+    #[test]
+    fn test_possible_division_by_zero() {
+        assert_eq!(
+            step(Instruction::Div, &[exact(10), Interval::new(0, 5)]),
+            (
+                vec![(FindingKind::DivisionByZero, false)],
+                Some(vec![Interval::new(2, 10)])
+            )
+        );
+        assert_eq!(
+            step(Instruction::Mod, &[ANY, Interval::new(1, 4)]),
+            (vec![], Some(vec![Interval::new(0, 3)]))
+        );
+    }
+
+    #[test]
+    fn test_unsigned_overflow() {
+        assert_eq!(
+            step(Instruction::Add, &[exact(Word::MAX), exact(1)]),
+            (vec![(FindingKind::Overflow, true)], None)
+        );
+        assert_eq!(
+            step(Instruction::Add, &[Interval::new(0, 9), exact(1)]),
+            (vec![], Some(vec![Interval::new(1, 10)]))
+        );
+        assert_eq!(
+            step(Instruction::Sub, &[Interval::new(0, 9), exact(1)]),
+            (
+                vec![(FindingKind::Overflow, false)],
+                Some(vec![Interval::new(0, 8)])
+            )
+        );
+    }
+
+    #[test]
+    fn test_signed() {
+        let min: Word = from_signed(SignedWord::MIN);
+        let minus_one: Word = from_signed(-1);
+
+        assert_eq!(
+            step(Instruction::IDiv, &[exact(min), exact(minus_one)]),
+            (vec![(FindingKind::Overflow, true)], None)
+        );
+        assert_eq!(
+            step(Instruction::IMod, &[exact(min), exact(minus_one)]),
+            (vec![], Some(vec![exact(0)]))
+        );
+        assert_eq!(
+            step(Instruction::IDiv, &[exact(from_signed(-7)), exact(2)]),
+            (vec![], Some(vec![exact(from_signed(-3))]))
+        );
+        assert_eq!(
+            step(Instruction::IDiv, &[ANY, Interval::new(0, 3)]),
+            (vec![(FindingKind::DivisionByZero, false)], Some(vec![ANY]))
+        );
+        /* only MIN / -1 overflows a division, and a remainder never does */
+        assert_eq!(
+            step(Instruction::IDiv, &[ANY, Interval::new(1, 3)]),
+            (vec![], Some(vec![ANY]))
+        );
+        assert_eq!(
+            step(Instruction::IDiv, &[ANY, ANY]),
+            (
+                vec![
+                    (FindingKind::Overflow, false),
+                    (FindingKind::DivisionByZero, false)
+                ],
+                Some(vec![ANY])
+            )
+        );
+        assert_eq!(
+            step(Instruction::IMod, &[ANY, Interval::new(1, 3)]),
+            (vec![], Some(vec![ANY]))
+        );
+    }
+
+    #[test]
+    fn test_div_rem() {
+        /* operands are the high word, low word and divisor */
+        assert_eq!(
+            step(Instruction::DivRem, &[exact(0), exact(7), exact(2)]),
+            (vec![], Some(vec![exact(3), exact(1)]))
+        );
+        assert_eq!(
+            step(Instruction::DivRem, &[exact(2), exact(0), exact(2)]),
+            (vec![(FindingKind::Overflow, true)], None)
+        );
+        assert_eq!(
+            step(Instruction::DivRem, &[exact(0), exact(7), exact(0)]),
+            (vec![(FindingKind::DivisionByZero, true)], None)
+        );
+        assert_eq!(
+            step(
+                Instruction::DivRem,
+                &[Interval::new(4, 9), ANY, Interval::new(2, 4)]
+            ),
+            (vec![(FindingKind::Overflow, true)], None)
+        );
+        assert_eq!(
+            step(
+                Instruction::DivRem,
+                &[Interval::new(0, 3), ANY, Interval::new(0, 4)]
+            ),
+            (
+                vec![
+                    (FindingKind::Overflow, false),
+                    (FindingKind::DivisionByZero, false)
+                ],
+                Some(vec![ANY, Interval::new(0, 3)])
+            )
+        );
+    }
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(mask(0), 0);
+        assert_eq!(mask(1), 1);
+        assert_eq!(mask(5), 7);
+        assert_eq!(mask(8), 15);
+        assert_eq!(mask(Word::MAX), Word::MAX);
+        assert_eq!(
+            step(Instruction::Or, &[Interval::new(1, 5), Interval::new(2, 8)]),
+            (vec![], Some(vec![Interval::new(2, 15)]))
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let code: Vec<Instruction> = vec![
+            /* mem[16] = 5 is overwritten, and memory is not a result */
+            Instruction::Set(5),
+            Instruction::Push,
+            Instruction::Set(16),
+            Instruction::Push,
+            Instruction::Store,
+            Instruction::Set(6),
+            Instruction::Push,
+            Instruction::Set(16),
+            Instruction::Push,
+            Instruction::Store,
+            /* pushed, then popped into a register that is overwritten */
+            Instruction::Push,
+            Instruction::Pop,
+            Instruction::Set(0),
+            Instruction::Halt,
+        ];
+        let analysis: Analysis = analyse(&code, AbstractState::unknown(0));
+
+        assert!(analysis.proven());
+        assert_eq!(
+            warnings(&code, &analysis),
+            vec![
+                Warning {
+                    pc: 4,
+                    kind: WarningKind::DeadStore,
+                },
+                Warning {
+                    pc: 9,
+                    kind: WarningKind::DeadStore,
+                },
+                Warning {
+                    pc: 10,
+                    kind: WarningKind::UnusedValue,
+                },
+            ]
+        );
+        /* a store read back on the next iteration is live */
+        assert!(warnings(
+            &counting_loop(true),
+            &analyse(&counting_loop(true), AbstractState::unknown(0))
+        )
+        .iter()
+        .all(|t| t.kind != WarningKind::DeadStore));
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::common::types::{from_signed, parse_word, SignedWord, Word};
+use crate::core::code::{Code, VecCode};
+use crate::core::diagnostic::offsets;
+use crate::core::instruction::Instruction;
+
+/*
+ * A textual assembly format. Each line holds at most one instruction,
+ * given by its mnemonic (in any case), optionally preceded by a label
+ * definition and followed by a `;` or `#` comment:
+ *
+ *     start:  Set 10      ; decimal, 0x-prefixed hex or negative literals
+ *             Push
+ *     loop:   Set start   ; a label stands for its instruction index
+ *             Jump
+ *
+ * Only Set takes an operand. Labels resolve to instruction indices rather
+ * than byte offsets, since that is what the program counter counts.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmError {
+    UnknownMnemonic(usize, String),
+    MissingOperand(usize),
+    UnexpectedOperand(usize),
+    InvalidOperand(usize, String),
+    InvalidLabel(usize, String),
+    DuplicateLabel(usize, String),
+    UndefinedLabel(usize, String),
+}
+
+/* an operand waiting on labels that may not have been defined yet */
+enum Operand<'a> {
+    Literal(Word),
+    Label(&'a str),
+}
+
+pub fn assemble(source: &str) -> Result<Code, AsmError> {
+    let mut labels: HashMap<&str, Word> = HashMap::new();
+    let mut parsed: Vec<(usize, Instruction, Option<Operand>)> = vec![];
+
+    for (i, raw_line) in source.lines().enumerate() {
+        let line_number: usize = i + 1;
+        let mut line: &str = strip_comment(raw_line).trim();
+
+        if let Some((label, rest)) = line.split_once(':') {
+            let label: &str = label.trim();
+
+            if !is_label(label) {
+                return Err(AsmError::InvalidLabel(
+                    line_number,
+                    label.to_string(),
+                ));
+            }
+
+            if labels.insert(label, parsed.len() as Word).is_some() {
+                return Err(AsmError::DuplicateLabel(
+                    line_number,
+                    label.to_string(),
+                ));
+            }
+
+            line = rest.trim();
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operand): (&str, Option<&str>) =
+            match line.split_once(char::is_whitespace) {
+                Some((m, o)) => (m, Some(o.trim())),
+                None => (line, None),
+            };
+        let instruction: Instruction = Instruction::from_mnemonic(mnemonic)
+            .ok_or_else(|| {
+                AsmError::UnknownMnemonic(line_number, mnemonic.to_string())
+            })?;
+
+        let operand: Option<Operand> = match (instruction, operand) {
+            (Instruction::Set(_), Some(t)) => {
+                Some(parse_operand(line_number, t)?)
+            }
+            (Instruction::Set(_), None) => {
+                return Err(AsmError::MissingOperand(line_number))
+            }
+            (_, Some(_)) => {
+                return Err(AsmError::UnexpectedOperand(line_number))
+            }
+            (_, None) => None,
+        };
+
+        parsed.push((line_number, instruction, operand));
+    }
+
+    let mut code: Vec<Instruction> = Vec::with_capacity(parsed.len());
+
+    for (line_number, instruction, operand) in parsed {
+        code.push(match operand {
+            Some(Operand::Literal(t)) => Instruction::Set(t),
+            Some(Operand::Label(t)) => {
+                Instruction::Set(*labels.get(t).ok_or_else(|| {
+                    AsmError::UndefinedLabel(line_number, t.to_string())
+                })?)
+            }
+            None => instruction,
+        });
+    }
+
+    Ok(VecCode(code))
+}
+
+/*
+ * Lists a program in the assembly format, with each instruction's index
+ * and byte offset in a trailing comment. A Set immediately followed by a
+ * Jump to a valid index is shown with a label at the target, so control
+ * flow reads naturally; the listing assembles back to the same program.
+ */
+pub fn disassemble(code: &Code) -> String {
+    let targets: BTreeSet<Word> = code
+        .0
+        .windows(2)
+        .filter_map(|t| match t {
+            [Instruction::Set(x), Instruction::Jump]
+                if (*x as usize) < code.0.len() =>
+            {
+                Some(*x)
+            }
+            _ => None,
+        })
+        .collect();
+    let offsets: Vec<usize> = offsets(&code.0);
+    let mut out: String = String::new();
+
+    for (pc, instruction) in code.0.iter().enumerate() {
+        if targets.contains(&(pc as Word)) {
+            out.push_str(&format!("L{}:\n", pc));
+        }
+
+        let next: Option<&Instruction> = code.0.get(pc + 1);
+        let text: String = match (instruction, next) {
+            (Instruction::Set(x), Some(Instruction::Jump))
+                if targets.contains(x) =>
+            {
+                format!("Set L{}", x)
+            }
+            (Instruction::Set(x), _) => format!("Set {}", x),
+            (t, _) => t.mnemonic().to_string(),
+        };
+
+        out.push_str(&format!(
+            "    {:<23} ; {:>5} @ {:#06x}\n",
+            text, pc, offsets[pc]
+        ));
+    }
+
+    out
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find([';', '#']) {
+        Some(t) => &line[..t],
+        None => line,
+    }
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|t| t.is_ascii_alphabetic() || t == '_')
+        && chars.all(|t| t.is_ascii_alphanumeric() || t == '_')
+}
+
+fn parse_operand(
+    line_number: usize,
+    operand: &str,
+) -> Result<Operand<'_>, AsmError> {
+    if is_label(operand) {
+        return Ok(Operand::Label(operand));
+    }
+
+    let literal: Option<Word> = match operand.strip_prefix('-') {
+        Some(t) => t
+            .parse::<SignedWord>()
+            .ok()
+            .and_then(|t| t.checked_neg())
+            .map(from_signed),
+        None => parse_word(operand),
+    };
+
+    literal.map(Operand::Literal).ok_or_else(|| {
+        AsmError::InvalidOperand(line_number, operand.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let code: Code = VecCode(vec![
+            Instruction::Set(10),
+            Instruction::Push,
+            Instruction::Set(0xffff_ffff_ffff_ffff),
+            Instruction::Pop,
+            Instruction::Set(1),
+            Instruction::Jump,
+            Instruction::Set(7),
+            Instruction::Set(0),
+            Instruction::Jump,
+            Instruction::Halt,
+        ]);
+        let listing: String = disassemble(&code);
+
+        /* the jump targets are shown as labels */
+        assert!(listing.contains("L1:"));
+        assert!(listing.contains("Set L1"));
+        assert!(listing.contains("L0:"));
+        assert_eq!(assemble(&listing).map(|t| t.0), Ok(code.0));
+    }
+
+    #[test]
+    fn test_labels() {
+        let source: &str = "
+            start:  Set end     ; forward reference
+                    Jump
+            loop:   Set -1
+            end:    Set loop    # backward reference
+                    Set start
+        ";
+
+        assert_eq!(
+            assemble(source).map(|t| t.0),
+            Ok(vec![
+                Instruction::Set(3),
+                Instruction::Jump,
+                Instruction::Set(Word::MAX),
+                Instruction::Set(2),
+                Instruction::Set(0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            assemble("a: Nop\na: Nop").map(|t| t.0),
+            Err(AsmError::DuplicateLabel(2, "a".to_string()))
+        );
+        assert_eq!(
+            assemble("Set 0x").map(|t| t.0),
+            Err(AsmError::InvalidOperand(1, "0x".to_string()))
+        );
+        assert_eq!(
+            assemble("Set 18446744073709551616").map(|t| t.0),
+            Err(AsmError::InvalidOperand(
+                1,
+                "18446744073709551616".to_string()
+            ))
+        );
+        assert_eq!(
+            assemble("Set nowhere").map(|t| t.0),
+            Err(AsmError::UndefinedLabel(1, "nowhere".to_string()))
+        );
+        assert_eq!(
+            assemble("Set").map(|t| t.0),
+            Err(AsmError::MissingOperand(1))
+        );
+        assert_eq!(
+            assemble("Push 1").map(|t| t.0),
+            Err(AsmError::UnexpectedOperand(1))
+        );
+        assert_eq!(
+            assemble("Frob").map(|t| t.0),
+            Err(AsmError::UnknownMnemonic(1, "Frob".to_string()))
+        );
+        assert_eq!(
+            assemble("1x: Nop").map(|t| t.0),
+            Err(AsmError::InvalidLabel(1, "1x".to_string()))
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::common::types::Word;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CacheConfigError {
+    Malformed(String),
+    ZeroParameter,
+    Indivisible,
+    /* the words in a set do not fit in a `usize` */
+    Overflow,
+    /* more sets than `MAX_CACHE_SETS` */
+    TooManySets(usize),
+}
+
+/* the most sets a simulated cache may have, bounding its allocation */
+pub const MAX_CACHE_SETS: usize = 1 << 20;
+
+/*
+ * Geometry of a simulated cache, measured in words (memory is word-addressed,
+ * so a "line" of eight holds eight consecutive addresses).
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CacheConfig {
+    pub size: usize,
+    pub associativity: usize,
+    pub line_size: usize,
+}
+
+impl CacheConfig {
+    /* the number of sets, or why the geometry is not a valid cache */
+    pub fn sets(&self) -> Result<usize, CacheConfigError> {
+        if self.size == 0 || self.associativity == 0 || self.line_size == 0 {
+            return Err(CacheConfigError::ZeroParameter);
+        }
+
+        let set_size: usize = self
+            .associativity
+            .checked_mul(self.line_size)
+            .ok_or(CacheConfigError::Overflow)?;
+
+        if !self.size.is_multiple_of(set_size) {
+            return Err(CacheConfigError::Indivisible);
+        }
+
+        match self.size / set_size {
+            t if t > MAX_CACHE_SETS => Err(CacheConfigError::TooManySets(t)),
+            t => Ok(t),
+        }
+    }
+}
+
+/* parses `SIZE,ASSOCIATIVITY,LINE_SIZE`, e.g. `256,4,8` */
+impl TryFrom<&str> for CacheConfig {
+    type Error = CacheConfigError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let fields: Vec<usize> = value
+            .split(',')
+            .map(|t| t.trim().parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| CacheConfigError::Malformed(value.to_string()))?;
+
+        let config: Self = match fields[..] {
+            [size, associativity, line_size] => Self {
+                size,
+                associativity,
+                line_size,
+            },
+            _ => return Err(CacheConfigError::Malformed(value.to_string())),
+        };
+
+        config.sets()?;
+        Ok(config)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Way {
+    tag: Word,
+    last_used: u64,
+}
+
+/*
+ * A set-associative cache with least-recently-used replacement. Only the
+ * tags are simulated; the data itself always lives in the memory backend.
+ */
+#[derive(Clone, Debug)]
+pub struct Cache {
+    config: CacheConfig,
+    sets: Vec<Vec<Way>>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl Cache {
+    pub fn new(config: CacheConfig) -> Result<Self, CacheConfigError> {
+        Ok(Self {
+            config,
+            sets: vec![vec![]; config.sets()?],
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /* simulates an access to `address`, returning whether it hit */
+    pub fn access(&mut self, address: Word) -> bool {
+        let line: Word = address / self.config.line_size as Word;
+        let index: usize = (line % self.sets.len() as Word) as usize;
+        let tag: Word = line / self.sets.len() as Word;
+        let set: &mut Vec<Way> = &mut self.sets[index];
+
+        self.clock += 1;
+
+        if let Some(way) = set.iter_mut().find(|t| t.tag == tag) {
+            way.last_used = self.clock;
+            self.hits += 1;
+            return true;
+        }
+
+        let way: Way = Way {
+            tag,
+            last_used: self.clock,
+        };
+
+        if set.len() < self.config.associativity {
+            set.push(way);
+        } else if let Some(victim) = set.iter_mut().min_by_key(|t| t.last_used)
+        {
+            *victim = way;
+        }
+
+        self.misses += 1;
+        false
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn accesses(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.accesses() == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.accesses() as f64
+        }
+    }
+
+    pub fn report(&self) -> String {
+        format!(
+            "cache: {} accesses, {} hits, {} misses ({:.2}% hit rate)",
+            self.accesses(),
+            self.hits,
+            self.misses,
+            100.0 * self.hit_rate()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            CacheConfig::try_from("256,4,8"),
+            Ok(CacheConfig {
+                size: 256,
+                associativity: 4,
+                line_size: 8,
+            })
+        );
+        assert_eq!(
+            CacheConfig::try_from("256,0,8"),
+            Err(CacheConfigError::ZeroParameter)
+        );
+        assert_eq!(
+            CacheConfig::try_from("256,3,8"),
+            Err(CacheConfigError::Indivisible)
+        );
+        assert!(matches!(
+            CacheConfig::try_from("256,4"),
+            Err(CacheConfigError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_size_overflow() {
+        assert_eq!(
+            CacheConfig::try_from("4,4294967296,4294967296"),
+            Err(CacheConfigError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_too_many_sets() {
+        assert_eq!(
+            CacheConfig::try_from("1152921504606846976,1,1"),
+            Err(CacheConfigError::TooManySets(1152921504606846976))
+        );
+
+        let config: CacheConfig = CacheConfig {
+            size: usize::MAX,
+            associativity: 1,
+            line_size: 1,
+        };
+        assert!(Cache::new(config).is_err());
+    }
+
+    #[test]
+    fn test_lru_replacement() {
+        /* one set of two ways, with one-word lines */
+        let mut cache: Cache =
+            Cache::new(CacheConfig::try_from("2,2,1").expect("valid"))
+                .expect("valid");
+
+        assert!(!cache.access(0));
+        assert!(!cache.access(1));
+        assert!(cache.access(0));
+        assert!(!cache.access(2));
+        assert!(cache.access(0));
+        assert!(!cache.access(1));
+        assert_eq!((cache.hits(), cache.misses()), (2, 4));
+    }
+}
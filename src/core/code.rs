@@ -1,20 +1,167 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Read;
+
 use crate::common::types::word_bytes;
 use crate::core::instruction::{Instruction, InstructionParseError};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct VecCode(pub Vec<Instruction>);
 
-#[allow(dead_code)]
+/*
+ * A source of instructions, indexed by position in the instruction stream
+ * (which is what the program counter counts).
+ */
+pub trait Program {
+    /* the instruction at `index`, decoding it first if necessary */
+    fn fetch(
+        &mut self,
+        index: usize,
+    ) -> Result<Option<Instruction>, CodeParseError>;
+
+    /* the instruction at `index` if it has already been decoded */
+    fn decoded(&self, index: usize) -> Option<Instruction>;
+
+    /*
+     * The most recent decoding failure, if any. Programs decoded up front
+     * have none, having failed to load instead.
+     */
+    fn last_error(&self) -> Option<CodeParseError> {
+        None
+    }
+}
+
+impl Program for VecCode {
+    fn fetch(
+        &mut self,
+        index: usize,
+    ) -> Result<Option<Instruction>, CodeParseError> {
+        Ok(self.0.get(index).copied())
+    }
+
+    fn decoded(&self, index: usize) -> Option<Instruction> {
+        self.0.get(index).copied()
+    }
+}
+
+/*
+ * A program that is decoded on demand. Locating an instruction only
+ * requires skipping over the lengths of those before it, so the raw bytes
+ * are scanned lazily and each instruction is fully decoded the first time
+ * it is fetched, then cached by its byte offset.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct LazyCode {
+    bytes: Vec<u8>,
+    offsets: Vec<usize>,
+    scanned: usize,
+    cache: HashMap<usize, Instruction>,
+    error: Option<CodeParseError>,
+}
+
+impl LazyCode {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            ..Default::default()
+        }
+    }
+
+    pub fn decoded_count(&self) -> usize {
+        self.cache.len()
+    }
+
+    fn instruction_len(&self, offset: usize) -> usize {
+        match self.bytes[offset] {
+            0x06 => (1 + word_bytes()).min(self.bytes.len() - offset),
+            _ => 1,
+        }
+    }
+}
+
+impl Program for LazyCode {
+    fn fetch(
+        &mut self,
+        index: usize,
+    ) -> Result<Option<Instruction>, CodeParseError> {
+        while self.offsets.len() <= index && self.scanned < self.bytes.len() {
+            self.offsets.push(self.scanned);
+            self.scanned += self.instruction_len(self.scanned);
+        }
+
+        let offset: usize = match self.offsets.get(index) {
+            Some(t) => *t,
+            None => return Ok(None),
+        };
+
+        if let Some(t) = self.cache.get(&offset) {
+            return Ok(Some(*t));
+        }
+
+        let slice: &[u8] =
+            &self.bytes[offset..offset + self.instruction_len(offset)];
+
+        match Instruction::try_from(slice) {
+            Ok(t) => {
+                self.cache.insert(offset, t);
+                Ok(Some(t))
+            }
+            Err(e) => {
+                let err: CodeParseError = CodeParseError {
+                    err: e,
+                    pos: offset,
+                };
+                self.error = Some(err);
+                Err(err)
+            }
+        }
+    }
+
+    fn decoded(&self, index: usize) -> Option<Instruction> {
+        self.offsets
+            .get(index)
+            .and_then(|t| self.cache.get(t))
+            .copied()
+    }
+
+    fn last_error(&self) -> Option<CodeParseError> {
+        self.error
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct CodeParseError {
     err: InstructionParseError,
     pos: usize,
 }
 
-impl TryFrom<&[u8]> for VecCode {
-    type Error = CodeParseError;
+impl CodeParseError {
+    pub fn error(&self) -> InstructionParseError {
+        self.err
+    }
 
-    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+/*
+ * How to treat bytes that cannot be decoded. Strict parsing rejects the
+ * whole program; lenient parsing keeps every instruction before the first
+ * undecodable byte and reports the error alongside the truncated program.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ParseMode {
+    #[default]
+    Strict,
+    Lenient,
+}
+
+impl VecCode {
+    pub fn parse(
+        data: &[u8],
+        mode: ParseMode,
+    ) -> Result<(Self, Option<CodeParseError>), CodeParseError> {
         /*
          * The sketch for this parsing code is basically:
          *
@@ -57,21 +204,168 @@ impl TryFrom<&[u8]> for VecCode {
              *         above logic!).
              */
             let (curr_slice, next_pos): (&[u8], usize) = match curr_byte {
-                0x06 => (&data[i..=(i + word_bytes())], i + word_bytes() + 1),
+                /* a literal running off the end is left for decoding to flag */
+                0x06 => match data.get(i..=(i + word_bytes())) {
+                    Some(t) => (t, i + word_bytes() + 1),
+                    None => (&data[i..], data.len()),
+                },
                 _ => (&data[i..=i], i + 1),
             };
 
             /* interpret the chosen slice and handle failure accordingly */
             match Instruction::try_from(curr_slice) {
                 Ok(t) => res.push(t),
-                Err(e) => return Err(Self::Error { err: e, pos: i }),
+                Err(e) => {
+                    let err: CodeParseError = CodeParseError { err: e, pos: i };
+
+                    return match mode {
+                        ParseMode::Strict => Err(err),
+                        ParseMode::Lenient => Ok((Self(res), Some(err))),
+                    };
+                }
             }
 
             /* jump to wherever we need to go now */
             i = next_pos;
         }
 
-        Ok(Self(res))
+        Ok((Self(res), None))
+    }
+}
+
+pub const DECODE_CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub enum CodeReadError {
+    IOError(io::Error),
+    ParseError(CodeParseError),
+}
+
+impl From<io::Error> for CodeReadError {
+    fn from(value: io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
+
+impl From<CodeParseError> for CodeReadError {
+    fn from(value: CodeParseError) -> Self {
+        Self::ParseError(value)
+    }
+}
+
+/*
+ * An incremental decoder that accepts bytes in arbitrarily-sized chunks.
+ * Bytes belonging to an instruction that straddles a chunk boundary (i.e. a
+ * SET whose literal has not fully arrived) are held back until the next
+ * chunk, so only a bounded amount of undecoded input is ever buffered.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct StreamDecoder {
+    pending: Vec<u8>,
+    offset: usize,
+}
+
+impl StreamDecoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn feed(
+        &mut self,
+        chunk: &[u8],
+        out: &mut Vec<Instruction>,
+    ) -> Result<(), CodeParseError> {
+        self.pending.extend_from_slice(chunk);
+
+        let mut i: usize = 0;
+
+        while i < self.pending.len() {
+            let len: usize = match self.pending[i] {
+                0x06 => 1 + word_bytes(),
+                _ => 1,
+            };
+
+            /* wait for the rest of this instruction */
+            if i + len > self.pending.len() {
+                break;
+            }
+
+            match Instruction::try_from(&self.pending[i..i + len]) {
+                Ok(t) => out.push(t),
+                Err(e) => {
+                    return Err(CodeParseError {
+                        err: e,
+                        pos: self.offset + i,
+                    })
+                }
+            }
+
+            i += len;
+        }
+
+        self.pending.drain(..i);
+        self.offset += i;
+        Ok(())
+    }
+
+    /* signals the end of input, failing if an instruction is incomplete */
+    pub fn finish(self) -> Result<(), CodeParseError> {
+        match self.pending.is_empty() {
+            true => Ok(()),
+            false => match Instruction::try_from(self.pending.as_slice()) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(CodeParseError {
+                    err: e,
+                    pos: self.offset,
+                }),
+            },
+        }
+    }
+}
+
+impl VecCode {
+    /* decodes a program from a stream without buffering all of it */
+    pub fn from_reader<R: Read>(
+        mut reader: R,
+        mode: ParseMode,
+    ) -> Result<(Self, Option<CodeParseError>), CodeReadError> {
+        let mut decoder: StreamDecoder = StreamDecoder::new();
+        let mut res: Vec<Instruction> = vec![];
+        let mut buf: [u8; DECODE_CHUNK_SIZE] = [0; DECODE_CHUNK_SIZE];
+
+        let outcome: Result<(), CodeParseError> = loop {
+            let n: usize = match reader.read(&mut buf) {
+                Ok(0) => break decoder.finish(),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            if let Err(e) = decoder.feed(&buf[..n], &mut res) {
+                break Err(e);
+            }
+        };
+
+        match (outcome, mode) {
+            (Ok(()), _) => Ok((Self(res), None)),
+            (Err(e), ParseMode::Lenient) => Ok((Self(res), Some(e))),
+            (Err(e), ParseMode::Strict) => Err(e.into()),
+        }
+    }
+}
+
+impl VecCode {
+    /* the program's binary encoding, as accepted by `parse` */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(Instruction::to_bytes).collect()
+    }
+}
+
+impl TryFrom<&[u8]> for VecCode {
+    type Error = CodeParseError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse(data, ParseMode::Strict).map(|(code, _)| code)
     }
 }
 
@@ -84,3 +378,143 @@ impl TryFrom<Vec<u8>> for Code {
 }
 
 pub type Code = VecCode;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::Word;
+
+    /* Push, then a Set whose literal stops `missing` bytes short */
+    fn truncated_set(missing: usize) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![0x04, 0x06];
+        bytes.extend(vec![0xab; word_bytes() - missing]);
+        bytes
+    }
+
+    #[test]
+    fn test_parse() {
+        let (code, error): (VecCode, Option<CodeParseError>) = VecCode::parse(
+            &[0x06, 0, 0, 0, 0, 0, 0, 0, 7, 0x04, 0x01],
+            ParseMode::Strict,
+        )
+        .expect("parses");
+
+        assert_eq!(
+            code.0,
+            vec![Instruction::Set(7), Instruction::Push, Instruction::Halt]
+        );
+        assert!(error.is_none());
+    }
+
+    #[test]
+    fn test_parse_bare_set() {
+        let error: Option<CodeParseError> =
+            VecCode::parse(&[0x06], ParseMode::Strict).err();
+
+        assert_eq!(
+            error.map(|t| (t.error(), t.position())),
+            Some((InstructionParseError::MissingLiteral, 0))
+        );
+    }
+
+    #[test]
+    fn test_parse_truncated_set() {
+        for missing in 1..word_bytes() {
+            let bytes: Vec<u8> = truncated_set(missing);
+
+            let strict: Option<CodeParseError> =
+                VecCode::parse(&bytes, ParseMode::Strict).err();
+            assert_eq!(
+                strict.map(|t| (t.error(), t.position())),
+                Some((InstructionParseError::IncompleteLiteral, 1))
+            );
+
+            let (code, error): (VecCode, Option<CodeParseError>) =
+                VecCode::parse(&bytes, ParseMode::Lenient)
+                    .expect("lenient parsing keeps the prefix");
+            assert_eq!(code.0, vec![Instruction::Push]);
+            assert_eq!(
+                error.map(|t| (t.error(), t.position())),
+                Some((InstructionParseError::IncompleteLiteral, 1))
+            );
+        }
+    }
+
+    #[test]
+    fn test_lazy_fetch_truncated_set() {
+        let mut bare: LazyCode = LazyCode::new(vec![0x06]);
+        assert_eq!(
+            bare.fetch(0).err().map(|t| (t.error(), t.position())),
+            Some((InstructionParseError::MissingLiteral, 0))
+        );
+        assert!(bare.last_error().is_some());
+
+        for missing in 1..word_bytes() {
+            let mut code: LazyCode = LazyCode::new(truncated_set(missing));
+
+            assert_eq!(code.fetch(0).ok(), Some(Some(Instruction::Push)));
+            assert_eq!(
+                code.fetch(1).err().map(|t| (t.error(), t.position())),
+                Some((InstructionParseError::IncompleteLiteral, 1))
+            );
+            assert_eq!(code.fetch(2).ok(), Some(None));
+        }
+    }
+
+    /* decodes `bytes` fed to a StreamDecoder in chunks of `size` */
+    fn stream(
+        bytes: &[u8],
+        size: usize,
+    ) -> (Vec<Instruction>, Option<CodeParseError>) {
+        let mut decoder: StreamDecoder = StreamDecoder::new();
+        let mut out: Vec<Instruction> = vec![];
+
+        for chunk in bytes.chunks(size) {
+            if let Err(e) = decoder.feed(chunk, &mut out) {
+                return (out, Some(e));
+            }
+        }
+
+        (out, decoder.finish().err())
+    }
+
+    #[test]
+    fn test_stream_matches_parse() {
+        let mut program: Vec<u8> = vec![];
+        program.extend(Instruction::Set(0x0102_0304_0506_0708).to_bytes());
+        program.push(0x04);
+        program.extend(Instruction::Set(Word::MAX).to_bytes());
+        program.push(0x01);
+
+        let mut invalid: Vec<u8> = program.clone();
+        invalid.insert(9, 0xff);
+
+        let inputs: Vec<Vec<u8>> = vec![
+            program.clone(),
+            invalid,
+            [program.as_slice(), &[0x06]].concat(),
+            [program.as_slice(), &truncated_set(3)].concat(),
+        ];
+
+        for (i, bytes) in inputs.into_iter().enumerate() {
+            let (code, error): (VecCode, Option<CodeParseError>) =
+                VecCode::parse(&bytes, ParseMode::Lenient)
+                    .expect("lenient parsing does not fail");
+            /* only the first input is well formed */
+            assert_eq!(error.is_some(), i > 0);
+            let expected = (code.0, error.map(|t| (t.error(), t.position())));
+
+            for size in [1, 2, 3, bytes.len()] {
+                let (out, error) = stream(&bytes, size);
+
+                assert_eq!(
+                    (out, error.map(|t| (t.error(), t.position()))),
+                    expected,
+                    "{:02x?} in chunks of {}",
+                    bytes,
+                    size
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use crate::common::config::{Config, Table, Value};
+use crate::core::instruction::Instruction;
+use crate::core::state::State;
+
+pub const DEFAULT_LATENCY: u64 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CostModelError {
+    UnknownMnemonic(String),
+    InvalidValue(String),
+    UnknownKey(String),
+}
+
+/* a per-opcode quantity with a fallback for opcodes not listed */
+#[derive(Clone, Debug, Default)]
+pub struct OpcodeTable {
+    pub default: u64,
+    pub overrides: HashMap<u8, u64>,
+}
+
+impl OpcodeTable {
+    pub fn new(default: u64) -> Self {
+        Self {
+            default,
+            overrides: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, instruction: Instruction) -> u64 {
+        self.overrides
+            .get(&instruction.to_byte())
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /*
+     * Reads a table of the form:
+     *
+     *     default = 1
+     *     Load = 3
+     *     Mul = 4
+     *
+     * where every key other than `default` must name an instruction.
+     */
+    pub fn from_table(
+        table: Option<&Table>,
+        default: u64,
+    ) -> Result<Self, CostModelError> {
+        let mut res: Self = Self::new(default);
+
+        if let Some(table) = table {
+            for (key, value) in table {
+                let amount: u64 = as_amount(key, value)?;
+
+                if key == "default" {
+                    res.default = amount;
+                    continue;
+                }
+
+                match Instruction::from_mnemonic(key) {
+                    Some(t) => res.overrides.insert(t.to_byte(), amount),
+                    None => {
+                        return Err(CostModelError::UnknownMnemonic(
+                            key.clone(),
+                        ))
+                    }
+                };
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/*
+ * A simple timing model for simulating cycle counts. Each instruction costs
+ * its latency, plus a penalty if it accesses memory and another if it
+ * transfers control somewhere other than the next instruction.
+ *
+ * Models are read from TOML of the form:
+ *
+ *     [latency]
+ *     default = 1
+ *     Mul = 4
+ *
+ *     [penalty]
+ *     memory = 2
+ *     branch = 3
+ */
+#[derive(Clone, Debug)]
+pub struct CostModel {
+    pub latency: OpcodeTable,
+    pub memory_penalty: u64,
+    pub branch_penalty: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            latency: OpcodeTable::new(DEFAULT_LATENCY),
+            memory_penalty: 0,
+            branch_penalty: 0,
+        }
+    }
+}
+
+impl CostModel {
+    pub fn cost(
+        &self,
+        old: &State,
+        instruction: Instruction,
+        new: &State,
+    ) -> u64 {
+        let mut cycles: u64 = self.latency.get(instruction);
+
+        if instruction.touches_memory() {
+            cycles += self.memory_penalty;
+        }
+
+        if is_branch_taken(old, instruction, new) {
+            cycles += self.branch_penalty;
+        }
+
+        cycles
+    }
+
+    /* the cost of an instruction in straight-line code, where no branch is taken */
+    pub fn static_cost(&self, instruction: Instruction) -> u64 {
+        match instruction.touches_memory() {
+            true => self.latency.get(instruction) + self.memory_penalty,
+            false => self.latency.get(instruction),
+        }
+    }
+}
+
+impl TryFrom<&Config> for CostModel {
+    type Error = CostModelError;
+
+    fn try_from(config: &Config) -> Result<Self, Self::Error> {
+        let mut res: Self = Self {
+            latency: OpcodeTable::from_table(
+                config.table("latency"),
+                DEFAULT_LATENCY,
+            )?,
+            ..Default::default()
+        };
+
+        if let Some(penalties) = config.table("penalty") {
+            for (key, value) in penalties {
+                match key.as_str() {
+                    "memory" => res.memory_penalty = as_amount(key, value)?,
+                    "branch" => res.branch_penalty = as_amount(key, value)?,
+                    _ => return Err(CostModelError::UnknownKey(key.clone())),
+                }
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/*
+ * A per-opcode energy estimate, read from TOML of the form:
+ *
+ *     unit = "pJ"
+ *
+ *     [energy]
+ *     default = 10
+ *     Mul = 40
+ *
+ * Opcodes not listed (and every opcode, if `default` is omitted) cost
+ * nothing.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct EnergyTable {
+    pub energy: OpcodeTable,
+    pub unit: Option<String>,
+}
+
+impl EnergyTable {
+    pub fn cost(&self, instruction: Instruction) -> u64 {
+        self.energy.get(instruction)
+    }
+}
+
+impl TryFrom<&Config> for EnergyTable {
+    type Error = CostModelError;
+
+    fn try_from(config: &Config) -> Result<Self, Self::Error> {
+        let unit: Option<String> = match config.get("", "unit") {
+            Some(t) => match t.as_str() {
+                Some(t) => Some(t.to_string()),
+                None => {
+                    return Err(CostModelError::InvalidValue(
+                        "unit".to_string(),
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            energy: OpcodeTable::from_table(config.table("energy"), 0)?,
+            unit,
+        })
+    }
+}
+
+pub fn is_branch_taken(
+    old: &State,
+    instruction: Instruction,
+    new: &State,
+) -> bool {
+    matches!(instruction, Instruction::Jump | Instruction::JumpIf)
+        && new.pc != old.pc.wrapping_add(1)
+}
+
+fn as_amount(key: &str, value: &Value) -> Result<u64, CostModelError> {
+    value
+        .as_integer()
+        .and_then(|t| u64::try_from(t).ok())
+        .ok_or_else(|| CostModelError::InvalidValue(key.to_string()))
+}
@@ -0,0 +1,574 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, Write};
+use std::mem;
+use std::rc::Rc;
+
+use crate::common::types::{parse_word, Word};
+
+/*
+ * Where Read takes its input from and Write sends its output to. A run
+ * without a device treats both as illegal instructions.
+ */
+pub trait Device {
+    /* the next input word, blocking until one is available */
+    fn read(&mut self) -> Result<Word, DeviceError>;
+
+    /* emits a word, which must be delivered before this returns */
+    fn write(&mut self, word: Word) -> Result<(), DeviceError>;
+
+    /* whether `read` would return without blocking */
+    fn poll(&mut self) -> Result<bool, DeviceError>;
+
+    /* what Read does once `read` reports the end of the input */
+    fn eof_policy(&self) -> EofPolicy {
+        EofPolicy::Fault
+    }
+}
+
+/*
+ * A device the caller keeps a handle on, so that it can still be inspected
+ * once the machine owning the other handle has run
+ */
+impl<D: Device + ?Sized> Device for Rc<RefCell<D>> {
+    fn read(&mut self) -> Result<Word, DeviceError> {
+        self.borrow_mut().read()
+    }
+
+    fn write(&mut self, word: Word) -> Result<(), DeviceError> {
+        self.borrow_mut().write(word)
+    }
+
+    fn poll(&mut self) -> Result<bool, DeviceError> {
+        self.borrow_mut().poll()
+    }
+
+    fn eof_policy(&self) -> EofPolicy {
+        self.borrow().eof_policy()
+    }
+}
+
+impl fmt::Debug for dyn Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Device")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceError {
+    EndOfInput,
+    MalformedInput,
+    Io(io::ErrorKind),
+}
+
+impl From<io::Error> for DeviceError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value.kind())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EofPolicy {
+    /* Read faults with EndOfInput */
+    #[default]
+    Fault,
+    /* Read pushes the given word instead */
+    Sentinel(Word),
+    /*
+     * every Read pushes the word read and then a status on top of it: 1
+     * if a word was read, or 0 (over a word of 0) at the end of the input
+     */
+    Status,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EofPolicyError {
+    UnknownPolicy(String),
+}
+
+/* parses `fault`, `status` or `sentinel:WORD` */
+impl TryFrom<&str> for EofPolicy {
+    type Error = EofPolicyError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.split_once(':') {
+            Some(("sentinel", t)) => {
+                parse_word(t).map(Self::Sentinel).ok_or_else(|| {
+                    EofPolicyError::UnknownPolicy(value.to_string())
+                })
+            }
+            None if value == "fault" => Ok(Self::Fault),
+            None if value == "status" => Ok(Self::Status),
+            _ => Err(EofPolicyError::UnknownPolicy(value.to_string())),
+        }
+    }
+}
+
+/*
+ * An input stream that can tell whether reading would block. Input held in
+ * memory never blocks.
+ */
+pub trait PollRead: BufRead {
+    fn ready(&mut self) -> io::Result<bool>;
+}
+
+impl PollRead for &[u8] {
+    fn ready(&mut self) -> io::Result<bool> {
+        Ok(true)
+    }
+}
+
+/* regular files never block, so are always ready */
+impl PollRead for io::BufReader<File> {
+    fn ready(&mut self) -> io::Result<bool> {
+        Ok(true)
+    }
+}
+
+impl<P: PollRead + ?Sized> PollRead for Box<P> {
+    fn ready(&mut self) -> io::Result<bool> {
+        (**self).ready()
+    }
+}
+
+/*
+ * Standard input behind a single buffer of our own. Reads are at least as
+ * large as the standard library's internal buffer, which is therefore
+ * always bypassed, so the only input already consumed from the descriptor
+ * is in a buffer `ready` can see.
+ */
+pub struct StdinReader(io::BufReader<io::Stdin>);
+
+impl Default for StdinReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StdinReader {
+    pub fn new() -> Self {
+        Self(io::BufReader::new(io::stdin()))
+    }
+}
+
+impl io::Read for StdinReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl BufRead for StdinReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.0.consume(amount)
+    }
+}
+
+impl PollRead for StdinReader {
+    /* the end of the input also counts as ready, since Read will not block */
+    #[cfg(unix)]
+    fn ready(&mut self) -> io::Result<bool> {
+        if !self.0.buffer().is_empty() {
+            return Ok(true);
+        }
+
+        let mut fd: libc::pollfd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        /* SAFETY: `fd` is a single valid pollfd, and a zero timeout never
+         * blocks */
+        match unsafe { libc::poll(&mut fd, 1, 0) } {
+            -1 => Err(io::Error::last_os_error()),
+            n => Ok(n > 0),
+        }
+    }
+
+    /* without a way to ask, only buffered input is known to be ready */
+    #[cfg(not(unix))]
+    fn ready(&mut self) -> io::Result<bool> {
+        Ok(!self.0.buffer().is_empty())
+    }
+}
+
+/* how words are laid out on a host stream */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Framing {
+    /*
+     * one decimal word per line; input may also use `0x`-prefixed
+     * hexadecimal, and blank lines are skipped
+     */
+    #[default]
+    Text,
+    /* raw eight-byte words */
+    BigEndian,
+    LittleEndian,
+    /*
+     * each word in its own frame: a four-byte big-endian byte count, then
+     * the word in that many big-endian bytes (at most eight, so leading
+     * zero bytes may be left out on input; output always sends eight)
+     */
+    LengthPrefixed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FramingError {
+    UnknownFraming(String),
+}
+
+impl TryFrom<&str> for Framing {
+    type Error = FramingError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "text" => Ok(Self::Text),
+            "be" => Ok(Self::BigEndian),
+            "le" => Ok(Self::LittleEndian),
+            "frames" => Ok(Self::LengthPrefixed),
+            _ => Err(FramingError::UnknownFraming(value.to_string())),
+        }
+    }
+}
+
+const FRAME_HEADER_BYTES: usize = 4;
+
+/*
+ * A device over a pair of host streams. Output is flushed after every
+ * word, so a program reading from and writing to a pipe behaves as a Unix
+ * filter.
+ */
+pub struct StreamDevice<R: PollRead, W: Write> {
+    reader: R,
+    writer: W,
+    framing: Framing,
+    eof_policy: EofPolicy,
+    line: Vec<u8>,
+}
+
+impl<R: PollRead, W: Write> StreamDevice<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader,
+            writer,
+            framing: Framing::default(),
+            eof_policy: EofPolicy::default(),
+            line: vec![],
+        }
+    }
+
+    pub fn with_framing(self, framing: Framing) -> Self {
+        Self { framing, ..self }
+    }
+
+    pub fn with_eof_policy(self, eof_policy: EofPolicy) -> Self {
+        Self { eof_policy, ..self }
+    }
+
+    /*
+     * The input is read a buffer at a time rather than through `BufRead`'s
+     * helpers, which retry reads a signal interrupts: a run blocked on its
+     * input must still stop on SIGINT or SIGTERM.
+     */
+    fn read_line(&mut self) -> Result<Word, DeviceError> {
+        loop {
+            if !self.fill_line()? {
+                return Err(DeviceError::EndOfInput);
+            }
+
+            let line: &str = std::str::from_utf8(&self.line)
+                .map_err(|_| DeviceError::MalformedInput)?;
+
+            if !line.trim().is_empty() {
+                return parse_word(line).ok_or(DeviceError::MalformedInput);
+            }
+        }
+    }
+
+    /* reads up to and including the next newline, or false at the end */
+    fn fill_line(&mut self) -> Result<bool, DeviceError> {
+        self.line.clear();
+
+        loop {
+            let available: &[u8] = self.reader.fill_buf()?;
+
+            if available.is_empty() {
+                return Ok(!self.line.is_empty());
+            }
+
+            match available.iter().position(|t| *t == b'\n') {
+                Some(i) => {
+                    self.line.extend_from_slice(&available[..=i]);
+                    self.reader.consume(i + 1);
+                    return Ok(true);
+                }
+                None => {
+                    let len: usize = available.len();
+                    self.line.extend_from_slice(available);
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    /*
+     * Fills `buf`. Input ending before the first byte is the end of the
+     * input, but input ending part-way through is malformed.
+     */
+    fn read_bytes(&mut self, buf: &mut [u8]) -> Result<(), DeviceError> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Err(DeviceError::EndOfInput);
+        }
+
+        self.read_exact(buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), DeviceError> {
+        let mut filled: usize = 0;
+
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..])? {
+                0 => return Err(DeviceError::MalformedInput),
+                n => filled += n,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<Word, DeviceError> {
+        let mut header: [u8; FRAME_HEADER_BYTES] = [0; FRAME_HEADER_BYTES];
+        self.read_bytes(&mut header)?;
+
+        let len: usize = u32::from_be_bytes(header) as usize;
+        let mut bytes: [u8; mem::size_of::<Word>()] =
+            [0; mem::size_of::<Word>()];
+        let start: usize = bytes
+            .len()
+            .checked_sub(len)
+            .ok_or(DeviceError::MalformedInput)?;
+
+        /* an empty frame is the word zero */
+        if len > 0 {
+            self.read_exact(&mut bytes[start..])?;
+        }
+
+        Ok(Word::from_be_bytes(bytes))
+    }
+}
+
+impl<R: PollRead, W: Write> Device for StreamDevice<R, W> {
+    fn read(&mut self) -> Result<Word, DeviceError> {
+        let mut bytes: [u8; mem::size_of::<Word>()] =
+            [0; mem::size_of::<Word>()];
+
+        match self.framing {
+            Framing::Text => self.read_line(),
+            Framing::BigEndian => {
+                self.read_bytes(&mut bytes)?;
+                Ok(Word::from_be_bytes(bytes))
+            }
+            Framing::LittleEndian => {
+                self.read_bytes(&mut bytes)?;
+                Ok(Word::from_le_bytes(bytes))
+            }
+            Framing::LengthPrefixed => self.read_frame(),
+        }
+    }
+
+    fn write(&mut self, word: Word) -> Result<(), DeviceError> {
+        match self.framing {
+            Framing::Text => writeln!(self.writer, "{}", word)?,
+            Framing::BigEndian => self.writer.write_all(&word.to_be_bytes())?,
+            Framing::LittleEndian => {
+                self.writer.write_all(&word.to_le_bytes())?
+            }
+            Framing::LengthPrefixed => {
+                let len: u32 = mem::size_of::<Word>() as u32;
+                self.writer.write_all(&len.to_be_bytes())?;
+                self.writer.write_all(&word.to_be_bytes())?;
+            }
+        }
+
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Result<bool, DeviceError> {
+        Ok(self.reader.ready()?)
+    }
+
+    fn eof_policy(&self) -> EofPolicy {
+        self.eof_policy
+    }
+}
+
+/*
+ * A device over words held in memory, for embedding the machine: input is
+ * queued up front and output is collected for the caller to inspect.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryDevice {
+    input: VecDeque<Word>,
+    output: Vec<Word>,
+    eof_policy: EofPolicy,
+}
+
+impl MemoryDevice {
+    pub fn new(input: Vec<Word>) -> Self {
+        Self {
+            input: input.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_eof_policy(self, eof_policy: EofPolicy) -> Self {
+        Self { eof_policy, ..self }
+    }
+
+    /* every word written so far, oldest first */
+    pub fn output(&self) -> &[Word] {
+        &self.output
+    }
+}
+
+impl Device for MemoryDevice {
+    fn read(&mut self) -> Result<Word, DeviceError> {
+        self.input.pop_front().ok_or(DeviceError::EndOfInput)
+    }
+
+    fn write(&mut self, word: Word) -> Result<(), DeviceError> {
+        self.output.push(word);
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Result<bool, DeviceError> {
+        Ok(true)
+    }
+
+    fn eof_policy(&self) -> EofPolicy {
+        self.eof_policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::code::VecCode;
+    use crate::core::instruction::Instruction;
+    use crate::core::machine::{Machine, MachineError};
+    use crate::core::state::State;
+
+    /* runs `program` against `device`, which is left for inspection */
+    fn run(
+        program: &[Instruction],
+        device: &Rc<RefCell<MemoryDevice>>,
+    ) -> Result<State, MachineError> {
+        Machine::new(VecCode(program.to_vec()))
+            .with_device(Box::new(Rc::clone(device)))
+            .run()
+    }
+
+    fn device(input: &[Word], policy: EofPolicy) -> Rc<RefCell<MemoryDevice>> {
+        Rc::new(RefCell::new(
+            MemoryDevice::new(input.to_vec()).with_eof_policy(policy),
+        ))
+    }
+
+    #[test]
+    fn test_read_and_write_echo_input() {
+        let device: Rc<RefCell<MemoryDevice>> =
+            device(&[7, 9], EofPolicy::Fault);
+        let state: Result<State, MachineError> = run(
+            &[
+                Instruction::Read,
+                Instruction::Write,
+                Instruction::Read,
+                Instruction::Write,
+                Instruction::Halt,
+            ],
+            &device,
+        );
+
+        assert!(state.is_ok_and(|t| t.stack.empty()));
+        assert_eq!(device.borrow().output(), &[7, 9]);
+    }
+
+    #[test]
+    fn test_poll_pushes_ready() {
+        let device: Rc<RefCell<MemoryDevice>> = device(&[], EofPolicy::Fault);
+        let state: Result<State, MachineError> =
+            run(&[Instruction::Poll, Instruction::Halt], &device);
+
+        assert!(state.is_ok_and(|t| t.stack.elems() == [1]));
+    }
+
+    #[test]
+    fn test_write_without_operand_emits_nothing() {
+        let device: Rc<RefCell<MemoryDevice>> = device(&[], EofPolicy::Fault);
+        let state: Result<State, MachineError> =
+            run(&[Instruction::Write, Instruction::Halt], &device);
+
+        assert_eq!(state.err(), Some(MachineError::InsufficientArguments));
+        assert!(device.borrow().output().is_empty());
+    }
+
+    #[test]
+    fn test_eof_policy_fault() {
+        let device: Rc<RefCell<MemoryDevice>> = device(&[5], EofPolicy::Fault);
+        let state: Result<State, MachineError> = run(
+            &[Instruction::Read, Instruction::Read, Instruction::Halt],
+            &device,
+        );
+
+        assert_eq!(state.err(), Some(MachineError::EndOfInput));
+    }
+
+    #[test]
+    fn test_eof_policy_sentinel() {
+        let device: Rc<RefCell<MemoryDevice>> =
+            device(&[5], EofPolicy::Sentinel(42));
+        let state: Result<State, MachineError> = run(
+            &[
+                Instruction::Read,
+                Instruction::Read,
+                Instruction::Read,
+                Instruction::Halt,
+            ],
+            &device,
+        );
+
+        assert!(state.is_ok_and(|t| t.stack.elems() == [5, 42, 42]));
+    }
+
+    #[test]
+    fn test_eof_policy_status() {
+        let device: Rc<RefCell<MemoryDevice>> = device(&[5], EofPolicy::Status);
+        let state: Result<State, MachineError> = run(
+            &[Instruction::Read, Instruction::Read, Instruction::Halt],
+            &device,
+        );
+
+        /* each status sits on top of the word it describes */
+        assert!(state.is_ok_and(|t| t.stack.elems() == [5, 1, 0, 0]));
+    }
+
+    #[test]
+    fn test_device_io_without_device_is_illegal() {
+        for instruction in
+            [Instruction::Read, Instruction::Write, Instruction::Poll]
+        {
+            assert_eq!(
+                <Machine>::step(State::default(), instruction).err(),
+                Some(MachineError::IllegalInstruction)
+            );
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::common::config::{Config, Value};
+use crate::core::analysis::{Finding, FindingKind, Warning, WarningKind};
+use crate::core::instruction::Instruction;
+
+/*
+ * Verifier results in a stable, machine-readable form. Codes never change
+ * meaning once assigned:
+ *
+ *     V001  arithmetic overflow       W001  dead store
+ *     V002  division by zero          W002  unused value
+ *     V003  stack underflow
+ *     V004  stack overflow
+ *     V005  illegal instruction
+ *     V006  unresolved jump
+ *     V007  inconsistent stack depth
+ *     V008  end of input
+ *     V009  denied instruction
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    /* whether the finding holds on every run reaching it */
+    pub certain: bool,
+    pub pc: usize,
+    /* the byte offset of the instruction within the program */
+    pub offset: usize,
+    pub kind: String,
+    pub message: String,
+    /* the line `verify` prints for this diagnostic in text mode */
+    #[serde(skip)]
+    pub text: String,
+}
+
+pub const REPORT_VERSION: u8 = 1;
+
+/* everything `verify` found, as emitted by `verify --format json` */
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub version: u8,
+    /* whether no run can fault */
+    pub verified: bool,
+    /* whether nothing reported reaches the configured failure threshold */
+    pub passed: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl FindingKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Overflow => "V001",
+            Self::DivisionByZero => "V002",
+            Self::StackUnderflow => "V003",
+            Self::StackOverflow => "V004",
+            Self::IllegalInstruction => "V005",
+            Self::UnresolvedJump => "V006",
+            Self::InconsistentDepth => "V007",
+            Self::EndOfInput => "V008",
+            Self::DeniedInstruction => "V009",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Overflow => "arithmetic overflow",
+            Self::DivisionByZero => "division by zero",
+            Self::StackUnderflow => "stack underflow",
+            Self::StackOverflow => "stack overflow",
+            Self::IllegalInstruction => "illegal instruction",
+            Self::UnresolvedJump => "unresolved jump",
+            Self::InconsistentDepth => "inconsistent stack depth",
+            Self::EndOfInput => "end of input",
+            Self::DeniedInstruction => "denied instruction",
+        }
+    }
+}
+
+impl WarningKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::DeadStore => "W001",
+            Self::UnusedValue => "W002",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::DeadStore => "stored value is never loaded",
+            Self::UnusedValue => "pushed value is never used",
+        }
+    }
+}
+
+/* the byte offset of every instruction, indexed by pc */
+pub fn offsets(code: &[Instruction]) -> Vec<usize> {
+    code.iter()
+        .scan(0, |offset, t| {
+            let start: usize = *offset;
+            *offset += t.encoded_len();
+            Some(start)
+        })
+        .collect()
+}
+
+impl Diagnostic {
+    pub fn from_finding(finding: &Finding, offsets: &[usize]) -> Self {
+        Self {
+            code: finding.kind.code(),
+            severity: Severity::Error,
+            certain: finding.certain,
+            pc: finding.pc,
+            offset: offsets.get(finding.pc).copied().unwrap_or_default(),
+            kind: format!("{:?}", finding.kind),
+            message: match finding.certain {
+                true => finding.kind.describe().to_string(),
+                false => format!("possible {}", finding.kind.describe()),
+            },
+            text: format!(
+                "pc {}: {} {:?}",
+                finding.pc,
+                if finding.certain {
+                    "certain"
+                } else {
+                    "possible"
+                },
+                finding.kind
+            ),
+        }
+    }
+
+    pub fn from_warning(warning: &Warning, offsets: &[usize]) -> Self {
+        Self {
+            code: warning.kind.code(),
+            severity: Severity::Warning,
+            certain: true,
+            pc: warning.pc,
+            offset: offsets.get(warning.pc).copied().unwrap_or_default(),
+            kind: format!("{:?}", warning.kind),
+            message: warning.kind.describe().to_string(),
+            text: format!("pc {}: warning {:?}", warning.pc, warning.kind),
+        }
+    }
+}
+
+const CODES: [&str; 10] = [
+    "V001", "V002", "V003", "V004", "V005", "V006", "V007", "V008", "W001",
+    "W002",
+];
+
+/* the least severity at which `verify` fails */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailOn {
+    Warning,
+    #[default]
+    Error,
+    Never,
+}
+
+/*
+ * Adjusts what the verifier reports, read from TOML of the form:
+ *
+ *     warnings = true         # run the warning passes as with --warnings
+ *     fail_on = "warning"     # or "error" (the default), or "never"
+ *
+ *     [severity]
+ *     W001 = "error"          # promote a warning
+ *     V006 = "warning"        # demote a finding
+ *     V008 = "off"            # suppress a code everywhere
+ *
+ *     [suppress]
+ *     V001 = ["10..20", "31"] # suppress a code at these pcs
+ *     all = ["0..4"]          # suppress every code at these pcs
+ *
+ * Ranges of pcs are half-open, as elsewhere.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct VerifyConfig {
+    pub warnings: bool,
+    pub fail_on: FailOn,
+    severities: Vec<(String, Option<Severity>)>,
+    suppressions: Vec<(Option<String>, Range<usize>)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerifyConfigError {
+    UnknownCode(String),
+    UnknownKey(String),
+    InvalidValue(String),
+}
+
+impl TryFrom<&Config> for VerifyConfig {
+    type Error = VerifyConfigError;
+
+    fn try_from(config: &Config) -> Result<Self, Self::Error> {
+        let mut res: Self = Self::default();
+
+        for (table, entries) in &config.0 {
+            for (key, value) in entries {
+                match table.as_str() {
+                    "" => res.set_option(key, value)?,
+                    "severity" => res.set_severity(key, value)?,
+                    "suppress" => res.add_suppression(key, value)?,
+                    _ => {
+                        return Err(VerifyConfigError::UnknownKey(
+                            table.clone(),
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+impl VerifyConfig {
+    fn set_option(
+        &mut self,
+        key: &str,
+        value: &Value,
+    ) -> Result<(), VerifyConfigError> {
+        let invalid = || VerifyConfigError::InvalidValue(key.to_string());
+
+        match key {
+            "warnings" => {
+                self.warnings = value.as_bool().ok_or_else(invalid)?
+            }
+            "fail_on" => {
+                self.fail_on = match value.as_str() {
+                    Some("warning") => FailOn::Warning,
+                    Some("error") => FailOn::Error,
+                    Some("never") => FailOn::Never,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(VerifyConfigError::UnknownKey(key.to_string())),
+        }
+
+        Ok(())
+    }
+
+    fn set_severity(
+        &mut self,
+        code: &str,
+        value: &Value,
+    ) -> Result<(), VerifyConfigError> {
+        let severity: Option<Severity> = match value.as_str() {
+            Some("error") => Some(Severity::Error),
+            Some("warning") => Some(Severity::Warning),
+            Some("off") => None,
+            _ => return Err(VerifyConfigError::InvalidValue(code.to_string())),
+        };
+
+        self.severities.push((known_code(code)?, severity));
+        Ok(())
+    }
+
+    fn add_suppression(
+        &mut self,
+        code: &str,
+        value: &Value,
+    ) -> Result<(), VerifyConfigError> {
+        let code: Option<String> = match code {
+            "all" => None,
+            t => Some(known_code(t)?),
+        };
+        let invalid = || VerifyConfigError::InvalidValue(code_or_all(&code));
+
+        for range in value.as_array().ok_or_else(invalid)? {
+            let range: Range<usize> = range
+                .as_str()
+                .and_then(parse_pc_range)
+                .ok_or_else(invalid)?;
+            self.suppressions.push((code.clone(), range));
+        }
+
+        Ok(())
+    }
+
+    /* re-grades diagnostics, dropping any that are suppressed */
+    pub fn apply(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter_map(|mut t| {
+                let suppressed: bool =
+                    self.suppressions.iter().any(|(code, range)| {
+                        code.as_ref().is_none_or(|c| c == t.code)
+                            && range.contains(&t.pc)
+                    });
+
+                if suppressed {
+                    return None;
+                }
+
+                match self.severities.iter().find(|(code, _)| code == t.code) {
+                    Some((_, Some(severity))) => t.severity = *severity,
+                    Some((_, None)) => return None,
+                    None => {}
+                }
+
+                Some(t)
+            })
+            .collect()
+    }
+
+    /* whether a diagnostic makes `verify` fail */
+    pub fn fails(&self, diagnostic: &Diagnostic) -> bool {
+        match self.fail_on {
+            FailOn::Warning => true,
+            FailOn::Error => diagnostic.severity == Severity::Error,
+            FailOn::Never => false,
+        }
+    }
+}
+
+fn known_code(code: &str) -> Result<String, VerifyConfigError> {
+    match CODES.contains(&code) {
+        true => Ok(code.to_string()),
+        false => Err(VerifyConfigError::UnknownCode(code.to_string())),
+    }
+}
+
+fn code_or_all(code: &Option<String>) -> String {
+    code.clone().unwrap_or_else(|| "all".to_string())
+}
+
+/* parses `A..B` or a single pc `A` */
+fn parse_pc_range(value: &str) -> Option<Range<usize>> {
+    match value.split_once("..") {
+        Some((lo, hi)) => {
+            Some(lo.trim().parse::<usize>().ok()?..hi.trim().parse().ok()?)
+        }
+        None => {
+            let pc: usize = value.trim().parse().ok()?;
+            Some(pc..pc + 1)
+        }
+    }
+}
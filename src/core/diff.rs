@@ -0,0 +1,108 @@
+use crate::core::instruction::Instruction;
+
+/*
+ * One line of an instruction-level diff. Positions are (instruction index,
+ * byte offset) pairs into the old and new programs respectively.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edit {
+    Same {
+        old: (usize, usize),
+        new: (usize, usize),
+        instruction: Instruction,
+    },
+    Deleted {
+        old: (usize, usize),
+        instruction: Instruction,
+    },
+    Inserted {
+        new: (usize, usize),
+        instruction: Instruction,
+    },
+    Changed {
+        old: (usize, usize),
+        new: (usize, usize),
+        from: Instruction,
+        to: Instruction,
+    },
+}
+
+/*
+ * Aligns two decoded programs by their longest common subsequence of
+ * instructions. A deletion immediately followed by an insertion is reported
+ * as a single change, which is how an edited literal usually shows up.
+ */
+pub fn diff(old: &[Instruction], new: &[Instruction]) -> Vec<Edit> {
+    let (n, m): (usize, usize) = (old.len(), new.len());
+
+    /* lcs[i][j] is the LCS length of old[i..] and new[j..] */
+    let mut lcs: Vec<Vec<usize>> = vec![vec![0; m + 1]; n + 1];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let old_offsets: Vec<usize> = offsets(old);
+    let new_offsets: Vec<usize> = offsets(new);
+    let mut edits: Vec<Edit> = vec![];
+    let (mut i, mut j): (usize, usize) = (0, 0);
+
+    while i < n || j < m {
+        let at_old: (usize, usize) = (i, old_offsets[i]);
+        let at_new: (usize, usize) = (j, new_offsets[j]);
+
+        if i < n && j < m && old[i] == new[j] {
+            edits.push(Edit::Same {
+                old: at_old,
+                new: at_new,
+                instruction: old[i],
+            });
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(Edit::Deleted {
+                old: at_old,
+                instruction: old[i],
+            });
+            i += 1;
+        } else {
+            let inserted: Edit = match edits.last() {
+                Some(Edit::Deleted { old, instruction }) => {
+                    let changed: Edit = Edit::Changed {
+                        old: *old,
+                        new: at_new,
+                        from: *instruction,
+                        to: new[j],
+                    };
+                    edits.pop();
+                    changed
+                }
+                _ => Edit::Inserted {
+                    new: at_new,
+                    instruction: new[j],
+                },
+            };
+            edits.push(inserted);
+            j += 1;
+        }
+    }
+
+    edits
+}
+
+/* the byte offset of each instruction, plus one past the end */
+fn offsets(code: &[Instruction]) -> Vec<usize> {
+    let mut res: Vec<usize> = vec![0];
+
+    for instruction in code {
+        res.push(res[res.len() - 1] + instruction.encoded_len());
+    }
+
+    res
+}
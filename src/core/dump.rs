@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use std::io;
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::Word;
+use crate::core::machine::MachineError;
+use crate::core::state::State;
+use crate::core::trace::TraceRecord;
+
+pub const DUMP_VERSION: u8 = 1;
+pub const DEFAULT_DUMP_HISTORY: usize = 32;
+
+#[derive(Debug)]
+pub enum DumpError {
+    IOError(io::Error),
+    Malformed,
+    UnsupportedVersion(u8),
+}
+
+impl From<io::Error> for DumpError {
+    fn from(value: io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
+
+/*
+ * Everything known about a faulted run: which program it was, how it
+ * failed, the state it failed in and the steps leading up to it. The state
+ * is as it was just before the faulting instruction.
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrashDump {
+    pub version: u8,
+    pub program: String,
+    /* FNV-1a of the program's encoding, in hexadecimal */
+    pub program_hash: String,
+    /* seconds since the Unix epoch */
+    pub time: u64,
+    pub fault: MachineError,
+    pub steps: u64,
+    pub state: State,
+    /* the last steps completed before the fault, oldest first */
+    pub history: Vec<TraceRecord>,
+}
+
+impl CrashDump {
+    pub fn render(&self) -> String {
+        let mut out: String = format!(
+            "program: {} (hash {})\ntime:    {}\n",
+            self.program, self.program_hash, self.time
+        );
+
+        out.push_str(&format!(
+            "fault:   {:?} (exit code {}) at pc {} after {} steps\n",
+            self.fault,
+            self.fault.code(),
+            self.state.pc,
+            self.steps
+        ));
+        out.push_str(&format!("reg:     {:#x}\n", self.state.reg));
+        out.push_str(&format!(
+            "stack ({} words, top first):\n",
+            self.state.stack.depth()
+        ));
+
+        for (i, word) in self.state.stack.elems().iter().rev().enumerate() {
+            out.push_str(&format!("  {:>5}: {:#x}\n", i, word));
+        }
+
+        let cells: Vec<(Word, Word)> = self.state.memory.cells();
+        out.push_str(&format!("memory ({} non-zero cells):\n", cells.len()));
+
+        for (address, data) in cells {
+            out.push_str(&format!("  {:#018x}: {:#x}\n", address, data));
+        }
+
+        out.push_str(&format!(
+            "history (last {} steps, oldest first):\n",
+            self.history.len()
+        ));
+
+        for record in &self.history {
+            out.push_str(&format!(
+                "  {:>10}  {:>6}  {:<16}  reg={:#x} depth={}\n",
+                record.step,
+                record.pc,
+                format!("{:?}", record.instruction),
+                record.reg,
+                record.depth
+            ));
+        }
+
+        out
+    }
+}
+
+/* the most recent steps of a run, kept in case it faults */
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    capacity: usize,
+    records: VecDeque<TraceRecord>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, record: TraceRecord) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+
+        if self.capacity > 0 {
+            self.records.push_back(record);
+        }
+    }
+
+    pub fn records(&self) -> Vec<TraceRecord> {
+        self.records.iter().copied().collect()
+    }
+}
+
+pub fn read_dump<R: Read>(source: R) -> Result<CrashDump, DumpError> {
+    let dump: CrashDump =
+        serde_json::from_reader(source).map_err(|_| DumpError::Malformed)?;
+
+    if dump.version != DUMP_VERSION {
+        return Err(DumpError::UnsupportedVersion(dump.version));
+    }
+
+    Ok(dump)
+}
@@ -0,0 +1,159 @@
+use crate::common::rng::XorShift;
+use crate::common::types::{parse_word, Word};
+use crate::core::code::Code;
+use crate::core::machine::{Machine, MachineError};
+use crate::core::stack::Stack;
+use crate::core::state::State;
+
+pub const DEFAULT_EQUIV_STEP_LIMIT: u64 = 1_000_000;
+
+/* deepest initial stack generated for random inputs */
+const MAX_RANDOM_DEPTH: u64 = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputSpecError {
+    UnknownKind(String),
+    InvalidNumber(String),
+    Malformed(String),
+}
+
+/*
+ * Which initial states both programs are run from: either `random:N` (or
+ * `random:N:SEED`) for N pseudorandom registers and shallow stacks, or
+ * `range:A..B` for every register value in the half-open range with an
+ * empty stack.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputSpec {
+    Random { count: u64, seed: u64 },
+    Range(Word, Word),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Input {
+    pub reg: Word,
+    pub stack: Vec<Word>,
+}
+
+impl TryFrom<&str> for InputSpec {
+    type Error = InputSpecError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let number = |t: &str| {
+            parse_word(t.trim())
+                .ok_or_else(|| InputSpecError::InvalidNumber(t.to_string()))
+        };
+
+        match value.split_once(':') {
+            Some(("random", rest)) => match rest.split_once(':') {
+                Some((count, seed)) => Ok(Self::Random {
+                    count: number(count)?,
+                    seed: number(seed)?,
+                }),
+                None => Ok(Self::Random {
+                    count: number(rest)?,
+                    seed: 1,
+                }),
+            },
+            Some(("range", rest)) => match rest.split_once("..") {
+                Some((lo, hi)) => Ok(Self::Range(number(lo)?, number(hi)?)),
+                None => Err(InputSpecError::Malformed(value.to_string())),
+            },
+            Some((kind, _)) => {
+                Err(InputSpecError::UnknownKind(kind.to_string()))
+            }
+            None => Err(InputSpecError::Malformed(value.to_string())),
+        }
+    }
+}
+
+impl InputSpec {
+    pub fn inputs(&self) -> Vec<Input> {
+        match *self {
+            Self::Random { count, seed } => {
+                let mut rng: XorShift = XorShift::new(seed);
+
+                (0..count)
+                    .map(|_| {
+                        let depth: u64 =
+                            rng.next_u64() % (MAX_RANDOM_DEPTH + 1);
+
+                        Input {
+                            reg: rng.operand(),
+                            stack: (0..depth).map(|_| rng.operand()).collect(),
+                        }
+                    })
+                    .collect()
+            }
+            Self::Range(lo, hi) => {
+                (lo..hi).map(|reg| Input { reg, stack: vec![] }).collect()
+            }
+        }
+    }
+}
+
+/*
+ * Everything observable about a run. The program counter is deliberately
+ * excluded, since two equivalent programs need not be laid out alike.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Finished {
+        reg: Word,
+        stack: Vec<Word>,
+        memory: Vec<(Word, Word)>,
+    },
+    Fault(MachineError),
+}
+
+impl From<Result<State, MachineError>> for Outcome {
+    fn from(value: Result<State, MachineError>) -> Self {
+        match value {
+            Ok(t) => Self::Finished {
+                reg: t.reg,
+                stack: t.stack.elems().to_vec(),
+                memory: t.memory.cells(),
+            },
+            Err(e) => Self::Fault(e),
+        }
+    }
+}
+
+pub fn outcome(code: &Code, input: &Input, step_limit: u64) -> Outcome {
+    let stack: Stack = Stack::try_from(input.stack.clone()).unwrap_or_default();
+    let mut machine: Machine = Machine::new(code.clone())
+        .with_reg(input.reg)
+        .with_stack(stack)
+        .with_step_limit(step_limit);
+
+    machine.run().into()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub input: Input,
+    pub left: Outcome,
+    pub right: Outcome,
+}
+
+/* runs both programs from each input, returning every disagreement */
+pub fn compare(
+    left: &Code,
+    right: &Code,
+    inputs: &[Input],
+    step_limit: u64,
+) -> Vec<Difference> {
+    inputs
+        .iter()
+        .filter_map(|input| {
+            let a: Outcome = outcome(left, input, step_limit);
+            let b: Outcome = outcome(right, input, step_limit);
+
+            (a != b).then(|| Difference {
+                input: input.clone(),
+                left: a,
+                right: b,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,136 @@
+use crate::common::types::Word;
+use crate::core::analysis::Finding;
+use crate::core::code::CodeParseError;
+use crate::core::explain::{explanations, ErrorKind, Explanation};
+use crate::core::instruction::InstructionParseError;
+use crate::core::machine::MachineError;
+
+/* verifier finding `Vnnn` has the code `VERIFY_CODE_BASE + nnn` */
+pub const VERIFY_CODE_BASE: i32 = 100;
+
+/*
+ * Any error a program can meet between its bytes and the end of a run,
+ * with a single space of stable numeric codes:
+ *
+ *     10-19, 25-27, 130  runtime (`MachineError::code`)
+ *     20-24              decode (`InstructionParseError::code`)
+ *     101-199            verify (`VERIFY_CODE_BASE` plus the V number)
+ *
+ * Decode and runtime codes double as the process exit codes for those
+ * errors. Where an error happened is kept alongside it when it is known.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DreamerError {
+    Decode {
+        error: InstructionParseError,
+        /* the byte offset of the undecodable instruction */
+        offset: Option<usize>,
+    },
+    Verify(Finding),
+    Runtime {
+        error: MachineError,
+        pc: Option<Word>,
+    },
+}
+
+impl DreamerError {
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Decode { error, .. } => error.code(),
+            /* the V number is the numeric part of the diagnostic code */
+            Self::Verify(t) => {
+                VERIFY_CODE_BASE
+                    + t.kind.code()[1..].parse::<i32>().unwrap_or_default()
+            }
+            Self::Runtime { error, .. } => error.code(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Decode { error, .. } => format!("{:?}", error),
+            Self::Verify(t) => format!("{:?}", t.kind),
+            Self::Runtime { error, .. } => format!("{:?}", error),
+        }
+    }
+
+    pub fn layer(&self) -> &'static str {
+        match self {
+            Self::Decode { .. } => "decode",
+            Self::Verify(_) => "verify",
+            Self::Runtime { .. } => "runtime",
+        }
+    }
+
+    /* the explain-error entry for decode and runtime errors */
+    pub fn explanation(&self) -> Option<Explanation> {
+        let kind: ErrorKind = match self {
+            Self::Decode { error, .. } => ErrorKind::Decode(*error),
+            Self::Verify(_) => return None,
+            Self::Runtime { error, .. } => ErrorKind::Runtime(*error),
+        };
+
+        explanations().into_iter().find(|t| t.kind == kind)
+    }
+
+    pub fn render(&self) -> String {
+        let location: String = match self {
+            Self::Decode {
+                offset: Some(t), ..
+            } => format!(" at byte {}", t),
+            Self::Verify(t) => format!(" at pc {}", t.pc),
+            Self::Runtime { pc: Some(t), .. } => format!(" at pc {}", t),
+            _ => String::new(),
+        };
+        let message: String = match self {
+            Self::Verify(t) if t.certain => t.kind.describe().to_string(),
+            Self::Verify(t) => format!("possible {}", t.kind.describe()),
+            _ => self
+                .explanation()
+                .map(|t| t.meaning.to_string())
+                .unwrap_or_default(),
+        };
+
+        format!(
+            "{} ({} error {}){}: {}",
+            self.name(),
+            self.layer(),
+            self.code(),
+            location,
+            message
+        )
+    }
+}
+
+impl From<InstructionParseError> for DreamerError {
+    fn from(value: InstructionParseError) -> Self {
+        Self::Decode {
+            error: value,
+            offset: None,
+        }
+    }
+}
+
+impl From<CodeParseError> for DreamerError {
+    fn from(value: CodeParseError) -> Self {
+        Self::Decode {
+            error: value.error(),
+            offset: Some(value.position()),
+        }
+    }
+}
+
+impl From<Finding> for DreamerError {
+    fn from(value: Finding) -> Self {
+        Self::Verify(value)
+    }
+}
+
+impl From<MachineError> for DreamerError {
+    fn from(value: MachineError) -> Self {
+        Self::Runtime {
+            error: value,
+            pc: None,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::mem;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::{word_to_usize, Word};
+use crate::core::device::{Device, DeviceError};
+
+/* the most payload words a single event may declare */
+pub const MAX_EVENT_WORDS: usize = 65535;
+
+/* a structured record appended by a program, for its host to collect */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    pub topic: Word,
+    pub payload: Vec<Word>,
+}
+
+#[derive(Debug)]
+pub enum EventLogError {
+    IOError(io::Error),
+    Malformed(u64),
+}
+
+impl From<io::Error> for EventLogError {
+    fn from(value: io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
+
+/* how far the words written so far have got through the current event */
+#[derive(Clone, Debug)]
+enum Pending {
+    Topic,
+    Length(Word),
+    Payload(Event, usize),
+}
+
+/*
+ * A write-only device that frames the words a program writes into events:
+ * a topic, a payload length, then that many payload words. Each complete
+ * event is appended to the log as one line of JSON. Reads see the end of
+ * the input, and an event left unfinished when the run ends is dropped.
+ */
+pub struct EventLog<W: Write> {
+    writer: W,
+    pending: Pending,
+}
+
+impl<W: Write> EventLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            pending: Pending::Topic,
+        }
+    }
+
+    fn emit(&mut self, event: &Event) -> Result<(), DeviceError> {
+        serde_json::to_writer(&mut self.writer, event)
+            .map_err(|e| DeviceError::from(io::Error::from(e)))?;
+        writeln!(self.writer)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Device for EventLog<W> {
+    fn read(&mut self) -> Result<Word, DeviceError> {
+        Err(DeviceError::EndOfInput)
+    }
+
+    fn write(&mut self, word: Word) -> Result<(), DeviceError> {
+        self.pending = match mem::replace(&mut self.pending, Pending::Topic) {
+            Pending::Topic => Pending::Length(word),
+            Pending::Length(topic) => {
+                let len: usize = word_to_usize(word)
+                    .filter(|t| *t <= MAX_EVENT_WORDS)
+                    .ok_or(DeviceError::MalformedInput)?;
+                let event: Event = Event {
+                    topic,
+                    payload: Vec::with_capacity(len),
+                };
+
+                if len == 0 {
+                    self.emit(&event)?;
+                    Pending::Topic
+                } else {
+                    Pending::Payload(event, len)
+                }
+            }
+            Pending::Payload(mut event, len) => {
+                event.payload.push(word);
+
+                if event.payload.len() == len {
+                    self.emit(&event)?;
+                    Pending::Topic
+                } else {
+                    Pending::Payload(event, len)
+                }
+            }
+        };
+
+        Ok(())
+    }
+
+    /* reads never block, since there is never any input */
+    fn poll(&mut self) -> Result<bool, DeviceError> {
+        Ok(true)
+    }
+}
+
+/* parses an event log written by `EventLog` */
+pub fn read_events<R: Read>(source: R) -> Result<Vec<Event>, EventLogError> {
+    let mut events: Vec<Event> = vec![];
+
+    for (i, line) in BufReader::new(source).lines().enumerate() {
+        let line: String = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str(&line) {
+            Ok(t) => events.push(t),
+            Err(_) => return Err(EventLogError::Malformed(i as u64 + 1)),
+        }
+    }
+
+    Ok(events)
+}
@@ -0,0 +1,237 @@
+use crate::core::instruction::InstructionParseError;
+use crate::core::machine::MachineError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    Decode(InstructionParseError),
+    Runtime(MachineError),
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Decode(t) => format!("{:?}", t),
+            Self::Runtime(t) => format!("{:?}", t),
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Decode(t) => t.code(),
+            Self::Runtime(t) => t.code(),
+        }
+    }
+
+    pub fn layer(&self) -> &'static str {
+        match self {
+            Self::Decode(_) => "decode",
+            Self::Runtime(_) => "runtime",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Explanation {
+    pub kind: ErrorKind,
+    pub meaning: &'static str,
+    pub causes: &'static [&'static str],
+    pub rules: &'static str,
+}
+
+pub fn explanations() -> Vec<Explanation> {
+    vec![
+        Explanation {
+            kind: ErrorKind::Decode(InstructionParseError::NoData),
+            meaning: "An instruction was decoded from an empty byte slice.",
+            causes: &["An empty program file was passed to the decoder."],
+            rules: "Every instruction occupies at least one byte.",
+        },
+        Explanation {
+            kind: ErrorKind::Decode(InstructionParseError::InvalidOpcode),
+            meaning: "A byte in the program does not name any instruction.",
+            causes: &[
+                "The file is not a Dreamer program (e.g. it is text).",
+                "A SET literal was truncated or misaligned, so decoding \
+                 resumed in the middle of its data.",
+            ],
+            rules: "Opcodes are single bytes; any byte without an assigned \
+                    instruction is rejected.",
+        },
+        Explanation {
+            kind: ErrorKind::Decode(InstructionParseError::MissingLiteral),
+            meaning: "A SET opcode (0x06) was decoded without its literal.",
+            causes: &["The program ends immediately after a 0x06 byte."],
+            rules: "SET is followed by a big-endian literal one word wide.",
+        },
+        Explanation {
+            kind: ErrorKind::Decode(
+                InstructionParseError::InappropriateLiteral,
+            ),
+            meaning: "Literal bytes followed an opcode that takes none.",
+            causes: &["A multi-byte slice was decoded as a non-SET opcode."],
+            rules: "Only SET carries a literal; all other instructions are \
+                    exactly one byte.",
+        },
+        Explanation {
+            kind: ErrorKind::Decode(InstructionParseError::IncompleteLiteral),
+            meaning: "A SET literal is shorter than one word.",
+            causes: &["The program was truncated inside a SET literal."],
+            rules: "SET is followed by a big-endian literal one word wide.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::InsufficientArguments),
+            meaning: "An instruction needed more stack operands than were \
+                      present.",
+            causes: &[
+                "A missing PUSH before an arithmetic or memory instruction.",
+                "Operands consumed by an earlier instruction on another \
+                 control-flow path.",
+            ],
+            rules: "Each instruction pops a fixed number of operands (its \
+                    arity) and faults if the stack is shallower than that.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::OutOfBounds),
+            meaning: "An access fell outside the bounds of the machine.",
+            causes: &[
+                "An address or index beyond what the machine allows.",
+                "A jump to a program counter too large to index the program \
+                 on this host (e.g. above 2^32 on a 32-bit machine).",
+            ],
+            rules: "Accesses must stay within the machine's address space.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::StackFull),
+            meaning: "A push was attempted on a full stack.",
+            causes: &["Unbounded recursion or a loop that pushes without \
+                       popping."],
+            rules: "The stack holds at most 65535 words, or fewer if a \
+                    limits preset sets a stack limit.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::StackEmpty),
+            meaning: "A pop was attempted on an empty stack.",
+            causes: &["A POP with no matching PUSH."],
+            rules: "POP moves the top of the stack into the register and \
+                    requires a non-empty stack.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::ArithmeticOverflow),
+            meaning: "An arithmetic result did not fit in a word, or a \
+                      division by zero was attempted.",
+            causes: &[
+                "Subtracting a larger value from a smaller one.",
+                "Dividing by zero.",
+                "Products or sums exceeding the word size.",
+            ],
+            rules: "Arithmetic is unsigned and checked; results outside the \
+                    range of a word fault rather than wrap.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::IllegalInstruction),
+            meaning: "A decoded instruction has no implementation.",
+            causes: &["Use of an instruction that is reserved but not yet \
+                       implemented (e.g. JUMPIF, READ or WRITE)."],
+            rules: "Only implemented instructions may be executed.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::UndecodableInstruction),
+            meaning: "Execution reached bytes that do not decode to an \
+                      instruction.",
+            causes: &["A corrupt or truncated program run with on-demand \
+                       decoding, which only decodes what it executes."],
+            rules: "Every executed instruction must be well-formed.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::StepLimitExceeded),
+            meaning: "A run executed more instructions than it was allowed.",
+            causes: &["A loop that never reaches HALT or the end of the \
+                       program."],
+            rules: "Runs given a step limit fault instead of exceeding it.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::InvariantViolation),
+            meaning: "A step left the machine in a state that should be \
+                      impossible.",
+            causes: &[
+                "A bug in the implementation of an instruction.",
+                "A jump beyond the end of the program while invariants are \
+                 being checked.",
+            ],
+            rules: "With --check-invariants, every step is validated and the \
+                    first violation is reported in detail.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::MemoryLimitExceeded),
+            meaning: "A step would have taken the stack and memory past the \
+                      run's memory ceiling.",
+            causes: &[
+                "A loop that keeps pushing or writing to fresh addresses.",
+                "A ceiling too small for the program's working set, or for \
+                 the trace buffers reserved out of it.",
+            ],
+            rules: "With --memory-limit, the step that would exceed the \
+                    ceiling faults and the machine keeps its prior state.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::EndOfInput),
+            meaning: "A Read found no more input.",
+            causes: &["A program reading more words than its input holds."],
+            rules: "Read blocks until a word arrives, and faults once the \
+                    input has ended.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::DeviceFailure),
+            meaning: "The host side of a Read or Write failed.",
+            causes: &[
+                "Input that is not a word in the expected framing.",
+                "A closed or failing output stream.",
+            ],
+            rules: "Read and Write fault rather than lose or invent data.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::DeniedInstruction),
+            meaning: "The program reached an instruction the run forbids.",
+            causes: &["An opcode listed in --deny-ops, which restricts the \
+                       instruction set for sandboxing or teaching."],
+            rules: "Denied instructions fault before they execute; verify \
+                    with the same --deny-ops reports them statically.",
+        },
+        Explanation {
+            kind: ErrorKind::Runtime(MachineError::Interrupted),
+            meaning: "The run was stopped by SIGINT or SIGTERM.",
+            causes: &["Ctrl-C, or the process being asked to terminate."],
+            rules: "Interrupted runs stop between instructions, flush their \
+                    trace and may write a snapshot of the machine state.",
+        },
+    ]
+}
+
+/* looks up an explanation by error name (case-insensitive) or exit code */
+pub fn explain(query: &str) -> Option<Explanation> {
+    let code: Option<i32> = query.trim().parse::<i32>().ok();
+
+    explanations().into_iter().find(|t| match code {
+        Some(code) => t.kind.code() == code,
+        None => t.kind.name().eq_ignore_ascii_case(query.trim()),
+    })
+}
+
+impl Explanation {
+    pub fn render(&self) -> String {
+        let mut out: String = format!(
+            "{} ({} error, exit code {})\n\n{}\n\nCommon causes:\n",
+            self.kind.name(),
+            self.kind.layer(),
+            self.kind.code(),
+            self.meaning
+        );
+
+        for cause in self.causes {
+            out.push_str(&format!("  - {}\n", cause));
+        }
+
+        out.push_str(&format!("\nRule: {}\n", self.rules));
+        out
+    }
+}
@@ -0,0 +1,454 @@
+use crate::common::types::{word_to_usize, Word};
+use crate::core::filter::{tokenise, FilterParseError, Parser, Token};
+use crate::core::memory::LinearlyAddressable;
+use crate::core::state::State;
+
+/* expressions share the trace filter language's parser, and its errors */
+pub type ExprParseError = FilterParseError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvalError {
+    Parse(ExprParseError),
+    /* `stack[n]` with fewer than `n + 1` words on the stack */
+    StackIndex(Word),
+    DivisionByZero,
+    /* a value the expression was evaluated against does not hold */
+    Unavailable(&'static str),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    LogicalAnd,
+    LogicalOr,
+}
+
+/* a named value an expression can read */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Var {
+    Pc,
+    Reg,
+    Depth,
+    /* the opcode of the instruction a step executed */
+    Op,
+    /* the address that instruction accessed, if it accessed memory */
+    Addr,
+    Step,
+}
+
+/*
+ * An expression over the machine state or a step of its trace, such as
+ * `mem[16] + reg` or `op = Store && addr in 16..32`. `stack[0]` is the top
+ * of the stack and `depth` its size. Operators are those of C, except that
+ * `=` also compares, `!` negates a whole comparison, and `and`, `or` and
+ * `not` spell `&&`, `||` and `!`; comparisons and logical operators give 1 or 0. Arithmetic wraps, as it
+ * does under a Wrap prefix; only division by zero, reading below the
+ * stack and reading a value that does not hold fail, and a comparison
+ * with a value that does not hold is false.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(Word),
+    Var(Var),
+    Stack(Box<Expr>),
+    Mem(Box<Expr>),
+    Not(Box<Expr>),
+    LogicalNot(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /* `x in lo..hi`, which holds where `lo <= x < hi` */
+    In(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+/*
+ * What an expression is evaluated against. A machine state knows nothing
+ * of steps, and a trace record keeps neither the stack nor memory, so each
+ * answers `EvalError::Unavailable` for what it does not hold.
+ */
+pub trait Scope {
+    fn var(&self, var: Var) -> Result<Word, EvalError>;
+
+    /* the word `index` places below the top of the stack */
+    fn stack(&self, index: Word) -> Result<Word, EvalError>;
+
+    fn mem(&self, address: Word) -> Result<Word, EvalError>;
+}
+
+impl BinaryOp {
+    /* binding strength, loosest first, as in C */
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Self::LogicalOr => 0,
+            Self::LogicalAnd => 1,
+            Self::Or => 2,
+            Self::Xor => 3,
+            Self::And => 4,
+            Self::Eq | Self::Ne => 5,
+            Self::Lt | Self::Le | Self::Gt | Self::Ge => Expr::IN_PRECEDENCE,
+            Self::Shl | Self::Shr => 7,
+            Self::Add | Self::Sub => 8,
+            Self::Mul | Self::Div | Self::Mod => 9,
+        }
+    }
+
+    fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Self::Eq | Self::Ne | Self::Lt | Self::Le | Self::Gt | Self::Ge
+        )
+    }
+
+    fn apply(&self, a: Word, b: Word) -> Result<Word, EvalError> {
+        Ok(match self {
+            Self::Add => a.wrapping_add(b),
+            Self::Sub => a.wrapping_sub(b),
+            Self::Mul => a.wrapping_mul(b),
+            Self::Div => a.checked_div(b).ok_or(EvalError::DivisionByZero)?,
+            Self::Mod => a.checked_rem(b).ok_or(EvalError::DivisionByZero)?,
+            Self::And => a & b,
+            Self::Or => a | b,
+            Self::Xor => a ^ b,
+            /* shifting every bit out leaves zero */
+            Self::Shl => u32::try_from(b)
+                .ok()
+                .and_then(|t| a.checked_shl(t))
+                .unwrap_or(0),
+            Self::Shr => u32::try_from(b)
+                .ok()
+                .and_then(|t| a.checked_shr(t))
+                .unwrap_or(0),
+            Self::Eq => (a == b) as Word,
+            Self::Ne => (a != b) as Word,
+            Self::Lt => (a < b) as Word,
+            Self::Le => (a <= b) as Word,
+            Self::Gt => (a > b) as Word,
+            Self::Ge => (a >= b) as Word,
+            Self::LogicalAnd => (a != 0 && b != 0) as Word,
+            Self::LogicalOr => (a != 0 || b != 0) as Word,
+        })
+    }
+}
+
+impl Var {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pc" => Some(Self::Pc),
+            "reg" => Some(Self::Reg),
+            "depth" => Some(Self::Depth),
+            "op" => Some(Self::Op),
+            "addr" => Some(Self::Addr),
+            "step" => Some(Self::Step),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pc => "pc",
+            Self::Reg => "reg",
+            Self::Depth => "depth",
+            Self::Op => "op",
+            Self::Addr => "addr",
+            Self::Step => "step",
+        }
+    }
+}
+
+impl Expr {
+    /* `in` binds as tightly as the ordering comparisons */
+    pub const IN_PRECEDENCE: u8 = 6;
+
+    pub fn eval<S: Scope + ?Sized>(
+        &self,
+        scope: &S,
+    ) -> Result<Word, EvalError> {
+        match self {
+            Self::Number(x) => Ok(*x),
+            Self::Var(t) => scope.var(*t),
+            Self::Stack(index) => scope.stack(index.eval(scope)?),
+            Self::Mem(address) => scope.mem(address.eval(scope)?),
+            Self::Not(t) => Ok(!t.eval(scope)?),
+            Self::LogicalNot(t) => Ok((t.eval(scope)? == 0) as Word),
+            /* the right-hand side is only evaluated if it is needed */
+            Self::Binary(BinaryOp::LogicalAnd, a, b) => {
+                Ok((a.eval(scope)? != 0 && b.eval(scope)? != 0) as Word)
+            }
+            Self::Binary(BinaryOp::LogicalOr, a, b) => {
+                Ok((a.eval(scope)? != 0 || b.eval(scope)? != 0) as Word)
+            }
+            Self::Binary(op, a, b) if op.is_comparison() => {
+                match (a.eval(scope), b.eval(scope)) {
+                    (Ok(a), Ok(b)) => op.apply(a, b),
+                    (Err(EvalError::Unavailable(_)), _)
+                    | (_, Err(EvalError::Unavailable(_))) => Ok(0),
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                }
+            }
+            Self::Binary(op, a, b) => op.apply(a.eval(scope)?, b.eval(scope)?),
+            Self::In(x, lo, hi) => {
+                match (x.eval(scope), lo.eval(scope), hi.eval(scope)) {
+                    (Ok(x), Ok(lo), Ok(hi)) => Ok((lo <= x && x < hi) as Word),
+                    (Err(EvalError::Unavailable(_)), _, _)
+                    | (_, Err(EvalError::Unavailable(_)), _)
+                    | (_, _, Err(EvalError::Unavailable(_))) => Ok(0),
+                    (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Err(e),
+                }
+            }
+        }
+    }
+
+    /* the first subexpression, this one included, satisfying `predicate` */
+    pub fn find(&self, predicate: &dyn Fn(&Expr) -> bool) -> Option<&Expr> {
+        if predicate(self) {
+            return Some(self);
+        }
+
+        match self {
+            Self::Number(_) | Self::Var(_) => None,
+            Self::Stack(t)
+            | Self::Mem(t)
+            | Self::Not(t)
+            | Self::LogicalNot(t) => t.find(predicate),
+            Self::Binary(_, a, b) => {
+                a.find(predicate).or_else(|| b.find(predicate))
+            }
+            Self::In(x, lo, hi) => x
+                .find(predicate)
+                .or_else(|| lo.find(predicate))
+                .or_else(|| hi.find(predicate)),
+        }
+    }
+}
+
+impl Scope for State {
+    fn var(&self, var: Var) -> Result<Word, EvalError> {
+        match var {
+            Var::Pc => Ok(self.pc),
+            Var::Reg => Ok(self.reg),
+            Var::Depth => Ok(self.stack.depth() as Word),
+            Var::Op | Var::Addr | Var::Step => {
+                Err(EvalError::Unavailable(var.name()))
+            }
+        }
+    }
+
+    fn stack(&self, index: Word) -> Result<Word, EvalError> {
+        let elems: &[Word] = self.stack.elems();
+
+        word_to_usize(index)
+            .and_then(|t| elems.len().checked_sub(t + 1))
+            .map(|t| elems[t])
+            .ok_or(EvalError::StackIndex(index))
+    }
+
+    fn mem(&self, address: Word) -> Result<Word, EvalError> {
+        Ok(self.memory.read(address))
+    }
+}
+
+impl From<ExprParseError> for EvalError {
+    fn from(value: ExprParseError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+impl TryFrom<&str> for Expr {
+    type Error = ExprParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let tokens: Vec<(usize, Token)> = tokenise(value)?;
+
+        if tokens.is_empty() {
+            return Err(ExprParseError::Empty);
+        }
+
+        let mut parser: Parser = Parser::new(tokens);
+        let expr: Expr = parser.expr(0)?;
+
+        match parser.tokens.get(parser.pos) {
+            Some((offset, _)) => Err(ExprParseError::UnexpectedToken(*offset)),
+            None => Ok(expr),
+        }
+    }
+}
+
+/* an expression whose value is reported whenever a step changes it */
+#[derive(Clone, Debug)]
+pub struct Watch {
+    pub source: String,
+    expr: Expr,
+    value: Result<Word, EvalError>,
+}
+
+impl Watch {
+    pub fn new(source: &str, initial: &State) -> Result<Self, ExprParseError> {
+        let expr: Expr = Expr::try_from(source)?;
+
+        /* a state knows nothing of the step that produced it */
+        if let Some(Expr::Var(t)) = expr
+            .find(&|t| matches!(t, Expr::Var(Var::Op | Var::Addr | Var::Step)))
+        {
+            return Err(ExprParseError::UnknownField(t.name().to_string()));
+        }
+
+        let value: Result<Word, EvalError> = expr.eval(initial);
+
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+            value,
+        })
+    }
+
+    /* re-evaluates the expression, returning whether its value changed */
+    pub fn update(&mut self, state: &State) -> bool {
+        let value: Result<Word, EvalError> = self.expr.eval(state);
+        let changed: bool = value != self.value;
+        self.value = value;
+        changed
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.value {
+            Ok(t) => format!("{} = {}", self.source, t),
+            Err(e) => format!("{} = <{:?}>", self.source, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::MAX_EXPR_DEPTH;
+    use crate::core::stack::Stack;
+
+    fn eval(source: &str) -> Result<Word, EvalError> {
+        Expr::try_from(source)?.eval(&State::default())
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9));
+        assert_eq!(eval("1 << 2 + 1"), Ok(8));
+        assert_eq!(eval("1 | 2 & 0"), Ok(1));
+        assert_eq!(eval("6 ^ 3 | 1"), Ok(5));
+        assert_eq!(eval("1 + 1 == 2"), Ok(1));
+        assert_eq!(eval("1 < 2 == 1"), Ok(1));
+        assert_eq!(eval("1 == 1 && 0 || 1"), Ok(1));
+        assert_eq!(eval("10 - 3 - 2"), Ok(5));
+        assert_eq!(eval("~0 + 1"), Ok(0));
+    }
+
+    #[test]
+    fn test_not_binds_looser_than_comparisons() {
+        /* `!1 == 2` is `!(1 == 2)`, not `(!1) == 2` */
+        assert_eq!(eval("!1 == 2"), Ok(1));
+        assert_eq!(eval("not 1 = 2"), Ok(1));
+        assert_eq!(eval("!1 + 1 == 2"), Ok(0));
+        /* but more tightly than the bitwise and logical operators */
+        assert_eq!(eval("!1 & 0"), Ok(0));
+        assert_eq!(eval("not 1 && 0"), Ok(0));
+        assert_eq!(eval("!0 || 0"), Ok(1));
+        assert_eq!(eval("not 0 and not 0"), Ok(1));
+    }
+
+    #[test]
+    fn test_in() {
+        assert_eq!(eval("1 in 1..5"), Ok(1));
+        assert_eq!(eval("4 in 1..5"), Ok(1));
+        assert_eq!(eval("5 in 1..5"), Ok(0));
+        assert_eq!(eval("0 in 1..5"), Ok(0));
+        assert_eq!(eval("1 + 1 in 1..1 + 2"), Ok(1));
+        assert_eq!(eval("3 in 1..5 == 1"), Ok(1));
+        assert_eq!(eval("3 in 5..1"), Ok(0));
+        assert_eq!(
+            Expr::try_from("1 in 1"),
+            Err(ExprParseError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(eval("1 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("1 % (2 - 2)"), Err(EvalError::DivisionByZero));
+        /* the right-hand side of a logical operator may not be evaluated */
+        assert_eq!(eval("0 && 1 / 0"), Ok(0));
+        assert_eq!(eval("1 || 1 / 0"), Ok(1));
+
+        let watch: Watch =
+            Watch::new("reg / 0", &State::default()).expect("parses");
+        assert_eq!(watch.describe(), "reg / 0 = <DivisionByZero>");
+    }
+
+    #[test]
+    fn test_state_scope() {
+        let state: State = State {
+            reg: 3,
+            stack: Stack::try_from(vec![1, 2]).expect("stack fits"),
+            ..State::default()
+        };
+        let eval = |t: &str| Expr::try_from(t).map(|t| t.eval(&state));
+
+        assert_eq!(eval("stack[0] * 10 + stack[1] + reg"), Ok(Ok(24)));
+        assert_eq!(eval("depth"), Ok(Ok(2)));
+        assert_eq!(eval("stack[2]"), Ok(Err(EvalError::StackIndex(2))));
+        assert_eq!(eval("mem[16]"), Ok(Ok(0)));
+        /* a state has no step, so comparisons with it are false */
+        assert_eq!(eval("step = 0"), Ok(Ok(0)));
+        assert!(Watch::new("step", &state).is_err());
+    }
+
+    #[test]
+    fn test_too_deep() {
+        let nested = |n: usize| {
+            Expr::try_from(
+                format!("{}1{}", "(".repeat(n), ")".repeat(n)).as_str(),
+            )
+        };
+        let negated =
+            |n: usize| Expr::try_from(format!("{}1", "~".repeat(n)).as_str());
+
+        assert!(nested(MAX_EXPR_DEPTH - 1).is_ok());
+        assert!(matches!(
+            nested(MAX_EXPR_DEPTH),
+            Err(ExprParseError::TooDeep(_))
+        ));
+        assert!(negated(MAX_EXPR_DEPTH - 1).is_ok());
+        assert!(matches!(
+            negated(MAX_EXPR_DEPTH),
+            Err(ExprParseError::TooDeep(_))
+        ));
+        assert!(matches!(nested(100_000), Err(ExprParseError::TooDeep(_))));
+    }
+
+    #[test]
+    fn test_flat_chain_is_limited() {
+        /* each operator deepens everything after it, even left to right */
+        let chain =
+            |terms: usize| Expr::try_from(vec!["1"; terms].join("+").as_str());
+
+        assert_eq!(
+            chain(MAX_EXPR_DEPTH).map(|t| t.eval(&State::default())),
+            Ok(Ok(MAX_EXPR_DEPTH as Word))
+        );
+        assert!(matches!(
+            chain(MAX_EXPR_DEPTH + 1),
+            Err(ExprParseError::TooDeep(_))
+        ));
+    }
+}
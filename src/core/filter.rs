@@ -0,0 +1,443 @@
+use crate::common::types::{parse_word, Word};
+use crate::core::expr::{BinaryOp, EvalError, Expr, Scope, Var};
+use crate::core::instruction::Instruction;
+use crate::core::trace::TraceRecord;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterParseError {
+    Empty,
+    UnexpectedCharacter(usize),
+    UnexpectedToken(usize),
+    UnexpectedEnd,
+    UnknownField(String),
+    InvalidNumber(String),
+    /* nesting deeper than `MAX_EXPR_DEPTH` at the given offset */
+    TooDeep(usize),
+}
+
+/*
+ * The deepest an expression may nest, counting each operator applied to
+ * the result of another, so that parsing, evaluating and dropping it
+ * cannot exhaust the stack however the input is written
+ */
+pub const MAX_EXPR_DEPTH: usize = 256;
+
+/*
+ * A predicate over a single execution step: an expression, in the same
+ * language as `--watch-expr`, that holds where it is not zero. It is
+ * evaluated against the step's trace record: `pc`, `op` and `addr`
+ * describe the instruction as it was about to execute (so `addr` only
+ * holds for instructions that access memory), whilst `reg` and `depth`
+ * describe the state it left behind. Records keep neither the stack nor
+ * memory, so `stack[]` and `mem[]` are rejected.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter(Expr);
+
+impl Filter {
+    pub fn matches(&self, record: &TraceRecord) -> bool {
+        self.0.eval(record).is_ok_and(|t| t != 0)
+    }
+}
+
+impl Scope for TraceRecord {
+    fn var(&self, var: Var) -> Result<Word, EvalError> {
+        match var {
+            Var::Op => Ok(self.instruction.to_byte() as Word),
+            Var::Pc => Ok(self.pc),
+            Var::Addr => self.address.ok_or(EvalError::Unavailable("addr")),
+            Var::Step => Ok(self.step),
+            Var::Reg => Ok(self.reg),
+            Var::Depth => Ok(self.depth),
+        }
+    }
+
+    fn stack(&self, _index: Word) -> Result<Word, EvalError> {
+        Err(EvalError::Unavailable("stack"))
+    }
+
+    fn mem(&self, _address: Word) -> Result<Word, EvalError> {
+        Err(EvalError::Unavailable("mem"))
+    }
+}
+
+impl TryFrom<Expr> for Filter {
+    type Error = FilterParseError;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        let unavailable: Option<&Expr> =
+            value.find(&|t| matches!(t, Expr::Stack(_) | Expr::Mem(_)));
+
+        match unavailable {
+            Some(Expr::Stack(_)) => {
+                Err(FilterParseError::UnknownField("stack".to_string()))
+            }
+            Some(_) => Err(FilterParseError::UnknownField("mem".to_string())),
+            None => Ok(Self(value)),
+        }
+    }
+}
+
+impl TryFrom<&str> for Filter {
+    type Error = FilterParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::try_from(Expr::try_from(value)?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token {
+    Ident(String),
+    Number(String),
+    Op(BinaryOp),
+    Range,
+    Not,
+    Tilde,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+}
+
+pub(crate) fn tokenise(
+    input: &str,
+) -> Result<Vec<(usize, Token)>, FilterParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens: Vec<(usize, Token)> = vec![];
+    let mut i: usize = 0;
+
+    while i < chars.len() {
+        let c: char = chars[i];
+        let next: Option<char> = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        /* multi-character symbols take priority over their prefixes */
+        let (token, len): (Token, usize) = match (c, next) {
+            ('&', Some('&')) => (Token::Op(BinaryOp::LogicalAnd), 2),
+            ('|', Some('|')) => (Token::Op(BinaryOp::LogicalOr), 2),
+            ('=', Some('=')) => (Token::Op(BinaryOp::Eq), 2),
+            ('!', Some('=')) => (Token::Op(BinaryOp::Ne), 2),
+            ('<', Some('<')) => (Token::Op(BinaryOp::Shl), 2),
+            ('>', Some('>')) => (Token::Op(BinaryOp::Shr), 2),
+            ('<', Some('=')) => (Token::Op(BinaryOp::Le), 2),
+            ('>', Some('=')) => (Token::Op(BinaryOp::Ge), 2),
+            ('.', Some('.')) => (Token::Range, 2),
+            ('=', _) => (Token::Op(BinaryOp::Eq), 1),
+            ('<', _) => (Token::Op(BinaryOp::Lt), 1),
+            ('>', _) => (Token::Op(BinaryOp::Gt), 1),
+            ('+', _) => (Token::Op(BinaryOp::Add), 1),
+            ('-', _) => (Token::Op(BinaryOp::Sub), 1),
+            ('*', _) => (Token::Op(BinaryOp::Mul), 1),
+            ('/', _) => (Token::Op(BinaryOp::Div), 1),
+            ('%', _) => (Token::Op(BinaryOp::Mod), 1),
+            ('&', _) => (Token::Op(BinaryOp::And), 1),
+            ('|', _) => (Token::Op(BinaryOp::Or), 1),
+            ('^', _) => (Token::Op(BinaryOp::Xor), 1),
+            ('!', _) => (Token::Not, 1),
+            ('~', _) => (Token::Tilde, 1),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('[', _) => (Token::LBracket, 1),
+            (']', _) => (Token::RBracket, 1),
+            _ if c.is_ascii_alphanumeric() || c == '_' => {
+                let start: usize = i;
+                let mut end: usize = i;
+
+                while chars
+                    .get(end)
+                    .is_some_and(|t| t.is_ascii_alphanumeric() || *t == '_')
+                {
+                    end += 1;
+                }
+
+                let word: String = chars[start..end].iter().collect();
+                let token: Token = if c.is_ascii_digit() {
+                    Token::Number(word)
+                } else {
+                    match word.to_ascii_lowercase().as_str() {
+                        "and" => Token::Op(BinaryOp::LogicalAnd),
+                        "or" => Token::Op(BinaryOp::LogicalOr),
+                        "not" => Token::Not,
+                        _ => Token::Ident(word),
+                    }
+                };
+
+                (token, end - start)
+            }
+            _ => return Err(FilterParseError::UnexpectedCharacter(i)),
+        };
+
+        tokens.push((i, token));
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+pub(crate) fn parse_number(literal: &str) -> Result<Word, FilterParseError> {
+    parse_word(literal)
+        .ok_or_else(|| FilterParseError::InvalidNumber(literal.to_string()))
+}
+
+/*
+ * The one parser for trace filters, trace queries and expressions over the
+ * machine state. Operators bind as they do in C, with `x in lo..hi`
+ * (`lo <= x < hi`) binding as a comparison, except that `!` and `not`
+ * bind more loosely than comparisons, so that they negate them whole.
+ *
+ * Input nesting deeper than `MAX_EXPR_DEPTH` is rejected with `TooDeep`.
+ * Each parenthesis, unary operator and binary operator counts as a level
+ * for everything after it, so even a flat chain such as `1+1+...` may have
+ * at most `MAX_EXPR_DEPTH - 1` operators.
+ */
+pub(crate) struct Parser {
+    pub tokens: Vec<(usize, Token)>,
+    pub pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<(usize, Token)>) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    pub fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    pub fn next(&mut self) -> Result<(usize, Token), FilterParseError> {
+        match self.tokens.get(self.pos) {
+            Some(t) => {
+                self.pos += 1;
+                Ok(t.clone())
+            }
+            None => Err(FilterParseError::UnexpectedEnd),
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), FilterParseError> {
+        match self.next()? {
+            (_, t) if t == token => Ok(()),
+            (offset, _) => Err(FilterParseError::UnexpectedToken(offset)),
+        }
+    }
+
+    /* binary operators binding at least as tightly as `min`, left to right */
+    pub fn expr(&mut self, min: u8) -> Result<Expr, FilterParseError> {
+        let depth: usize = self.depth;
+        let result: Result<Expr, FilterParseError> = self.binary(min);
+        self.depth = depth;
+        result
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterParseError> {
+        let depth: usize = self.depth;
+        let result: Result<Expr, FilterParseError> =
+            self.descend().and_then(|_| self.operand());
+        self.depth = depth;
+        result
+    }
+
+    /* one level deeper into the expression, failing past the limit */
+    fn descend(&mut self) -> Result<(), FilterParseError> {
+        self.depth += 1;
+
+        if self.depth > MAX_EXPR_DEPTH {
+            let offset: usize = self
+                .tokens
+                .get(self.pos)
+                .or(self.tokens.last())
+                .map_or(0, |(offset, _)| *offset);
+            Err(FilterParseError::TooDeep(offset))
+        } else {
+            Ok(())
+        }
+    }
+
+    /* each operator applied deepens everything parsed after it */
+    fn binary(&mut self, min: u8) -> Result<Expr, FilterParseError> {
+        let mut lhs: Expr = self.unary()?;
+
+        loop {
+            match self.peek() {
+                Some(Token::Op(op)) if op.precedence() >= min => {
+                    let op: BinaryOp = *op;
+                    self.descend()?;
+                    self.pos += 1;
+                    let rhs: Expr = self.expr(op.precedence() + 1)?;
+                    lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+                }
+                Some(Token::Ident(t))
+                    if t.eq_ignore_ascii_case("in")
+                        && Expr::IN_PRECEDENCE >= min =>
+                {
+                    self.descend()?;
+                    self.pos += 1;
+                    let lo: Expr = self.expr(Expr::IN_PRECEDENCE + 1)?;
+                    self.expect(Token::Range)?;
+                    let hi: Expr = self.expr(Expr::IN_PRECEDENCE + 1)?;
+                    lhs = Expr::In(Box::new(lhs), Box::new(lo), Box::new(hi));
+                }
+                _ => return Ok(lhs),
+            }
+        }
+    }
+
+    fn operand(&mut self) -> Result<Expr, FilterParseError> {
+        match self.next()? {
+            /* negates a whole comparison, as in `not op = Load` */
+            (_, Token::Not) => Ok(Expr::LogicalNot(Box::new(
+                self.expr(BinaryOp::Eq.precedence())?,
+            ))),
+            (_, Token::Tilde) => Ok(Expr::Not(Box::new(self.unary()?))),
+            (_, Token::LParen) => {
+                let inner: Expr = self.expr(0)?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            (_, Token::Number(literal)) => {
+                parse_number(&literal).map(Expr::Number)
+            }
+            (_, Token::Ident(name)) => {
+                match name.to_ascii_lowercase().as_str() {
+                    "stack" => Ok(Expr::Stack(Box::new(self.index()?))),
+                    "mem" => Ok(Expr::Mem(Box::new(self.index()?))),
+                    t => {
+                        match (Var::from_name(t), Instruction::from_mnemonic(t))
+                        {
+                            (Some(var), _) => Ok(Expr::Var(var)),
+                            /* an instruction's mnemonic stands for its opcode */
+                            (None, Some(instruction)) => {
+                                Ok(Expr::Number(instruction.to_byte() as Word))
+                            }
+                            (None, None) => {
+                                Err(FilterParseError::UnknownField(name))
+                            }
+                        }
+                    }
+                }
+            }
+            (offset, _) => Err(FilterParseError::UnexpectedToken(offset)),
+        }
+    }
+
+    fn index(&mut self) -> Result<Expr, FilterParseError> {
+        self.expect(Token::LBracket)?;
+        let index: Expr = self.expr(0)?;
+        self.expect(Token::RBracket)?;
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pc: Word, instruction: Instruction) -> TraceRecord {
+        TraceRecord {
+            step: 0,
+            pc,
+            instruction,
+            address: match instruction {
+                Instruction::Load | Instruction::Store => Some(16),
+                _ => None,
+            },
+            reg: 0,
+            depth: 1,
+        }
+    }
+
+    fn matches(filter: &str, record: &TraceRecord) -> bool {
+        Filter::try_from(filter)
+            .expect("filter parses")
+            .matches(record)
+    }
+
+    #[test]
+    fn test_tokenise() {
+        assert_eq!(
+            tokenise("op<=Load&&!x").map(|t| {
+                t.into_iter().map(|(_, t)| t).collect::<Vec<Token>>()
+            }),
+            Ok(vec![
+                Token::Ident("op".to_string()),
+                Token::Op(BinaryOp::Le),
+                Token::Ident("Load".to_string()),
+                Token::Op(BinaryOp::LogicalAnd),
+                Token::Not,
+                Token::Ident("x".to_string()),
+            ])
+        );
+        assert_eq!(
+            tokenise("pc @ 1"),
+            Err(FilterParseError::UnexpectedCharacter(3))
+        );
+    }
+
+    #[test]
+    fn test_opcodes_and_ranges() {
+        let filter: &str = "op=Store || pc in 10..20";
+
+        assert!(matches(filter, &record(0, Instruction::Store)));
+        assert!(matches(filter, &record(10, Instruction::Nop)));
+        assert!(matches(filter, &record(19, Instruction::Nop)));
+        assert!(!matches(filter, &record(20, Instruction::Nop)));
+        assert!(!matches(filter, &record(0, Instruction::Load)));
+    }
+
+    #[test]
+    fn test_not_negates_a_comparison() {
+        assert!(matches("not op = Load", &record(0, Instruction::Store)));
+        assert!(!matches("not op = Load", &record(0, Instruction::Load)));
+        assert!(matches(
+            "!op = Load and pc < 4",
+            &record(0, Instruction::Store)
+        ));
+    }
+
+    #[test]
+    fn test_unavailable_address_is_false() {
+        assert!(matches("addr = 16", &record(0, Instruction::Load)));
+        assert!(!matches("addr = 16", &record(0, Instruction::Nop)));
+        assert!(!matches("addr in 0..32", &record(0, Instruction::Nop)));
+        assert!(!matches("addr + 1", &record(0, Instruction::Nop)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Filter::try_from(""), Err(FilterParseError::Empty));
+        assert_eq!(
+            Filter::try_from("stack[0] = 1"),
+            Err(FilterParseError::UnknownField("stack".to_string()))
+        );
+        assert_eq!(
+            Filter::try_from("mem[0] = 1"),
+            Err(FilterParseError::UnknownField("mem".to_string()))
+        );
+        assert_eq!(
+            Filter::try_from("opcode = 1"),
+            Err(FilterParseError::UnknownField("opcode".to_string()))
+        );
+        assert_eq!(
+            Filter::try_from("pc ="),
+            Err(FilterParseError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Filter::try_from("pc = 1)"),
+            Err(FilterParseError::UnexpectedToken(6))
+        );
+        assert!(matches!(
+            Filter::try_from("(".repeat(MAX_EXPR_DEPTH).as_str()),
+            Err(FilterParseError::TooDeep(_))
+        ));
+    }
+}
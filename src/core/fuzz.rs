@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+
+use crate::common::rng::XorShift;
+use crate::common::types::Word;
+use crate::core::code::Code;
+use crate::core::machine::{Machine, MachineError};
+use crate::core::memory::LinearlyAddressable;
+use crate::core::stack::Stack;
+use crate::core::state::State;
+
+pub const DEFAULT_FUZZ_STEP_LIMIT: u64 = 100_000;
+
+const MAX_MEMORY_CELLS: usize = 4;
+
+/* boundary values that tend to provoke arithmetic faults */
+const INTERESTING_WORDS: [Word; 8] = [
+    0,
+    1,
+    2,
+    16,
+    0x7fff_ffff_ffff_ffff,
+    0x8000_0000_0000_0000,
+    Word::MAX - 1,
+    Word::MAX,
+];
+
+/* an initial stack (bottom first) and a handful of memory cells */
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct FuzzInput {
+    pub stack: Vec<Word>,
+    pub memory: Vec<(Word, Word)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crash {
+    pub error: MachineError,
+    pub pc: Word,
+    pub input: FuzzInput,
+    pub found_after: u64,
+}
+
+/*
+ * A mutational fuzzer over the inputs to a fixed program. Inputs that lead
+ * to a run not seen before (judged by where it ended and how many steps it
+ * took) are kept and mutated further, so that the search spreads across
+ * the program's behaviours. Each distinct fault, identified by its error
+ * and pc, is reported once with the smallest input found to provoke it.
+ */
+pub struct InputFuzzer {
+    code: Code,
+    rng: XorShift,
+    step_limit: u64,
+    corpus: Vec<FuzzInput>,
+    seen: HashSet<(Option<MachineError>, Word, u64)>,
+    runs: u64,
+}
+
+impl InputFuzzer {
+    pub fn new(code: Code, stack_args: usize, seed: u64) -> Self {
+        Self {
+            code,
+            rng: XorShift::new(seed),
+            step_limit: DEFAULT_FUZZ_STEP_LIMIT,
+            corpus: vec![FuzzInput {
+                stack: vec![0; stack_args],
+                memory: vec![],
+            }],
+            seen: HashSet::new(),
+            runs: 0,
+        }
+    }
+
+    pub fn with_step_limit(self, step_limit: u64) -> Self {
+        Self { step_limit, ..self }
+    }
+
+    pub fn run(&mut self, iterations: u64) -> Vec<Crash> {
+        let mut crashes: Vec<Crash> = vec![];
+
+        for _ in 0..iterations {
+            let parent: usize =
+                self.rng.below(self.corpus.len() as u64) as usize;
+            let input: FuzzInput = self.mutate(&self.corpus[parent].clone());
+            let (result, steps): (Result<Word, (MachineError, Word)>, u64) =
+                self.execute(&input);
+
+            let signature: (Option<MachineError>, Word, u64) = match result {
+                Ok(pc) => (None, pc, steps),
+                Err((e, pc)) => (Some(e), pc, steps),
+            };
+
+            if !self.seen.insert(signature) {
+                continue;
+            }
+
+            self.corpus.push(input.clone());
+
+            if let Err((error, pc)) = result {
+                let known: bool =
+                    crashes.iter().any(|t| t.error == error && t.pc == pc);
+
+                if is_fault(error) && !known {
+                    crashes.push(Crash {
+                        error,
+                        pc,
+                        input: self.minimise(input, error, pc),
+                        found_after: self.runs,
+                    });
+                }
+            }
+        }
+
+        crashes
+    }
+
+    pub fn runs(&self) -> u64 {
+        self.runs
+    }
+
+    fn mutate(&mut self, input: &FuzzInput) -> FuzzInput {
+        let mut res: FuzzInput = input.clone();
+
+        match self.rng.below(4) {
+            0 | 1 if !res.stack.is_empty() => {
+                let i: usize = self.rng.below(res.stack.len() as u64) as usize;
+                res.stack[i] = self.mutate_word(res.stack[i]);
+            }
+            2 if res.memory.len() < MAX_MEMORY_CELLS => {
+                let cell: (Word, Word) =
+                    (self.rng.below(16), self.rng.operand());
+                res.memory.push(cell);
+            }
+            _ if !res.memory.is_empty() => {
+                let i: usize = self.rng.below(res.memory.len() as u64) as usize;
+                res.memory[i].1 = self.mutate_word(res.memory[i].1);
+            }
+            _ => {
+                let cell: (Word, Word) =
+                    (self.rng.below(16), self.rng.operand());
+                res.memory.push(cell);
+            }
+        }
+
+        res
+    }
+
+    fn mutate_word(&mut self, word: Word) -> Word {
+        match self.rng.below(5) {
+            0 => {
+                INTERESTING_WORDS
+                    [self.rng.below(INTERESTING_WORDS.len() as u64) as usize]
+            }
+            1 => word ^ (1 << self.rng.below(Word::BITS as u64)),
+            2 => word.wrapping_add(1),
+            3 => word.wrapping_sub(1),
+            _ => self.rng.operand(),
+        }
+    }
+
+    /* runs the program, giving the final (or faulting) pc and step count */
+    fn execute(
+        &mut self,
+        input: &FuzzInput,
+    ) -> (Result<Word, (MachineError, Word)>, u64) {
+        let mut state: State = State {
+            stack: Stack::try_from(input.stack.clone()).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for (address, data) in &input.memory {
+            state.memory.write(*address, *data);
+        }
+
+        let mut machine: Machine = Machine::new(self.code.clone())
+            .with_state(state)
+            .with_step_limit(self.step_limit);
+        let mut steps: u64 = 0;
+        let result: Result<State, MachineError> =
+            machine.run_callback(&mut |_, _, _| {
+                steps += 1;
+                Ok(())
+            });
+
+        self.runs += 1;
+
+        match result {
+            Ok(t) => (Ok(t.pc), steps),
+            Err(e) => (Err((e, machine.state.pc)), steps),
+        }
+    }
+
+    /*
+     * Shrinks an input whilst it still provokes the same fault: memory
+     * cells are dropped, then words are zeroed or halved until no further
+     * simplification applies.
+     */
+    fn minimise(
+        &mut self,
+        mut input: FuzzInput,
+        error: MachineError,
+        pc: Word,
+    ) -> FuzzInput {
+        loop {
+            let mut candidates: Vec<FuzzInput> = vec![];
+
+            for i in 0..input.memory.len() {
+                let mut t: FuzzInput = input.clone();
+                t.memory.remove(i);
+                candidates.push(t);
+            }
+
+            for i in 0..input.stack.len() {
+                for word in simpler(input.stack[i]) {
+                    let mut t: FuzzInput = input.clone();
+                    t.stack[i] = word;
+                    candidates.push(t);
+                }
+            }
+
+            for i in 0..input.memory.len() {
+                for word in simpler(input.memory[i].1) {
+                    let mut t: FuzzInput = input.clone();
+                    t.memory[i].1 = word;
+                    candidates.push(t);
+                }
+            }
+
+            let smaller: Option<FuzzInput> = candidates
+                .into_iter()
+                .find(|t| self.execute(t).0 == Err((error, pc)));
+
+            match smaller {
+                Some(t) => input = t,
+                None => return input,
+            }
+        }
+    }
+}
+
+fn simpler(word: Word) -> Vec<Word> {
+    match word {
+        0 => vec![],
+        1 => vec![0],
+        _ => vec![0, 1, word / 2],
+    }
+}
+
+/* whether an error reflects the program's behaviour rather than the run */
+fn is_fault(error: MachineError) -> bool {
+    !matches!(
+        error,
+        MachineError::StepLimitExceeded | MachineError::Interrupted
+    )
+}
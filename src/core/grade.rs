@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::Word;
+use crate::core::code::Code;
+use crate::core::machine::{Machine, MachineError};
+use crate::core::memory::LinearlyAddressable;
+use crate::core::stack::{Stack, MAX_STACK_DEPTH};
+use crate::core::state::State;
+
+pub const DEFAULT_GRADE_STEP_LIMIT: u64 = 1_000_000;
+pub const SCORE_REPORT_VERSION: u8 = 1;
+
+/* the most words an output region may span */
+const MAX_OUTPUT_WORDS: Word = 65536;
+
+#[derive(Debug)]
+pub enum SpecError {
+    IOError(io::Error),
+    Malformed(String),
+    DuplicateOutput(String),
+    OutputTooLarge(String),
+    UnknownOutput(String, String),
+    LengthMismatch(String, String),
+    MissingExpected(String, String),
+    InvalidStack(String),
+    /* the cases' points sum to more than a score can hold */
+    PointsOverflow,
+    ReferenceFault(String, MachineError),
+}
+
+impl From<io::Error> for SpecError {
+    fn from(value: io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Output {
+    pub name: String,
+    pub start: Word,
+    pub size: Word,
+}
+
+/*
+ * One input to run the student's program from, worth `points` if every
+ * output then holds what `expected` says. Outputs left out of `expected`
+ * are taken from a reference program, if one is given.
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Case {
+    pub name: String,
+    #[serde(default)]
+    pub reg: Word,
+    #[serde(default)]
+    pub stack: Vec<Word>,
+    #[serde(default)]
+    pub expected: BTreeMap<String, Vec<Word>>,
+    #[serde(default = "one")]
+    pub points: u64,
+}
+
+fn one() -> u64 {
+    1
+}
+
+/*
+ * An exercise, read from JSON of the form:
+ *
+ *     {
+ *         "outputs": [{ "name": "result", "start": 0, "size": 2 }],
+ *         "step_limit": 10000,
+ *         "cases": [
+ *             { "name": "small", "reg": 3, "expected": { "result": [3, 9] } }
+ *         ]
+ *     }
+ *
+ * A program passes a case if it finishes within the step limit and each
+ * output region holds exactly the expected words.
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Spec {
+    pub outputs: Vec<Output>,
+    #[serde(default)]
+    pub step_limit: Option<u64>,
+    pub cases: Vec<Case>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mismatch {
+    pub output: String,
+    pub expected: Vec<Word>,
+    pub actual: Vec<Word>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CaseReport {
+    pub name: String,
+    pub passed: bool,
+    pub points: u64,
+    pub fault: Option<MachineError>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoreReport {
+    pub version: u8,
+    pub score: u64,
+    pub total: u64,
+    pub cases: Vec<CaseReport>,
+}
+
+pub fn read_spec<R: Read>(source: R) -> Result<Spec, SpecError> {
+    let spec: Spec = serde_json::from_reader(source)
+        .map_err(|e| SpecError::Malformed(e.to_string()))?;
+
+    for (i, output) in spec.outputs.iter().enumerate() {
+        if spec.outputs[..i].iter().any(|t| t.name == output.name) {
+            return Err(SpecError::DuplicateOutput(output.name.clone()));
+        }
+
+        if output.size > MAX_OUTPUT_WORDS {
+            return Err(SpecError::OutputTooLarge(output.name.clone()));
+        }
+    }
+
+    spec.cases
+        .iter()
+        .try_fold(0u64, |total, t| total.checked_add(t.points))
+        .ok_or(SpecError::PointsOverflow)?;
+
+    for case in &spec.cases {
+        if case.stack.len() > MAX_STACK_DEPTH {
+            return Err(SpecError::InvalidStack(case.name.clone()));
+        }
+
+        for (name, words) in &case.expected {
+            match spec.outputs.iter().find(|t| &t.name == name) {
+                Some(t) if words.len() as Word == t.size => {}
+                Some(_) => {
+                    return Err(SpecError::LengthMismatch(
+                        case.name.clone(),
+                        name.clone(),
+                    ))
+                }
+                None => {
+                    return Err(SpecError::UnknownOutput(
+                        case.name.clone(),
+                        name.clone(),
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(spec)
+}
+
+impl Output {
+    fn words(&self, state: &State) -> Vec<Word> {
+        (0..self.size)
+            .map(|i| state.memory.read(self.start.wrapping_add(i)))
+            .collect()
+    }
+}
+
+fn run(
+    code: &Code,
+    case: &Case,
+    step_limit: u64,
+) -> Result<State, MachineError> {
+    let stack: Stack = Stack::try_from(case.stack.clone()).unwrap_or_default();
+    let mut machine: Machine = Machine::new(code.clone())
+        .with_reg(case.reg)
+        .with_stack(stack)
+        .with_step_limit(step_limit);
+
+    machine.run()
+}
+
+/*
+ * Runs the student's program over every case of the exercise, filling in
+ * the expected contents of outputs a case leaves out from the reference
+ * program, if there is one
+ */
+pub fn grade(
+    student: &Code,
+    reference: Option<&Code>,
+    spec: &Spec,
+) -> Result<ScoreReport, SpecError> {
+    let step_limit: u64 = spec.step_limit.unwrap_or(DEFAULT_GRADE_STEP_LIMIT);
+    let mut report: ScoreReport = ScoreReport {
+        version: SCORE_REPORT_VERSION,
+        score: 0,
+        total: 0,
+        cases: vec![],
+    };
+
+    for case in &spec.cases {
+        let expected: State = match reference {
+            Some(t) => run(t, case, step_limit)
+                .map_err(|e| SpecError::ReferenceFault(case.name.clone(), e))?,
+            None => State::default(),
+        };
+        let result: Result<State, MachineError> =
+            run(student, case, step_limit);
+        let mut mismatches: Vec<Mismatch> = vec![];
+
+        for output in &spec.outputs {
+            let want: Vec<Word> =
+                match (case.expected.get(&output.name), reference) {
+                    (Some(t), _) => t.clone(),
+                    (None, Some(_)) => output.words(&expected),
+                    (None, None) => {
+                        return Err(SpecError::MissingExpected(
+                            case.name.clone(),
+                            output.name.clone(),
+                        ))
+                    }
+                };
+
+            if let Ok(t) = &result {
+                let actual: Vec<Word> = output.words(t);
+
+                if actual != want {
+                    mismatches.push(Mismatch {
+                        output: output.name.clone(),
+                        expected: want,
+                        actual,
+                    });
+                }
+            }
+        }
+
+        let passed: bool = result.is_ok() && mismatches.is_empty();
+
+        /* the score never exceeds the total, so cannot overflow either */
+        report.total = report
+            .total
+            .checked_add(case.points)
+            .ok_or(SpecError::PointsOverflow)?;
+        report.score += if passed { case.points } else { 0 };
+        report.cases.push(CaseReport {
+            name: case.name.clone(),
+            passed,
+            points: if passed { case.points } else { 0 },
+            fault: result.err(),
+            mismatches,
+        });
+    }
+
+    Ok(report)
+}
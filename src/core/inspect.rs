@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::Word;
+use crate::core::memory::LinearlyAddressable;
+use crate::core::state::State;
+
+/* steps between checks for pending requests, keeping them off the hot path */
+const INSPECT_CHECK_INTERVAL: u64 = 1024;
+
+/* requests that may wait for the machine before clients are held off */
+const INSPECT_QUEUE_DEPTH: usize = 4;
+
+/* the most words one memory range may ask for */
+pub const MAX_INSPECT_RANGE: Word = 4096;
+
+#[derive(Debug)]
+pub enum InspectError {
+    IOError(io::Error),
+    Malformed,
+    Unsupported,
+}
+
+impl From<io::Error> for InspectError {
+    fn from(value: io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
+
+/*
+ * What `attach` asks of a running machine, sent as a single JSON line:
+ * the memory ranges to read, each as `[start, length]`. Lengths beyond
+ * `MAX_INSPECT_RANGE` are cut short.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InspectRequest {
+    #[serde(default)]
+    pub memory: Vec<(Word, Word)>,
+}
+
+/* the machine as it stood between two steps, sent back as a JSON line */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InspectReply {
+    pub steps: u64,
+    pub pc: Word,
+    pub reg: Word,
+    /* bottom first, as in a state dump */
+    pub stack: Vec<Word>,
+    pub memory: BTreeMap<Word, Word>,
+}
+
+impl InspectReply {
+    pub fn new(state: &State, steps: u64, request: &InspectRequest) -> Self {
+        let memory: BTreeMap<Word, Word> = request
+            .memory
+            .iter()
+            .flat_map(|(start, len)| {
+                (0..(*len).min(MAX_INSPECT_RANGE))
+                    .map(move |t| start.wrapping_add(t))
+            })
+            .map(|t| (t, state.memory.read(t)))
+            .collect();
+
+        Self {
+            steps,
+            pc: state.pc,
+            reg: state.reg,
+            stack: state.stack.elems().to_vec(),
+            memory,
+        }
+    }
+}
+
+type Pending = (InspectRequest, Sender<InspectReply>);
+
+/*
+ * Serves read-only views of a running machine on a Unix socket. A thread
+ * accepts connections and queues each request, and the machine answers
+ * whatever is queued every `INSPECT_CHECK_INTERVAL` steps, so inspecting
+ * a run never stops it for longer than it takes to copy out the reply.
+ * The socket is removed when the server is dropped.
+ */
+#[derive(Debug)]
+pub struct InspectServer {
+    path: PathBuf,
+    requests: Receiver<Pending>,
+}
+
+impl InspectServer {
+    #[cfg(unix)]
+    pub fn bind(path: &Path) -> Result<Self, InspectError> {
+        let listener: UnixListener = UnixListener::bind(path)?;
+        let (queue, requests): (SyncSender<Pending>, Receiver<Pending>) =
+            mpsc::sync_channel(INSPECT_QUEUE_DEPTH);
+
+        /* runs until the process exits; a failed client only drops itself */
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = serve(stream, &queue);
+            }
+        });
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            requests,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(_path: &Path) -> Result<Self, InspectError> {
+        Err(InspectError::Unsupported)
+    }
+
+    /* answers any queued requests, if it is time to check */
+    pub fn poll(&mut self, state: &State, steps: u64) {
+        if !steps.is_multiple_of(INSPECT_CHECK_INTERVAL) {
+            return;
+        }
+
+        while let Ok((request, reply)) = self.requests.try_recv() {
+            /* the client may have hung up while waiting */
+            let _ = reply.send(InspectReply::new(state, steps, &request));
+        }
+    }
+}
+
+impl Drop for InspectServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/*
+ * Handles one client: reads its request, waits for the machine to answer
+ * and writes the reply. If the run ends first the connection is closed
+ * without a reply.
+ */
+#[cfg(unix)]
+fn serve(
+    stream: UnixStream,
+    queue: &SyncSender<Pending>,
+) -> Result<(), InspectError> {
+    let mut line: String = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let request: InspectRequest =
+        serde_json::from_str(&line).map_err(|_| InspectError::Malformed)?;
+    let (reply, replies): (Sender<InspectReply>, Receiver<InspectReply>) =
+        mpsc::channel();
+
+    /* the run has already finished */
+    queue
+        .send((request, reply))
+        .map_err(|_| InspectError::IOError(io::ErrorKind::BrokenPipe.into()))?;
+
+    if let Ok(t) = replies.recv() {
+        write_line(&stream, &t)?;
+    }
+
+    Ok(())
+}
+
+/* connects to a machine served by `InspectServer` and asks it `request` */
+#[cfg(unix)]
+pub fn attach(
+    path: &Path,
+    request: &InspectRequest,
+) -> Result<InspectReply, InspectError> {
+    let stream: UnixStream = UnixStream::connect(path)?;
+    write_line(&stream, request)?;
+
+    let mut line: String = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    if line.is_empty() {
+        return Err(InspectError::IOError(io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    serde_json::from_str(&line).map_err(|_| InspectError::Malformed)
+}
+
+#[cfg(not(unix))]
+pub fn attach(
+    _path: &Path,
+    _request: &InspectRequest,
+) -> Result<InspectReply, InspectError> {
+    Err(InspectError::Unsupported)
+}
+
+fn write_line<W: Write, T: Serialize>(
+    mut sink: W,
+    value: &T,
+) -> Result<(), InspectError> {
+    let line: String =
+        serde_json::to_string(value).map_err(|_| InspectError::Malformed)?;
+    writeln!(sink, "{}", line)?;
+    sink.flush()?;
+    Ok(())
+}
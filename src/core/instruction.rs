@@ -1,6 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 use crate::common::types::{word_bytes, Word};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/* raised whenever an opcode is added or the meaning of one changes */
+pub const ISA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Instruction {
     Nop,
     Halt,
@@ -23,9 +28,28 @@ pub enum Instruction {
     Or,
     Not,
     Xor,
+    CasWord,
+    FetchAdd,
+    Wrap,
+    IAdd,
+    ISub,
+    IMul,
+    IDiv,
+    IMod,
+    SLt,
+    MulHi,
+    DivRem,
+    Rotl,
+    Rotr,
+    Popcnt,
+    Clz,
+    Ctz,
+    TraceOn,
+    TraceOff,
+    Poll,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InstructionParseError {
     NoData,
     InvalidOpcode,
@@ -34,6 +58,19 @@ pub enum InstructionParseError {
     IncompleteLiteral,
 }
 
+impl InstructionParseError {
+    /* stable process exit code reported when decoding fails with this error */
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::NoData => 20,
+            Self::InvalidOpcode => 21,
+            Self::MissingLiteral => 22,
+            Self::InappropriateLiteral => 23,
+            Self::IncompleteLiteral => 24,
+        }
+    }
+}
+
 impl TryFrom<Vec<u8>> for Instruction {
     type Error = InstructionParseError;
 
@@ -51,9 +88,10 @@ impl TryFrom<&[u8]> for Instruction {
         } else if value.len() > 1 {
             if value[0] == 0x06 {
                 if value.len() == 1 + word_bytes() {
-                    Ok(Self::Set(Word::from_be_bytes(
-                        value[1..].try_into().unwrap(),
-                    )))
+                    value[1..]
+                        .try_into()
+                        .map(|t| Self::Set(Word::from_be_bytes(t)))
+                        .map_err(|_| Self::Error::IncompleteLiteral)
                 } else {
                     Err(Self::Error::IncompleteLiteral)
                 }
@@ -82,6 +120,25 @@ impl TryFrom<&[u8]> for Instruction {
                 0x12 => Ok(Self::Or),
                 0x13 => Ok(Self::Not),
                 0x14 => Ok(Self::Xor),
+                0x15 => Ok(Self::CasWord),
+                0x16 => Ok(Self::FetchAdd),
+                0x17 => Ok(Self::Wrap),
+                0x18 => Ok(Self::IAdd),
+                0x19 => Ok(Self::ISub),
+                0x1A => Ok(Self::IMul),
+                0x1B => Ok(Self::IDiv),
+                0x1C => Ok(Self::IMod),
+                0x1D => Ok(Self::SLt),
+                0x1E => Ok(Self::MulHi),
+                0x1F => Ok(Self::DivRem),
+                0x20 => Ok(Self::Rotl),
+                0x21 => Ok(Self::Rotr),
+                0x22 => Ok(Self::Popcnt),
+                0x23 => Ok(Self::Clz),
+                0x24 => Ok(Self::Ctz),
+                0x25 => Ok(Self::TraceOn),
+                0x26 => Ok(Self::TraceOff),
+                0x27 => Ok(Self::Poll),
                 0x06 => Err(Self::Error::MissingLiteral),
                 _ => Err(Self::Error::InvalidOpcode),
             }
@@ -94,6 +151,90 @@ impl Instruction {
         Instruction::try_from(bytes).ok()
     }
 
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        (0..=u8::MAX)
+            .filter_map(|opcode| match Instruction::try_from(&[opcode][..]) {
+                Ok(t) => Some(t),
+                Err(InstructionParseError::MissingLiteral) => {
+                    Some(Self::Set(Word::default()))
+                }
+                Err(_) => None,
+            })
+            .find(|t| t.mnemonic().eq_ignore_ascii_case(mnemonic))
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Nop => "Nop",
+            Self::Halt => "Halt",
+            Self::Load => "Load",
+            Self::Store => "Store",
+            Self::Push => "Push",
+            Self::Pop => "Pop",
+            Self::Set(_) => "Set",
+            Self::Read => "Read",
+            Self::Write => "Write",
+            Self::Jump => "Jump",
+            Self::JumpIf => "JumpIf",
+            Self::Add => "Add",
+            Self::Sub => "Sub",
+            Self::Mul => "Mul",
+            Self::Div => "Div",
+            Self::Mod => "Mod",
+            Self::Cmp => "Cmp",
+            Self::And => "And",
+            Self::Or => "Or",
+            Self::Not => "Not",
+            Self::Xor => "Xor",
+            Self::CasWord => "CasWord",
+            Self::FetchAdd => "FetchAdd",
+            Self::Wrap => "Wrap",
+            Self::IAdd => "IAdd",
+            Self::ISub => "ISub",
+            Self::IMul => "IMul",
+            Self::IDiv => "IDiv",
+            Self::IMod => "IMod",
+            Self::SLt => "SLt",
+            Self::MulHi => "MulHi",
+            Self::DivRem => "DivRem",
+            Self::Rotl => "Rotl",
+            Self::Rotr => "Rotr",
+            Self::Popcnt => "Popcnt",
+            Self::Clz => "Clz",
+            Self::Ctz => "Ctz",
+            Self::TraceOn => "TraceOn",
+            Self::TraceOff => "TraceOff",
+            Self::Poll => "Poll",
+        }
+    }
+
+    /* the encoding of this instruction within a program */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![self.to_byte()];
+
+        if let Self::Set(x) = self {
+            bytes.extend_from_slice(&x.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    /* the number of bytes this instruction occupies in a program */
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Self::Set(_) => 1 + word_bytes(),
+            _ => 1,
+        }
+    }
+
+    /* whether this instruction addresses memory via the top of the stack */
+    pub fn touches_memory(&self) -> bool {
+        matches!(
+            self,
+            Self::Load | Self::Store | Self::CasWord | Self::FetchAdd
+        )
+    }
+
     pub fn to_byte(&self) -> u8 {
         match self {
             Self::Nop => 0x00,
@@ -117,6 +258,67 @@ impl Instruction {
             Self::Or => 0x12,
             Self::Not => 0x13,
             Self::Xor => 0x14,
+            Self::CasWord => 0x15,
+            Self::FetchAdd => 0x16,
+            Self::Wrap => 0x17,
+            Self::IAdd => 0x18,
+            Self::ISub => 0x19,
+            Self::IMul => 0x1A,
+            Self::IDiv => 0x1B,
+            Self::IMod => 0x1C,
+            Self::SLt => 0x1D,
+            Self::MulHi => 0x1E,
+            Self::DivRem => 0x1F,
+            Self::Rotl => 0x20,
+            Self::Rotr => 0x21,
+            Self::Popcnt => 0x22,
+            Self::Clz => 0x23,
+            Self::Ctz => 0x24,
+            Self::TraceOn => 0x25,
+            Self::TraceOff => 0x26,
+            Self::Poll => 0x27,
+        }
+    }
+}
+
+/* a set of opcodes, such as those a run is not allowed to execute */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpcodeSet([u64; 4]);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpcodeSetError {
+    UnknownMnemonic(String),
+}
+
+/* parses a comma-separated list of mnemonics, e.g. `Read,Write,Jump` */
+impl TryFrom<&str> for OpcodeSet {
+    type Error = OpcodeSetError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut set: Self = Self::default();
+
+        for mnemonic in value.split(',').map(str::trim) {
+            set.insert(Instruction::from_mnemonic(mnemonic).ok_or_else(
+                || OpcodeSetError::UnknownMnemonic(mnemonic.to_string()),
+            )?);
         }
+
+        Ok(set)
+    }
+}
+
+impl OpcodeSet {
+    pub fn insert(&mut self, instruction: Instruction) {
+        let opcode: usize = instruction.to_byte() as usize;
+        self.0[opcode / 64] |= 1 << (opcode % 64);
+    }
+
+    pub fn contains(&self, instruction: Instruction) -> bool {
+        let opcode: usize = instruction.to_byte() as usize;
+        self.0[opcode / 64] & (1 << (opcode % 64)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|t| *t == 0)
     }
 }
@@ -0,0 +1,80 @@
+use crate::common::types::{word_to_usize, Word};
+use crate::core::instruction::Instruction;
+use crate::core::stack::MAX_STACK_DEPTH;
+use crate::core::state::State;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Violation {
+    StackTooDeep(usize),
+    PcOutOfRange { pc: Word, len: usize },
+    PcNotAdvanced { before: Word, after: Word },
+    HaltMovedPc { before: Word, after: Word },
+}
+
+/*
+ * Checks the state left behind by a single step. These should all hold by
+ * construction, so a violation points at a bug in the engine rather than
+ * the program:
+ *
+ *  - the stack is no deeper than its maximum
+ *  - the pc indexes an instruction, or is one past the last (i.e. the
+ *    program has finished), where the program's length is known
+ *  - instructions other than jumps advance the pc by exactly one
+ *  - HALT leaves the pc where it was
+ */
+pub fn check(
+    old: &State,
+    instruction: Instruction,
+    new: &State,
+    code_len: Option<usize>,
+) -> Result<(), Violation> {
+    if new.stack.depth() > MAX_STACK_DEPTH {
+        return Err(Violation::StackTooDeep(new.stack.depth()));
+    }
+
+    if let Some(len) = code_len {
+        if word_to_usize(new.pc).is_none_or(|t| t > len) {
+            return Err(Violation::PcOutOfRange { pc: new.pc, len });
+        }
+    }
+
+    match instruction {
+        Instruction::Jump | Instruction::JumpIf => Ok(()),
+        Instruction::Halt if new.pc != old.pc => Err(Violation::HaltMovedPc {
+            before: old.pc,
+            after: new.pc,
+        }),
+        Instruction::Halt => Ok(()),
+        _ if Some(new.pc) != old.pc.checked_add(1) => {
+            Err(Violation::PcNotAdvanced {
+                before: old.pc,
+                after: new.pc,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/* a violation together with the step that caused it */
+#[derive(Clone, Debug)]
+pub struct InvariantFailure {
+    pub violation: Violation,
+    pub step: u64,
+    pub instruction: Instruction,
+    pub before: State,
+    pub after: State,
+}
+
+impl InvariantFailure {
+    pub fn render(&self) -> String {
+        format!(
+            "invariant violated at step {}: {:?}\n  instruction: {:?}\n  \
+             before: {:?}\n  after:  {:?}\n",
+            self.step,
+            self.violation,
+            self.instruction,
+            self.before,
+            self.after
+        )
+    }
+}
@@ -0,0 +1,306 @@
+use serde::Serialize;
+
+use crate::common::types::{word_bytes, Word};
+use crate::core::analysis::{arity, stack_effect};
+use crate::core::cost::CostModel;
+use crate::core::explain::{explanations, ErrorKind};
+use crate::core::instruction::{
+    Instruction, InstructionParseError, ISA_VERSION,
+};
+use crate::core::machine::MachineError;
+
+/* what each ISA version changed, oldest first */
+const CHANGELOG: &[(u32, &str)] = &[(
+    1,
+    "Initial instruction set: opcodes 0x00 to 0x27, with JumpIf reserved.",
+)];
+
+/*
+ * Faults that do not belong to any one instruction: raised by the run
+ * around it, by its limits, or by a failed fetch.
+ */
+const RUN_FAULTS: [MachineError; 7] = [
+    MachineError::OutOfBounds,
+    MachineError::UndecodableInstruction,
+    MachineError::StepLimitExceeded,
+    MachineError::MemoryLimitExceeded,
+    MachineError::InvariantViolation,
+    MachineError::DeniedInstruction,
+    MachineError::Interrupted,
+];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Change {
+    pub version: u32,
+    pub changes: &'static str,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Opcode {
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    /* bytes, including the opcode */
+    pub length: usize,
+    pub pops: usize,
+    pub pushes: usize,
+    pub summary: &'static str,
+    pub faults: Vec<String>,
+    /* in straight-line code, under the cost model the reference was made with */
+    pub cycles: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Fault {
+    pub name: String,
+    pub code: i32,
+    pub meaning: &'static str,
+}
+
+/*
+ * The instruction set as implemented: generated from the decoder, the
+ * stack effects the verifier uses, the explain-error table and a cost
+ * model, so that it cannot drift from the code it describes.
+ */
+#[derive(Clone, Debug, Serialize)]
+pub struct IsaReference {
+    pub isa_version: u32,
+    pub word_bits: u32,
+    pub changelog: Vec<Change>,
+    pub opcodes: Vec<Opcode>,
+    pub run_faults: Vec<Fault>,
+}
+
+/* what an instruction does; `a` is the top of the stack and `b` below it */
+fn summary(instruction: Instruction) -> &'static str {
+    match instruction {
+        Instruction::Nop => "Does nothing.",
+        Instruction::Halt => "Stops the run, leaving pc on the Halt.",
+        Instruction::Load => {
+            "Pops an address and pushes the word stored there."
+        }
+        Instruction::Store => {
+            "Pops an address a and a word b, and stores b at a."
+        }
+        Instruction::Push => "Pushes reg.",
+        Instruction::Pop => "Pops the top of the stack into reg.",
+        Instruction::Set(_) => {
+            "Sets reg to the literal that follows the opcode."
+        }
+        Instruction::Read => {
+            "Pushes the next input word; at the end of the input the EOF \
+             policy decides, and the status policy also pushes a status word."
+        }
+        Instruction::Write => "Pops a word and emits it.",
+        Instruction::Jump => {
+            "Continues from the instruction index on top of the stack, \
+             leaving it there."
+        }
+        Instruction::JumpIf => "Reserved; always faults.",
+        Instruction::Add => "Pops a and b, and pushes a + b.",
+        Instruction::Sub => "Pops a and b, and pushes a - b.",
+        Instruction::Mul => "Pops a and b, and pushes a * b.",
+        Instruction::Div => "Pops a and b, and pushes a / b.",
+        Instruction::Mod => "Pops a and b, and pushes a % b.",
+        Instruction::Cmp => {
+            "Pops a and b, and pushes 1 if they are equal, else 0."
+        }
+        Instruction::And => "Pops a and b, and pushes a & b.",
+        Instruction::Or => "Pops a and b, and pushes a | b.",
+        Instruction::Not => "Pops a and pushes !a.",
+        Instruction::Xor => "Pops a and b, and pushes a ^ b.",
+        Instruction::CasWord => {
+            "Pops an address, an expected word and a new word; stores the new \
+             word if the cell holds the expected one, and pushes the old value."
+        }
+        Instruction::FetchAdd => {
+            "Pops an address and a delta, adds the delta to the cell, and \
+             pushes the old value."
+        }
+        Instruction::Wrap => {
+            "Makes the next instruction wrap on overflow instead of faulting."
+        }
+        Instruction::IAdd => "Pops a and b, and pushes a + b, signed.",
+        Instruction::ISub => "Pops a and b, and pushes a - b, signed.",
+        Instruction::IMul => "Pops a and b, and pushes a * b, signed.",
+        Instruction::IDiv => {
+            "Pops a and b, and pushes a / b, signed, truncating towards zero."
+        }
+        Instruction::IMod => {
+            "Pops a and b, and pushes a % b, signed, with the sign of a."
+        }
+        Instruction::SLt => {
+            "Pops a and b, and pushes 1 if a < b signed, else 0."
+        }
+        Instruction::MulHi => {
+            "Pops a and b, and pushes the high word of their double-width \
+             product."
+        }
+        Instruction::DivRem => {
+            "Pops a high word, a low word and a divisor, and pushes the \
+             remainder and then the quotient of the double-width division."
+        }
+        Instruction::Rotl => "Pops a and b, and pushes a rotated left b bits.",
+        Instruction::Rotr => "Pops a and b, and pushes a rotated right b bits.",
+        Instruction::Popcnt => "Pops a and pushes its number of set bits.",
+        Instruction::Clz => "Pops a and pushes its number of leading zeros.",
+        Instruction::Ctz => "Pops a and pushes its number of trailing zeros.",
+        Instruction::TraceOn => "Resumes tracing; otherwise does nothing.",
+        Instruction::TraceOff => "Pauses tracing; otherwise does nothing.",
+        Instruction::Poll => "Pushes 1 if a Read would not block, else 0.",
+    }
+}
+
+/* the faults an instruction raises itself, mirroring `Machine::step` */
+fn faults(instruction: Instruction) -> Vec<MachineError> {
+    let (pops, pushes): (usize, usize) = stack_effect(instruction);
+    let mut faults: Vec<MachineError> = vec![];
+
+    match instruction {
+        Instruction::JumpIf => return vec![MachineError::IllegalInstruction],
+        Instruction::Pop => faults.push(MachineError::StackEmpty),
+        t if arity(t) > 0 => faults.push(MachineError::InsufficientArguments),
+        _ => {}
+    }
+
+    if pushes > pops {
+        faults.push(MachineError::StackFull);
+    }
+
+    match instruction {
+        Instruction::Add
+        | Instruction::Sub
+        | Instruction::Mul
+        | Instruction::Div
+        | Instruction::Mod
+        | Instruction::FetchAdd
+        | Instruction::IAdd
+        | Instruction::ISub
+        | Instruction::IMul
+        | Instruction::IDiv
+        | Instruction::IMod
+        | Instruction::DivRem => faults.push(MachineError::ArithmeticOverflow),
+        Instruction::Read => faults.extend([
+            MachineError::IllegalInstruction,
+            MachineError::EndOfInput,
+            MachineError::DeviceFailure,
+        ]),
+        Instruction::Write | Instruction::Poll => faults.extend([
+            MachineError::IllegalInstruction,
+            MachineError::DeviceFailure,
+        ]),
+        _ => {}
+    }
+
+    faults
+}
+
+impl IsaReference {
+    pub fn new(model: &CostModel) -> Self {
+        let opcodes: Vec<Opcode> = (0..=u8::MAX)
+            .filter_map(|opcode| match Instruction::try_from(&[opcode][..]) {
+                Ok(t) => Some(t),
+                /* Set only decodes with its literal */
+                Err(InstructionParseError::MissingLiteral) => {
+                    Some(Instruction::Set(Word::default()))
+                }
+                Err(_) => None,
+            })
+            .map(|t| {
+                let (pops, pushes): (usize, usize) = stack_effect(t);
+
+                Opcode {
+                    opcode: t.to_byte(),
+                    mnemonic: t.mnemonic(),
+                    length: t.encoded_len(),
+                    pops,
+                    pushes,
+                    summary: summary(t),
+                    faults: faults(t)
+                        .iter()
+                        .map(|e| format!("{:?}", e))
+                        .collect(),
+                    cycles: model.static_cost(t),
+                }
+            })
+            .collect();
+
+        Self {
+            isa_version: ISA_VERSION,
+            word_bits: Word::BITS,
+            changelog: CHANGELOG
+                .iter()
+                .map(|(version, changes)| Change {
+                    version: *version,
+                    changes,
+                })
+                .collect(),
+            opcodes,
+            run_faults: RUN_FAULTS.iter().map(|t| fault(*t)).collect(),
+        }
+    }
+
+    pub fn render_markdown(&self) -> String {
+        let mut out: String = format!(
+            "# Dreamer ISA, version {}\n\n\
+             Words are {} bits. Programs are a sequence of one-byte opcodes; \
+             Set is followed by its literal, {} bytes big-endian. The \
+             program counter counts instructions, not bytes.\n\n\
+             In the summaries, `a` is the top of the stack and `b` the word \
+             below it. Arithmetic faults on overflow unless the previous \
+             instruction was Wrap; a zero divisor always faults.\n\n",
+            self.isa_version,
+            self.word_bits,
+            word_bytes()
+        );
+
+        out.push_str("## Opcodes\n\n");
+        out.push_str(
+            "| Opcode | Mnemonic | Bytes | Pops | Pushes | Cycles | Summary | Faults |\n",
+        );
+        out.push_str("|---|---|---|---|---|---|---|---|\n");
+
+        for t in &self.opcodes {
+            out.push_str(&format!(
+                "| 0x{:02X} | {} | {} | {} | {} | {} | {} | {} |\n",
+                t.opcode,
+                t.mnemonic,
+                t.length,
+                t.pops,
+                t.pushes,
+                t.cycles,
+                t.summary.replace('|', "\\|"),
+                t.faults.join(", ")
+            ));
+        }
+
+        out.push_str("\n## Faults raised by any instruction\n\n");
+        out.push_str("| Code | Name | Meaning |\n|---|---|---|\n");
+
+        for t in &self.run_faults {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                t.code, t.name, t.meaning
+            ));
+        }
+
+        out.push_str("\n## Changelog\n\n");
+
+        for t in &self.changelog {
+            out.push_str(&format!("- Version {}: {}\n", t.version, t.changes));
+        }
+
+        out
+    }
+}
+
+fn fault(error: MachineError) -> Fault {
+    Fault {
+        name: format!("{:?}", error),
+        code: error.code(),
+        meaning: explanations()
+            .into_iter()
+            .find(|t| t.kind == ErrorKind::Runtime(error))
+            .map(|t| t.meaning)
+            .unwrap_or_default(),
+    }
+}
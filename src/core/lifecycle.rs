@@ -0,0 +1,45 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::Word;
+use crate::core::machine::MachineError;
+
+/*
+ * The milestones of a machine's life, for hosts that drive a state machine
+ * of their own off a run rather than polling it. `steps` counts the
+ * instructions a run completed; a faulting instruction is not counted.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event")]
+pub enum Lifecycle {
+    /* an observer was attached to a machine with its program loaded */
+    Loaded,
+    /* a run began, from the first instruction of the program */
+    Started,
+    /* a transaction was opened, leaving `depth` open */
+    Checkpointed {
+        depth: usize,
+    },
+    Faulted {
+        error: MachineError,
+        pc: Word,
+        steps: u64,
+    },
+    /* the run reached Halt or the end of the program */
+    Halted {
+        pc: Word,
+        steps: u64,
+    },
+}
+
+/* receives a machine's lifecycle events as they happen */
+pub trait Observer {
+    fn notify(&mut self, event: &Lifecycle);
+}
+
+impl fmt::Debug for dyn Observer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Observer")
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::config::{Config, Value};
+use crate::core::stack::MAX_STACK_DEPTH;
+
+/*
+ * A bundle of the limits a run can be held to. Each is optional, and an
+ * absent limit leaves the machine's own bound (if any) in place.
+ */
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct Limits {
+    pub steps: Option<u64>,
+    /* bytes, as counted by `State::footprint` */
+    pub memory: Option<usize>,
+    /* words */
+    pub stack: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LimitsError {
+    UnknownPreset(String),
+    UnknownKey(String, String),
+    InvalidValue(String, String),
+}
+
+impl Limits {
+    /* the built-in presets: `small`, `medium` and `large` */
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "small" => Some(Self {
+                steps: Some(10_000),
+                memory: Some(1 << 20),
+                stack: Some(256),
+            }),
+            "medium" => Some(Self {
+                steps: Some(1_000_000),
+                memory: Some(16 << 20),
+                stack: Some(4096),
+            }),
+            "large" => Some(Self {
+                steps: Some(100_000_000),
+                memory: Some(256 << 20),
+                stack: Some(MAX_STACK_DEPTH),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * Named limit presets, read from TOML of the form:
+ *
+ *     [classroom]
+ *     steps = 50000
+ *     memory = 65536
+ *     stack = 128
+ *
+ * Any of the keys may be left out. A preset defined here takes precedence
+ * over a built-in one of the same name.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct LimitPresets(HashMap<String, Limits>);
+
+impl TryFrom<&Config> for LimitPresets {
+    type Error = LimitsError;
+
+    fn try_from(config: &Config) -> Result<Self, Self::Error> {
+        let mut presets: HashMap<String, Limits> = HashMap::new();
+
+        for (name, table) in &config.0 {
+            /* the root table holds no presets */
+            if name.is_empty() {
+                continue;
+            }
+
+            let mut limits: Limits = Limits::default();
+
+            for (key, value) in table {
+                let invalid =
+                    || LimitsError::InvalidValue(name.clone(), key.clone());
+
+                match key.as_str() {
+                    "steps" => {
+                        limits.steps =
+                            Some(as_amount(value).ok_or_else(invalid)?)
+                    }
+                    "memory" => {
+                        limits.memory =
+                            Some(as_amount(value).ok_or_else(invalid)?)
+                    }
+                    "stack" => {
+                        limits.stack = Some(
+                            as_amount(value)
+                                .filter(|t| *t <= MAX_STACK_DEPTH)
+                                .ok_or_else(invalid)?,
+                        )
+                    }
+                    _ => {
+                        return Err(LimitsError::UnknownKey(
+                            name.clone(),
+                            key.clone(),
+                        ))
+                    }
+                }
+            }
+
+            presets.insert(name.clone(), limits);
+        }
+
+        Ok(Self(presets))
+    }
+}
+
+impl LimitPresets {
+    /* looks a preset up here first, then among the built-in ones */
+    pub fn get(&self, name: &str) -> Result<Limits, LimitsError> {
+        self.0
+            .get(name)
+            .copied()
+            .or_else(|| Limits::builtin(name))
+            .ok_or_else(|| LimitsError::UnknownPreset(name.to_string()))
+    }
+}
+
+fn as_amount<T: TryFrom<i128>>(value: &Value) -> Option<T> {
+    value.as_integer().and_then(|t| T::try_from(t).ok())
+}
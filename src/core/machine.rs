@@ -1,11 +1,20 @@
-use crate::common::types::Word;
-use crate::core::code::Code;
-use crate::core::instruction::Instruction;
-use crate::core::memory::Memory;
-use crate::core::stack::{Stack, MAX_STACK_DEPTH};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::{
+    from_signed, to_signed, wide, word_bytes, word_to_usize, SignedWord, Word,
+};
+use crate::core::code::{Code, Program};
+use crate::core::device::{Device, DeviceError, EofPolicy};
+use crate::core::instruction::{Instruction, OpcodeSet};
+use crate::core::lifecycle::{Lifecycle, Observer};
+use crate::core::limits::Limits;
+use crate::core::stack::{Stack, StackError, MAX_STACK_DEPTH};
 use crate::core::state::State;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MachineError {
     InsufficientArguments,
     OutOfBounds,
@@ -13,36 +22,264 @@ pub enum MachineError {
     StackEmpty,
     ArithmeticOverflow,
     IllegalInstruction,
+    UndecodableInstruction,
+    StepLimitExceeded,
+    InvariantViolation,
+    MemoryLimitExceeded,
+    EndOfInput,
+    DeviceFailure,
+    DeniedInstruction,
+    Interrupted,
 }
 
-#[derive(Clone, Debug)]
-pub struct Machine {
+impl MachineError {
+    /* stable process exit code reported when a run faults with this error */
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::InsufficientArguments => 10,
+            Self::OutOfBounds => 11,
+            Self::StackFull => 12,
+            Self::StackEmpty => 13,
+            Self::ArithmeticOverflow => 14,
+            Self::IllegalInstruction => 15,
+            Self::UndecodableInstruction => 16,
+            Self::StepLimitExceeded => 17,
+            Self::InvariantViolation => 18,
+            Self::MemoryLimitExceeded => 19,
+            /* 20 to 24 are taken by decoding errors */
+            Self::EndOfInput => 25,
+            Self::DeviceFailure => 26,
+            Self::DeniedInstruction => 27,
+            /* conventional code for termination by SIGINT */
+            Self::Interrupted => 130,
+        }
+    }
+}
+
+impl From<DeviceError> for MachineError {
+    fn from(value: DeviceError) -> Self {
+        match value {
+            DeviceError::EndOfInput => Self::EndOfInput,
+            /* a blocking read cut short by SIGINT or SIGTERM */
+            DeviceError::Io(io::ErrorKind::Interrupted) => Self::Interrupted,
+            DeviceError::MalformedInput | DeviceError::Io(_) => {
+                Self::DeviceFailure
+            }
+        }
+    }
+}
+
+impl From<StackError> for MachineError {
+    fn from(value: StackError) -> Self {
+        match value {
+            StackError::Full => Self::StackFull,
+            StackError::Empty => Self::StackEmpty,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Machine<P: Program = Code> {
     pub state: State,
-    pub prog: Code,
+    pub prog: P,
+    pub step_limit: Option<u64>,
+    pub memory_limit: Option<usize>,
+    pub stack_limit: Option<usize>,
+    pub denied: OpcodeSet,
+    pub interrupt: Option<&'static AtomicBool>,
+    pub device: Option<Box<dyn Device>>,
+    pub observer: Option<Box<dyn Observer>>,
+    savepoints: Vec<State>,
+    steps: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionError {
+    NoTransaction,
 }
 
-impl Machine {
-    pub fn new(prog: Code) -> Self {
+impl<P: Program> Machine<P> {
+    pub fn new(prog: P) -> Self {
         Self {
             state: Default::default(),
             prog,
+            step_limit: None,
+            memory_limit: None,
+            stack_limit: None,
+            denied: OpcodeSet::default(),
+            interrupt: None,
+            device: None,
+            observer: None,
+            savepoints: vec![],
+            steps: 0,
         }
     }
 
+    pub fn with_state(self, state: State) -> Self {
+        Self { state, ..self }
+    }
+
+    pub fn with_reg(mut self, reg: Word) -> Self {
+        self.state.reg = reg;
+        self
+    }
+
+    pub fn with_stack(mut self, stack: Stack) -> Self {
+        self.state.stack = stack;
+        self
+    }
+
+    /* faults once more than `limit` instructions have executed */
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    /* faults once the stack and memory occupy more than `limit` bytes */
+    pub fn with_memory_limit(mut self, limit: usize) -> Self {
+        self.memory_limit = Some(limit);
+        self
+    }
+
+    /* faults with StackFull once the stack would exceed `limit` words */
+    pub fn with_stack_limit(mut self, limit: usize) -> Self {
+        self.stack_limit = Some(limit);
+        self
+    }
+
+    /* applies every limit set in `limits`, leaving the others alone */
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.step_limit = limits.steps.or(self.step_limit);
+        self.memory_limit = limits.memory.or(self.memory_limit);
+        self.stack_limit = limits.stack.or(self.stack_limit);
+        self
+    }
+
+    /* faults with DeniedInstruction instead of executing any of `denied` */
+    pub fn with_denied(mut self, denied: OpcodeSet) -> Self {
+        self.denied = denied;
+        self
+    }
+
+    /* stops the run between instructions once `flag` is raised */
+    pub fn with_interrupt(mut self, flag: &'static AtomicBool) -> Self {
+        self.interrupt = Some(flag);
+        self
+    }
+
+    /* services Read and Write through `device` */
+    pub fn with_device(mut self, device: Box<dyn Device>) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    /* reports lifecycle events to `observer`, starting with Loaded */
+    pub fn with_observer(mut self, observer: Box<dyn Observer>) -> Self {
+        self.observer = Some(observer);
+        self.notify(Lifecycle::Loaded);
+        self
+    }
+
+    fn notify(&mut self, event: Lifecycle) {
+        if let Some(t) = &mut self.observer {
+            t.notify(&event);
+        }
+    }
+
+    /* the number of instructions the last run completed */
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /* records and reports how a run ended */
+    fn finish(&mut self, result: &Result<State, MachineError>, steps: u64) {
+        let pc: Word = self.state.pc;
+        self.steps = steps;
+
+        self.notify(match result {
+            Ok(_) => Lifecycle::Halted { pc, steps },
+            Err(e) => Lifecycle::Faulted {
+                error: *e,
+                pc,
+                steps,
+            },
+        });
+    }
+
+    /*
+     * Opens a transaction over the machine's state. Transactions nest: each
+     * commit or rollback closes the innermost one. Only the state is
+     * covered, so words already read from or written to a device stay
+     * consumed or emitted after a rollback.
+     */
+    pub fn begin(&mut self) {
+        self.savepoints.push(self.state.clone());
+        self.notify(Lifecycle::Checkpointed {
+            depth: self.savepoints.len(),
+        });
+    }
+
+    /* keeps everything done since the innermost `begin` */
+    pub fn commit(&mut self) -> Result<(), TransactionError> {
+        self.savepoints
+            .pop()
+            .map(|_| ())
+            .ok_or(TransactionError::NoTransaction)
+    }
+
+    /* restores the state as it was at the innermost `begin` */
+    pub fn rollback(&mut self) -> Result<(), TransactionError> {
+        self.state = self
+            .savepoints
+            .pop()
+            .ok_or(TransactionError::NoTransaction)?;
+        Ok(())
+    }
+
+    /* how many transactions are open */
+    pub fn transaction_depth(&self) -> usize {
+        self.savepoints.len()
+    }
+
+    /* a single transition, for a machine without a device */
     pub fn step(
         state: State,
         instruction: Instruction,
     ) -> Result<State, MachineError> {
+        let mut state: State = state;
+        Self::step_mut(&mut state, instruction)?;
+        Ok(state)
+    }
+
+    /*
+     * A single transition applied in place, for a machine without a
+     * device. On failure the state is left exactly as it was.
+     */
+    pub fn step_mut(
+        state: &mut State,
+        instruction: Instruction,
+    ) -> Result<(), MachineError> {
+        Self::step_with(state, instruction, None)
+    }
+
+    fn step_with(
+        state: &mut State,
+        instruction: Instruction,
+        device: Option<&mut dyn Device>,
+    ) -> Result<(), MachineError> {
         match instruction {
             Instruction::Nop => ops::nop(state),
+            /* markers for tooling, which the machine itself ignores */
+            Instruction::TraceOn | Instruction::TraceOff => ops::nop(state),
             Instruction::Halt => ops::halt(state),
             Instruction::Load => ops::load(state),
             Instruction::Store => ops::store(state),
             Instruction::Push => ops::push(state),
             Instruction::Pop => ops::pop(state),
             Instruction::Set(x) => ops::set(x, state),
-            Instruction::Read => ops::read(state),
-            Instruction::Write => ops::write(state),
+            Instruction::Read => ops::read(state, device),
+            Instruction::Write => ops::write(state, device),
+            Instruction::Poll => ops::poll(state, device),
             Instruction::Jump => ops::jump(state),
             Instruction::Add => ops::add(state),
             Instruction::Sub => ops::sub(state),
@@ -54,23 +291,65 @@ impl Machine {
             Instruction::Or => ops::or(state),
             Instruction::Not => ops::not(state),
             Instruction::Xor => ops::xor(state),
+            Instruction::CasWord => ops::cas_word(state),
+            Instruction::FetchAdd => ops::fetch_add(state),
+            Instruction::Wrap => ops::wrap(state),
+            Instruction::IAdd => ops::iadd(state),
+            Instruction::ISub => ops::isub(state),
+            Instruction::IMul => ops::imul(state),
+            Instruction::IDiv => ops::idiv(state),
+            Instruction::IMod => ops::imod(state),
+            Instruction::SLt => ops::slt(state),
+            Instruction::MulHi => ops::mul_hi(state),
+            Instruction::DivRem => ops::div_rem(state),
+            Instruction::Rotl => ops::rotl(state),
+            Instruction::Rotr => ops::rotr(state),
+            Instruction::Popcnt => ops::popcnt(state),
+            Instruction::Clz => ops::clz(state),
+            Instruction::Ctz => ops::ctz(state),
             _ => Err(MachineError::IllegalInstruction),
-        }
+        }?;
+
+        /* a Wrap prefix only ever applies to the instruction after it */
+        state.wrap = instruction == Instruction::Wrap;
+        Ok(())
     }
 
     pub fn run(&mut self) -> Result<State, MachineError> {
+        let mut steps: u64 = 0;
+
+        self.notify(Lifecycle::Started);
+        let result: Result<State, MachineError> = self.run_steps(&mut steps);
+        self.finish(&result, steps);
+        result
+    }
+
+    fn run_steps(&mut self, steps: &mut u64) -> Result<State, MachineError> {
         let mut curr_pos: Word = 0;
 
-        while (curr_pos as usize) < self.prog.0.len() {
-            /* grab current instruction */
-            let curr_instruction: Instruction = self.prog.0[curr_pos as usize];
+        /* grab current instruction */
+        while let Some(curr_instruction) = self.fetch(curr_pos)? {
+            if self.step_limit.is_some_and(|t| *steps >= t) {
+                return Err(MachineError::StepLimitExceeded);
+            }
 
-            /* apply transition function */
-            let new_state: State =
-                Self::step(self.state.clone(), curr_instruction)?;
+            if self.interrupt.is_some_and(|t| t.load(Ordering::SeqCst)) {
+                return Err(MachineError::Interrupted);
+            }
+
+            if self.denied.contains(curr_instruction) {
+                return Err(MachineError::DeniedInstruction);
+            }
 
-            /* write state */
-            self.state = new_state;
+            self.check_limits(curr_instruction)?;
+
+            /* apply transition function */
+            Self::step_with(
+                &mut self.state,
+                curr_instruction,
+                self.device.as_mut().map(|t| t.as_mut() as &mut dyn Device),
+            )?;
+            *steps += 1;
 
             if curr_instruction == Instruction::Halt {
                 return Ok(self.state.clone());
@@ -83,25 +362,68 @@ impl Machine {
         Ok(self.state.clone())
     }
 
+    /*
+     * As `run`, but calls `f` with the states either side of every step.
+     * Keeping the earlier state costs a copy of it per step.
+     */
     pub fn run_callback(
         &mut self,
-        f: &dyn Fn(State, Instruction),
+        f: &mut dyn FnMut(
+            &State,
+            Instruction,
+            &State,
+        ) -> Result<(), MachineError>,
+    ) -> Result<State, MachineError> {
+        let mut steps: u64 = 0;
+
+        self.notify(Lifecycle::Started);
+        let result: Result<State, MachineError> =
+            self.run_callback_steps(f, &mut steps);
+        self.finish(&result, steps);
+        result
+    }
+
+    fn run_callback_steps(
+        &mut self,
+        f: &mut dyn FnMut(
+            &State,
+            Instruction,
+            &State,
+        ) -> Result<(), MachineError>,
+        steps: &mut u64,
     ) -> Result<State, MachineError> {
         let mut curr_pos: Word = 0;
 
-        while (curr_pos as usize) < self.prog.0.len() {
-            /* grab current instruction */
-            let curr_instruction: Instruction = self.prog.0[curr_pos as usize];
+        /* grab current instruction */
+        while let Some(curr_instruction) = self.fetch(curr_pos)? {
+            if self.step_limit.is_some_and(|t| *steps >= t) {
+                return Err(MachineError::StepLimitExceeded);
+            }
+
+            if self.interrupt.is_some_and(|t| t.load(Ordering::SeqCst)) {
+                return Err(MachineError::Interrupted);
+            }
 
-            /* apply transition function */
-            let new_state: State =
-                Self::step(self.state.clone(), curr_instruction)?;
+            if self.denied.contains(curr_instruction) {
+                return Err(MachineError::DeniedInstruction);
+            }
 
-            /* callback */
-            f(new_state.clone(), curr_instruction);
+            self.check_limits(curr_instruction)?;
 
-            /* write state */
-            self.state = new_state;
+            /* apply transition function */
+            let old_state: State = self.state.clone();
+            Self::step_with(
+                &mut self.state,
+                curr_instruction,
+                self.device.as_mut().map(|t| t.as_mut() as &mut dyn Device),
+            )?;
+
+            /* callback, which may abort the run before the step lands */
+            if let Err(e) = f(&old_state, curr_instruction, &self.state) {
+                self.state = old_state;
+                return Err(e);
+            }
+            *steps += 1;
 
             if curr_instruction == Instruction::Halt {
                 return Ok(self.state.clone());
@@ -113,14 +435,76 @@ impl Machine {
 
         Ok(self.state.clone())
     }
+
+    /*
+     * Faults if executing `instruction` would take the stack over its depth
+     * limit or the footprint over the memory limit. Only Push, Read, Poll
+     * and a FetchAdd to a fresh cell grow either, so this is decided before
+     * the step rather than by undoing it afterwards.
+     */
+    fn check_limits(
+        &self,
+        instruction: Instruction,
+    ) -> Result<(), MachineError> {
+        if self.memory_limit.is_none() && self.stack_limit.is_none() {
+            return Ok(());
+        }
+
+        let pushed: usize = match instruction {
+            Instruction::Push | Instruction::Poll => 1,
+            Instruction::Read => match self.device.as_ref() {
+                Some(t) if t.eof_policy() == EofPolicy::Status => 2,
+                _ => 1,
+            },
+            _ => 0,
+        };
+        let growth: usize = match instruction {
+            Instruction::FetchAdd => match self.state.stack.peek() {
+                Some(t) if !self.state.memory.is_allocated(t) => 1,
+                _ => 0,
+            },
+            _ => pushed,
+        };
+
+        if self
+            .stack_limit
+            .is_some_and(|t| self.state.stack.depth() + pushed > t)
+        {
+            Err(MachineError::StackFull)
+        } else if self
+            .memory_limit
+            .is_some_and(|t| self.state.footprint() + growth * word_bytes() > t)
+        {
+            Err(MachineError::MemoryLimitExceeded)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn fetch(
+        &mut self,
+        pos: Word,
+    ) -> Result<Option<Instruction>, MachineError> {
+        let index: usize =
+            word_to_usize(pos).ok_or(MachineError::OutOfBounds)?;
+
+        self.prog
+            .fetch(index)
+            .map_err(|_| MachineError::UndecodableInstruction)
+    }
 }
 
+/*
+ * Every op checks everything that can make it fault before it touches the
+ * state, so a failed step leaves the state as it was.
+ */
 mod ops {
     use super::*;
     use crate::core::memory::LinearlyAddressable;
 
     const OPS_ARITY_LOAD: usize = 1;
     const OPS_ARITY_STORE: usize = 2;
+    const OPS_ARITY_WRITE: usize = 1;
     const OPS_ARITY_JUMP: usize = 1;
     const OPS_ARITY_ADD: usize = 2;
     const OPS_ARITY_SUB: usize = 2;
@@ -132,359 +516,660 @@ mod ops {
     const OPS_ARITY_OR: usize = 2;
     const OPS_ARITY_NEG: usize = 1;
     const OPS_ARITY_XOR: usize = 2;
+    const OPS_ARITY_CAS_WORD: usize = 3;
+    const OPS_ARITY_FETCH_ADD: usize = 2;
+    const OPS_ARITY_SIGNED: usize = 2;
+    const OPS_ARITY_MUL_HI: usize = 2;
+    const OPS_ARITY_DIV_REM: usize = 3;
+    const OPS_ARITY_ROTATE: usize = 2;
+    const OPS_ARITY_COUNT: usize = 1;
 
-    pub fn nop(state: State) -> Result<State, MachineError> {
-        Ok(State {
-            pc: state.pc + 1,
-            ..state
-        })
-    }
+    /* the top N words of the stack, top first */
+    fn operands<const N: usize>(
+        state: &State,
+    ) -> Result<[Word; N], MachineError> {
+        let elems: &[Word] = state.stack.elems();
 
-    pub fn halt(state: State) -> Result<State, MachineError> {
-        Ok(state)
-    }
-
-    pub fn load(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_LOAD {
+        if elems.len() < N {
             Err(MachineError::InsufficientArguments)
         } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let address: Word = tmp_stack.pop().unwrap();
-                    let data: Word = state.memory.read(address);
-                    tmp_stack.push(data).unwrap();
-                    tmp_stack
-                },
-                ..state
-            })
+            Ok(std::array::from_fn(|i| elems[elems.len() - 1 - i]))
         }
     }
 
-    pub fn store(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_STORE {
-            Err(MachineError::InsufficientArguments)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let _address: Word = tmp_stack.pop().unwrap();
-                    let _data: Word = tmp_stack.pop().unwrap();
-                    tmp_stack
-                },
-                memory: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let address: Word = tmp_stack.pop().unwrap();
-                    let data: Word = tmp_stack.pop().unwrap();
-
-                    let mut tmp_memory: Memory = state.memory.clone();
-                    tmp_memory.write(address, data);
-                    tmp_memory
-                },
-                ..state
-            })
+    /*
+     * Replaces the top `n` words of the stack with `words`, pushed in
+     * order, and moves on to the next instruction.
+     */
+    fn advance(
+        state: &mut State,
+        n: usize,
+        words: &[Word],
+    ) -> Result<(), MachineError> {
+        for _ in 0..n {
+            state.stack.pop()?;
         }
+
+        for word in words {
+            state.stack.push(*word)?;
+        }
+
+        state.pc += 1;
+        Ok(())
     }
 
-    pub fn push(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() == MAX_STACK_DEPTH {
-            Err(MachineError::StackFull)
+    /* faults on overflow unless the instruction is prefixed with Wrap */
+    fn wrapped(
+        state: &State,
+        (c, overflowed): (Word, bool),
+    ) -> Result<Word, MachineError> {
+        if overflowed && !state.wrap {
+            Err(MachineError::ArithmeticOverflow)
         } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    tmp_stack.push(state.reg).unwrap();
-                    tmp_stack
-                },
-                ..state
-            })
+            Ok(c)
         }
     }
 
-    pub fn pop(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() == 0 {
-            Err(MachineError::StackEmpty)
+    /* room for `n` more words on the stack */
+    fn reserve(state: &State, n: usize) -> Result<(), MachineError> {
+        if MAX_STACK_DEPTH - state.stack.depth() < n {
+            Err(MachineError::StackFull)
         } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    tmp_stack.pop().unwrap();
-                    tmp_stack
-                },
-                reg: state.stack.clone().pop().unwrap(),
-                ..state
-            })
+            Ok(())
         }
     }
 
-    pub fn set(value: Word, state: State) -> Result<State, MachineError> {
-        Ok(State {
-            pc: state.pc + 1,
-            reg: value,
-            ..state
-        })
+    pub fn nop(state: &mut State) -> Result<(), MachineError> {
+        advance(state, 0, &[])
     }
 
-    pub fn read(_state: State) -> Result<State, MachineError> {
-        todo!()
+    pub fn halt(_state: &mut State) -> Result<(), MachineError> {
+        Ok(())
     }
 
-    pub fn write(_state: State) -> Result<State, MachineError> {
-        todo!()
+    pub fn load(state: &mut State) -> Result<(), MachineError> {
+        let [address]: [Word; OPS_ARITY_LOAD] = operands(state)?;
+        let data: Word = state.memory.read(address);
+        advance(state, OPS_ARITY_LOAD, &[data])
     }
 
-    pub fn jump(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_JUMP {
-            Err(MachineError::InsufficientArguments)
-        } else {
-            Ok(State {
-                pc: state.stack.clone().pop().unwrap(),
-                ..state
-            })
-        }
+    pub fn store(state: &mut State) -> Result<(), MachineError> {
+        let [address, data]: [Word; OPS_ARITY_STORE] = operands(state)?;
+        state.memory.write(address, data);
+        advance(state, OPS_ARITY_STORE, &[])
     }
 
-    pub fn add(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_ADD {
-            return Err(MachineError::InsufficientArguments);
-        }
+    pub fn push(state: &mut State) -> Result<(), MachineError> {
+        reserve(state, 1)?;
+        let reg: Word = state.reg;
+        advance(state, 0, &[reg])
+    }
 
-        let a: Word = state.stack.clone().pop().unwrap();
-        let b: Word = {
-            let mut tmp_stack: Stack = state.stack.clone();
-            tmp_stack.pop().unwrap();
-            tmp_stack.pop().unwrap()
-        };
+    pub fn pop(state: &mut State) -> Result<(), MachineError> {
+        state.reg = state.stack.peek().ok_or(MachineError::StackEmpty)?;
+        advance(state, 1, &[])
+    }
 
-        if Word::checked_add(a, b).is_none() {
-            Err(MachineError::ArithmeticOverflow)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    let c: Word = a + b;
-                    tmp_stack.push(c).unwrap();
-                    tmp_stack
-                },
-                ..state
-            })
-        }
+    pub fn set(value: Word, state: &mut State) -> Result<(), MachineError> {
+        state.reg = value;
+        advance(state, 0, &[])
     }
 
-    pub fn sub(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_SUB {
-            return Err(MachineError::InsufficientArguments);
-        }
+    /*
+     * Pushes the next input word. What happens at the end of the input is
+     * up to the device's EOF policy, as is whether a status word follows.
+     */
+    pub fn read(
+        state: &mut State,
+        device: Option<&mut dyn Device>,
+    ) -> Result<(), MachineError> {
+        let device: &mut dyn Device =
+            device.ok_or(MachineError::IllegalInstruction)?;
+        let policy: EofPolicy = device.eof_policy();
 
-        let a: Word = state.stack.clone().pop().unwrap();
-        let b: Word = {
-            let mut tmp_stack: Stack = state.stack.clone();
-            tmp_stack.pop().unwrap();
-            tmp_stack.pop().unwrap()
-        };
+        reserve(
+            state,
+            match policy {
+                EofPolicy::Status => 2,
+                _ => 1,
+            },
+        )?;
 
-        if Word::checked_sub(a, b).is_none() {
-            Err(MachineError::ArithmeticOverflow)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    let c: Word = a - b;
-                    tmp_stack.push(c).unwrap();
-                    tmp_stack
-                },
-                ..state
-            })
+        match (device.read(), policy) {
+            (Ok(t), EofPolicy::Status) => advance(state, 0, &[t, 1]),
+            (Ok(t), _) => advance(state, 0, &[t]),
+            (Err(DeviceError::EndOfInput), EofPolicy::Sentinel(t)) => {
+                advance(state, 0, &[t])
+            }
+            (Err(DeviceError::EndOfInput), EofPolicy::Status) => {
+                advance(state, 0, &[0, 0])
+            }
+            (Err(e), _) => Err(e.into()),
         }
     }
-    pub fn mul(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_MUL {
-            return Err(MachineError::InsufficientArguments);
-        }
 
-        let a: Word = state.stack.clone().pop().unwrap();
-        let b: Word = {
-            let mut tmp_stack: Stack = state.stack.clone();
-            tmp_stack.pop().unwrap();
-            tmp_stack.pop().unwrap()
-        };
+    /* pushes 1 if a Read would not block, and 0 otherwise */
+    pub fn poll(
+        state: &mut State,
+        device: Option<&mut dyn Device>,
+    ) -> Result<(), MachineError> {
+        let device: &mut dyn Device =
+            device.ok_or(MachineError::IllegalInstruction)?;
+        reserve(state, 1)?;
+        let ready: bool = device.poll()?;
+        advance(state, 0, &[ready as Word])
+    }
 
-        if Word::checked_mul(a, b).is_none() {
-            Err(MachineError::ArithmeticOverflow)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    let c: Word = a * b;
-                    tmp_stack.push(c).unwrap();
-                    tmp_stack
-                },
-                ..state
-            })
+    /* pops a word and emits it */
+    pub fn write(
+        state: &mut State,
+        device: Option<&mut dyn Device>,
+    ) -> Result<(), MachineError> {
+        let device: &mut dyn Device =
+            device.ok_or(MachineError::IllegalInstruction)?;
+        let [word]: [Word; OPS_ARITY_WRITE] = operands(state)?;
+        device.write(word)?;
+        advance(state, OPS_ARITY_WRITE, &[])
+    }
+
+    pub fn jump(state: &mut State) -> Result<(), MachineError> {
+        let [target]: [Word; OPS_ARITY_JUMP] = operands(state)?;
+        state.pc = target;
+        Ok(())
+    }
+
+    pub fn add(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_ADD] = operands(state)?;
+        let c: Word = wrapped(state, a.overflowing_add(b))?;
+        advance(state, OPS_ARITY_ADD, &[c])
+    }
+
+    pub fn sub(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_SUB] = operands(state)?;
+        let c: Word = wrapped(state, a.overflowing_sub(b))?;
+        advance(state, OPS_ARITY_SUB, &[c])
+    }
+
+    pub fn mul(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_MUL] = operands(state)?;
+        let c: Word = wrapped(state, a.overflowing_mul(b))?;
+        advance(state, OPS_ARITY_MUL, &[c])
+    }
+
+    pub fn div(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_DIV] = operands(state)?;
+        let c: Word =
+            a.checked_div(b).ok_or(MachineError::ArithmeticOverflow)?;
+        advance(state, OPS_ARITY_DIV, &[c])
+    }
+
+    pub fn r#mod(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_MOD] = operands(state)?;
+        let c: Word =
+            a.checked_rem(b).ok_or(MachineError::ArithmeticOverflow)?;
+        advance(state, OPS_ARITY_MOD, &[c])
+    }
+
+    pub fn cmp(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_CMP] = operands(state)?;
+        advance(state, OPS_ARITY_CMP, &[(a == b) as Word])
+    }
+
+    pub fn and(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_AND] = operands(state)?;
+        advance(state, OPS_ARITY_AND, &[a & b])
+    }
+
+    pub fn or(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_OR] = operands(state)?;
+        advance(state, OPS_ARITY_OR, &[a | b])
+    }
+
+    pub fn not(state: &mut State) -> Result<(), MachineError> {
+        let [a]: [Word; OPS_ARITY_NEG] = operands(state)?;
+        advance(state, OPS_ARITY_NEG, &[!a])
+    }
+
+    pub fn xor(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_XOR] = operands(state)?;
+        advance(state, OPS_ARITY_XOR, &[a ^ b])
+    }
+
+    /*
+     * The atomic memory instructions are trivially atomic whilst there is
+     * only a single execution context, but their semantics are defined as a
+     * single indivisible read-modify-write of the memory cell so that they
+     * remain well-defined once memory is shared between contexts.
+     */
+    pub fn cas_word(state: &mut State) -> Result<(), MachineError> {
+        let [address, expected, new]: [Word; OPS_ARITY_CAS_WORD] =
+            operands(state)?;
+        let old: Word = state.memory.read(address);
+
+        if old == expected {
+            state.memory.write(address, new);
         }
+
+        advance(state, OPS_ARITY_CAS_WORD, &[old])
+    }
+
+    pub fn fetch_add(state: &mut State) -> Result<(), MachineError> {
+        let [address, delta]: [Word; OPS_ARITY_FETCH_ADD] = operands(state)?;
+        let old: Word = state.memory.read(address);
+        let new: Word = wrapped(state, old.overflowing_add(delta))?;
+        state.memory.write(address, new);
+        advance(state, OPS_ARITY_FETCH_ADD, &[old])
+    }
+
+    pub fn wrap(state: &mut State) -> Result<(), MachineError> {
+        advance(state, 0, &[])
+    }
+
+    /* the high word of the double-width product, which cannot overflow */
+    pub fn mul_hi(state: &mut State) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_MUL_HI] = operands(state)?;
+        advance(state, OPS_ARITY_MUL_HI, &[wide::mul_hi(a, b)])
+    }
+
+    /*
+     * Divides the double-width dividend whose high word is on top of the
+     * stack and whose low word is beneath it by the word beneath that,
+     * leaving the remainder and then the quotient on top. Faults if the
+     * divisor is zero or the quotient does not fit in a word, which is
+     * exactly when the high word is not less than the divisor.
+     */
+    pub fn div_rem(state: &mut State) -> Result<(), MachineError> {
+        let [hi, lo, divisor]: [Word; OPS_ARITY_DIV_REM] = operands(state)?;
+        let (quotient, remainder): (Word, Word) =
+            wide::div_rem(hi, lo, divisor)
+                .ok_or(MachineError::ArithmeticOverflow)?;
+        advance(state, OPS_ARITY_DIV_REM, &[remainder, quotient])
+    }
+
+    /* rotates a by b bits; only the low six bits of b matter */
+    pub fn rotl(state: &mut State) -> Result<(), MachineError> {
+        rotate(state, Word::rotate_left)
+    }
+
+    pub fn rotr(state: &mut State) -> Result<(), MachineError> {
+        rotate(state, Word::rotate_right)
+    }
+
+    pub fn popcnt(state: &mut State) -> Result<(), MachineError> {
+        count(state, Word::count_ones)
+    }
+
+    /* the leading and trailing zero counts of zero are both 64 */
+    pub fn clz(state: &mut State) -> Result<(), MachineError> {
+        count(state, Word::leading_zeros)
+    }
+
+    pub fn ctz(state: &mut State) -> Result<(), MachineError> {
+        count(state, Word::trailing_zeros)
+    }
+
+    fn rotate(
+        state: &mut State,
+        f: fn(Word, u32) -> Word,
+    ) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_ROTATE] = operands(state)?;
+        let c: Word = f(a, (b % Word::BITS as Word) as u32);
+        advance(state, OPS_ARITY_ROTATE, &[c])
+    }
+
+    fn count(
+        state: &mut State,
+        f: fn(Word) -> u32,
+    ) -> Result<(), MachineError> {
+        let [a]: [Word; OPS_ARITY_COUNT] = operands(state)?;
+        advance(state, OPS_ARITY_COUNT, &[f(a) as Word])
+    }
+
+    /*
+     * The signed instructions read words as two's-complement integers.
+     * Division truncates towards zero, so the remainder takes the sign of
+     * the dividend. Overflow faults unless the instruction is prefixed with
+     * Wrap; a zero divisor always faults.
+     */
+    pub fn iadd(state: &mut State) -> Result<(), MachineError> {
+        let wrap: bool = state.wrap;
+        signed(state, |a, b| match wrap {
+            true => Some(a.wrapping_add(b)),
+            false => a.checked_add(b),
+        })
+    }
+
+    pub fn isub(state: &mut State) -> Result<(), MachineError> {
+        let wrap: bool = state.wrap;
+        signed(state, |a, b| match wrap {
+            true => Some(a.wrapping_sub(b)),
+            false => a.checked_sub(b),
+        })
+    }
+
+    pub fn imul(state: &mut State) -> Result<(), MachineError> {
+        let wrap: bool = state.wrap;
+        signed(state, |a, b| match wrap {
+            true => Some(a.wrapping_mul(b)),
+            false => a.checked_mul(b),
+        })
+    }
+
+    pub fn idiv(state: &mut State) -> Result<(), MachineError> {
+        let wrap: bool = state.wrap;
+        signed(state, |a, b| match (b, wrap) {
+            (0, _) => None,
+            (_, true) => Some(a.wrapping_div(b)),
+            (_, false) => a.checked_div(b),
+        })
+    }
+
+    /* the remainder of the most negative word by -1 is defined to be 0 */
+    pub fn imod(state: &mut State) -> Result<(), MachineError> {
+        signed(state, |a, b| match b {
+            0 => None,
+            _ => Some(a.wrapping_rem(b)),
+        })
+    }
+
+    pub fn slt(state: &mut State) -> Result<(), MachineError> {
+        signed(state, |a, b| Some((a < b) as SignedWord))
     }
 
-    pub fn div(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_DIV {
-            return Err(MachineError::InsufficientArguments);
+    /* replaces the top two words, a and b, with f(a, b) */
+    fn signed(
+        state: &mut State,
+        f: impl Fn(SignedWord, SignedWord) -> Option<SignedWord>,
+    ) -> Result<(), MachineError> {
+        let [a, b]: [Word; OPS_ARITY_SIGNED] = operands(state)?;
+        let c: SignedWord = f(to_signed(a), to_signed(b))
+            .ok_or(MachineError::ArithmeticOverflow)?;
+        advance(state, OPS_ARITY_SIGNED, &[from_signed(c)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::rng::XorShift;
+    use crate::core::code::{CodeParseError, LazyCode, ParseMode, VecCode};
+    use crate::core::memory::LinearlyAddressable;
+
+    const MIN: SignedWord = SignedWord::MIN;
+    const MAX: SignedWord = SignedWord::MAX;
+
+    /* a state whose stack holds `stack`, listed top first */
+    fn with_stack(stack: &[Word]) -> State {
+        State {
+            stack: Stack::try_from(
+                stack.iter().rev().copied().collect::<Vec<Word>>(),
+            )
+            .expect("stack fits"),
+            ..State::default()
         }
+    }
 
-        let a: Word = state.stack.clone().pop().unwrap();
-        let b: Word = {
-            let mut tmp_stack: Stack = state.stack.clone();
-            tmp_stack.pop().unwrap();
-            tmp_stack.pop().unwrap()
-        };
+    /* executes `instruction` over `stack`, listed top first */
+    fn step(
+        instruction: Instruction,
+        stack: &[Word],
+    ) -> Result<Vec<Word>, MachineError> {
+        <Machine>::step(with_stack(stack), instruction)
+            .map(|t| t.stack.elems().iter().rev().copied().collect())
+    }
 
-        if Word::checked_div(a, b).is_none() {
-            Err(MachineError::ArithmeticOverflow)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    let c: Word = a / b;
-                    tmp_stack.push(c).unwrap();
-                    tmp_stack
-                },
-                ..state
-            })
+    /* as `step`, but with the instruction prefixed by Wrap */
+    fn step_wrapped(
+        instruction: Instruction,
+        stack: &[Word],
+    ) -> Result<Vec<Word>, MachineError> {
+        let state: State =
+            <Machine>::step(with_stack(stack), Instruction::Wrap)?;
+        <Machine>::step(state, instruction)
+            .map(|t| t.stack.elems().iter().rev().copied().collect())
+    }
+
+    fn signed(
+        instruction: Instruction,
+        a: SignedWord,
+        b: SignedWord,
+    ) -> Result<SignedWord, MachineError> {
+        step(instruction, &[from_signed(a), from_signed(b)])
+            .map(|t| to_signed(t[0]))
+    }
+
+    #[test]
+    fn test_signed_overflow_faults() {
+        for (instruction, a, b) in [
+            (Instruction::IAdd, MAX, 1),
+            (Instruction::ISub, MIN, 1),
+            (Instruction::IMul, MIN, -1),
+            (Instruction::IDiv, MIN, -1),
+        ] {
+            assert_eq!(
+                signed(instruction, a, b),
+                Err(MachineError::ArithmeticOverflow),
+                "{:?}",
+                instruction
+            );
         }
     }
 
-    pub fn r#mod(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_MOD {
-            Err(MachineError::InsufficientArguments)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    let c: Word = a % b;
-                    tmp_stack.push(c).unwrap();
-                    tmp_stack
-                },
-                ..state
-            })
+    #[test]
+    fn test_signed_overflow_wraps_after_wrap() {
+        for (instruction, a, b, c) in [
+            (Instruction::IAdd, MAX, 1, MIN),
+            (Instruction::ISub, MIN, 1, MAX),
+            (Instruction::IMul, MIN, -1, MIN),
+            (Instruction::IDiv, MIN, -1, MIN),
+        ] {
+            assert_eq!(
+                step_wrapped(instruction, &[from_signed(a), from_signed(b)]),
+                Ok(vec![from_signed(c)]),
+                "{:?}",
+                instruction
+            );
         }
     }
 
-    pub fn cmp(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_CMP {
-            Err(MachineError::InsufficientArguments)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    if a == b {
-                        tmp_stack.push(1).unwrap();
-                    } else {
-                        tmp_stack.push(0).unwrap();
-                    }
+    #[test]
+    fn test_signed_division_truncates_towards_zero() {
+        assert_eq!(signed(Instruction::IDiv, -7, 2), Ok(-3));
+        assert_eq!(signed(Instruction::IMod, -7, 2), Ok(-1));
+        assert_eq!(signed(Instruction::IMod, 7, -2), Ok(1));
+    }
 
-                    tmp_stack
-                },
-                ..state
-            })
-        }
+    #[test]
+    fn test_signed_remainder_of_min_by_minus_one_is_zero() {
+        assert_eq!(signed(Instruction::IMod, MIN, -1), Ok(0));
     }
 
-    pub fn and(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_AND {
-            Err(MachineError::InsufficientArguments)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    let c: Word = a & b;
-                    tmp_stack.push(c).unwrap();
-
-                    tmp_stack
-                },
-                ..state
-            })
+    #[test]
+    fn test_signed_zero_divisor_faults_even_when_wrapped() {
+        for instruction in [Instruction::IDiv, Instruction::IMod] {
+            assert_eq!(
+                step_wrapped(instruction, &[1, 0]),
+                Err(MachineError::ArithmeticOverflow)
+            );
         }
     }
 
-    pub fn or(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_OR {
-            Err(MachineError::InsufficientArguments)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    let c: Word = a | b;
-                    tmp_stack.push(c).unwrap();
-
-                    tmp_stack
-                },
-                ..state
-            })
+    #[test]
+    fn test_slt_compares_signed() {
+        assert_eq!(signed(Instruction::SLt, -1, 0), Ok(1));
+        assert_eq!(signed(Instruction::SLt, 0, -1), Ok(0));
+        assert_eq!(signed(Instruction::SLt, MIN, MAX), Ok(1));
+    }
+
+    #[test]
+    fn test_mul_hi() {
+        assert_eq!(
+            step(Instruction::MulHi, &[Word::MAX, Word::MAX]),
+            Ok(vec![Word::MAX - 1])
+        );
+        assert_eq!(step(Instruction::MulHi, &[1 << 32, 1 << 32]), Ok(vec![1]));
+    }
+
+    #[test]
+    fn test_div_rem() {
+        /* hi, lo, divisor; the quotient ends up on top of the remainder */
+        assert_eq!(step(Instruction::DivRem, &[0, 7, 2]), Ok(vec![3, 1]));
+        assert_eq!(step(Instruction::DivRem, &[1, 1, 2]), Ok(vec![1 << 63, 1]));
+    }
+
+    #[test]
+    fn test_div_rem_quotient_overflow_faults() {
+        for stack in [[2, 0, 2], [3, 0, 2], [0, 1, 0]] {
+            assert_eq!(
+                step(Instruction::DivRem, &stack),
+                Err(MachineError::ArithmeticOverflow),
+                "{:?}",
+                stack
+            );
         }
     }
 
-    pub fn not(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_NEG {
-            Err(MachineError::InsufficientArguments)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = !a;
-                    tmp_stack.push(b).unwrap();
-
-                    tmp_stack
-                },
-                ..state
-            })
+    #[test]
+    fn test_rotate_uses_low_six_bits() {
+        assert_eq!(step(Instruction::Rotl, &[5, 64]), Ok(vec![5]));
+        assert_eq!(step(Instruction::Rotl, &[1, 65]), Ok(vec![2]));
+        assert_eq!(step(Instruction::Rotr, &[1, 1]), Ok(vec![1 << 63]));
+        assert_eq!(step(Instruction::Rotr, &[1, 128]), Ok(vec![1]));
+    }
+
+    #[test]
+    fn test_counts_of_zero() {
+        assert_eq!(step(Instruction::Clz, &[0]), Ok(vec![64]));
+        assert_eq!(step(Instruction::Ctz, &[0]), Ok(vec![64]));
+        assert_eq!(step(Instruction::Popcnt, &[0]), Ok(vec![0]));
+        assert_eq!(step(Instruction::Popcnt, &[Word::MAX]), Ok(vec![64]));
+    }
+
+    #[test]
+    fn test_cas_word_on_unallocated_cell() {
+        /* address, expected, new; an unallocated cell holds zero */
+        let state: Result<State, MachineError> =
+            <Machine>::step(with_stack(&[16, 0, 9]), Instruction::CasWord);
+        assert!(state
+            .is_ok_and(|t| t.memory.read(16) == 9 && t.stack.elems() == [0]));
+
+        let state: Result<State, MachineError> =
+            <Machine>::step(with_stack(&[16, 1, 9]), Instruction::CasWord);
+        assert!(state.is_ok_and(
+            |t| !t.memory.is_allocated(16) && t.stack.elems() == [0]
+        ));
+    }
+
+    #[test]
+    fn test_fetch_add() {
+        let mut state: State = with_stack(&[16, 3]);
+        state.memory.write(16, 4);
+
+        let state: Result<State, MachineError> =
+            <Machine>::step(state, Instruction::FetchAdd);
+        assert!(state
+            .is_ok_and(|t| t.memory.read(16) == 7 && t.stack.elems() == [4]));
+    }
+
+    #[test]
+    fn test_fetch_add_overflow_leaves_state_unchanged() {
+        let mut state: State = with_stack(&[16, 1]);
+        state.memory.write(16, Word::MAX);
+
+        assert_eq!(
+            <Machine>::step_mut(&mut state, Instruction::FetchAdd),
+            Err(MachineError::ArithmeticOverflow)
+        );
+        assert_eq!(state.memory.read(16), Word::MAX);
+        assert_eq!(state.stack.elems(), &[1, 16]);
+        assert_eq!(state.pc, 0);
+    }
+
+    #[test]
+    fn test_fetch_add_wraps_after_wrap() {
+        let mut state: State = with_stack(&[16, 2]);
+        state.memory.write(16, Word::MAX);
+
+        let state: Result<State, MachineError> =
+            <Machine>::step(state, Instruction::Wrap)
+                .and_then(|t| <Machine>::step(t, Instruction::FetchAdd));
+        assert!(state.is_ok_and(
+            |t| t.memory.read(16) == 1 && t.stack.elems() == [Word::MAX]
+        ));
+    }
+
+    #[test]
+    fn test_wrap_applies_to_the_next_instruction_only() {
+        /* MAX + 2 wraps to 1, but 1 + MAX then overflows */
+        let state: Result<State, MachineError> = <Machine>::step(
+            with_stack(&[Word::MAX, 2, Word::MAX]),
+            Instruction::Wrap,
+        )
+        .and_then(|t| <Machine>::step(t, Instruction::Add));
+        assert!(state.as_ref().is_ok_and(|t| !t.wrap));
+
+        assert_eq!(
+            state
+                .and_then(|t| <Machine>::step(t, Instruction::Add))
+                .err(),
+            Some(MachineError::ArithmeticOverflow)
+        );
+    }
+
+    /*
+     * Random bytes, mostly valid opcodes, with Set literals that are often
+     * cut short by the end of the program
+     */
+    fn random_program(rng: &mut XorShift) -> Vec<u8> {
+        let len: u64 = rng.below(64);
+        let mut bytes: Vec<u8> = vec![];
+
+        while (bytes.len() as u64) < len {
+            match rng.below(8) {
+                0 => {
+                    bytes.push(0x06);
+                    for _ in 0..rng.below(10) {
+                        bytes.push(rng.next_u64() as u8);
+                    }
+                }
+                1 => bytes.push(rng.next_u64() as u8),
+                _ => bytes.push(rng.below(0x28) as u8),
+            }
         }
+
+        bytes
     }
 
-    pub fn xor(state: State) -> Result<State, MachineError> {
-        if state.stack.depth() < OPS_ARITY_XOR {
-            Err(MachineError::InsufficientArguments)
-        } else {
-            Ok(State {
-                pc: state.pc + 1,
-                stack: {
-                    let mut tmp_stack: Stack = state.stack.clone();
-                    let a: Word = tmp_stack.pop().unwrap();
-                    let b: Word = tmp_stack.pop().unwrap();
-                    let c: Word = a ^ b;
-                    tmp_stack.push(c).unwrap();
-
-                    tmp_stack
-                },
-                ..state
-            })
+    /*
+     * Stands in for a fuzz target: decoding and running untrusted bytes,
+     * from an arbitrary initial state, must fail with an error rather than
+     * panic, however the bytes and state are chosen.
+     */
+    #[test]
+    fn test_untrusted_programs_do_not_panic() {
+        for seed in 0..65536 {
+            let mut rng: XorShift = XorShift::new(seed);
+            let bytes: Vec<u8> = random_program(&mut rng);
+            let reg: Word = rng.operand();
+            let stack: Vec<Word> =
+                (0..rng.below(4)).map(|_| rng.operand()).collect();
+            let stack: Stack = Stack::try_from(stack).expect("stack fits");
+
+            let _ = VecCode::parse(&bytes, ParseMode::Strict);
+            let (code, _): (VecCode, Option<CodeParseError>) =
+                VecCode::parse(&bytes, ParseMode::Lenient)
+                    .expect("lenient parsing does not fail");
+
+            let _ = Machine::new(code)
+                .with_reg(reg)
+                .with_stack(stack.clone())
+                .with_step_limit(1_000)
+                .run();
+            let _ = Machine::new(LazyCode::new(bytes))
+                .with_reg(reg)
+                .with_stack(stack)
+                .with_step_limit(1_000)
+                .run();
         }
     }
 }
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::limits::Limits;
+use crate::core::machine::MachineError;
+
+pub const MANIFEST_VERSION: u8 = 1;
+
+/*
+ * Everything needed to reproduce a run and check that a rerun matches it:
+ * what ran, on which build and engine, under which limits, from which
+ * inputs, and how it ended. Hashes are FNV-1a in hexadecimal, and state
+ * hashes are `State::digest`.
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u8,
+    pub tool_version: String,
+    pub isa_version: u32,
+    pub program: String,
+    pub program_hash: String,
+    /* `eager` or `lazy` */
+    pub engine: String,
+    pub limits: Limits,
+    pub denied: Option<String>,
+    pub initial_state_hash: String,
+    /* the file Read took its input from, if any */
+    pub input_hash: Option<String>,
+    pub fault: Option<MachineError>,
+    pub final_state_hash: String,
+    pub steps: u64,
+    pub cycles: Option<u64>,
+    pub energy: Option<u64>,
+}
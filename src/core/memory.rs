@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::types::Word;
+use crate::common::types::{from_signed, to_signed, SignedWord, Word};
 
 pub trait LinearlyAddressable {
     fn read(&self, address: Word) -> Word;
@@ -22,15 +22,154 @@ impl HashMemory {
     pub fn new() -> Self {
         Self(HashMap::new())
     }
+
+    /* every cell holding a non-zero word, in address order */
+    pub fn cells(&self) -> Vec<(Word, Word)> {
+        let mut cells: Vec<(Word, Word)> = self
+            .0
+            .iter()
+            .filter(|(_, data)| **data != Word::default())
+            .map(|(address, data)| (*address, *data))
+            .collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    /* the number of cells backed by storage, including any holding zero */
+    pub fn allocated(&self) -> usize {
+        self.0.len()
+    }
+
+    /* whether `address` is backed by storage, even if it holds zero */
+    pub fn is_allocated(&self, address: Word) -> bool {
+        self.0.contains_key(&address)
+    }
+}
+
+/*
+ * Host types that occupy a fixed number of consecutive memory cells, so
+ * that embedders can exchange structured data with programs without
+ * packing words by hand. Structs whose fields all have a layout can get
+ * one from `word_layout!`, which places the fields in declaration order.
+ */
+pub trait WordLayout: Sized {
+    const WORDS: usize;
+
+    /* decodes a value from exactly `WORDS` words */
+    fn from_words(words: &[Word]) -> Self;
+
+    /* appends the `WORDS` words encoding this value */
+    fn to_words(&self, words: &mut Vec<Word>);
+}
+
+impl WordLayout for Word {
+    const WORDS: usize = 1;
+
+    fn from_words(words: &[Word]) -> Self {
+        words[0]
+    }
+
+    fn to_words(&self, words: &mut Vec<Word>) {
+        words.push(*self);
+    }
+}
+
+impl WordLayout for SignedWord {
+    const WORDS: usize = 1;
+
+    fn from_words(words: &[Word]) -> Self {
+        to_signed(words[0])
+    }
+
+    fn to_words(&self, words: &mut Vec<Word>) {
+        words.push(from_signed(*self));
+    }
+}
+
+/* any non-zero word reads as true */
+impl WordLayout for bool {
+    const WORDS: usize = 1;
+
+    fn from_words(words: &[Word]) -> Self {
+        words[0] != 0
+    }
+
+    fn to_words(&self, words: &mut Vec<Word>) {
+        words.push(*self as Word);
+    }
+}
+
+impl<T: WordLayout, const N: usize> WordLayout for [T; N] {
+    const WORDS: usize = T::WORDS * N;
+
+    fn from_words(words: &[Word]) -> Self {
+        std::array::from_fn(|i| T::from_words(&words[i * T::WORDS..]))
+    }
+
+    fn to_words(&self, words: &mut Vec<Word>) {
+        for elem in self {
+            elem.to_words(words);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! word_layout {
+    ($name:ident { $($field:ident: $ty:ty),* $(,)? }) => {
+        impl $crate::core::memory::WordLayout for $name {
+            const WORDS: usize =
+                0 $(+ <$ty as $crate::core::memory::WordLayout>::WORDS)*;
+
+            #[allow(unused_assignments)]
+            fn from_words(words: &[$crate::common::types::Word]) -> Self {
+                let mut offset: usize = 0;
+
+                Self {
+                    $($field: {
+                        let field: $ty =
+                            $crate::core::memory::WordLayout::from_words(
+                                &words[offset..],
+                            );
+                        offset +=
+                            <$ty as $crate::core::memory::WordLayout>::WORDS;
+                        field
+                    },)*
+                }
+            }
+
+            fn to_words(&self, words: &mut Vec<$crate::common::types::Word>) {
+                $($crate::core::memory::WordLayout::to_words(
+                    &self.$field,
+                    words,
+                );)*
+            }
+        }
+    };
+}
+
+impl HashMemory {
+    /* reads a value from the consecutive cells starting at `address` */
+    pub fn view<T: WordLayout>(&self, address: Word) -> T {
+        let words: Vec<Word> = (0..T::WORDS as Word)
+            .map(|i| self.read(address.wrapping_add(i)))
+            .collect();
+        T::from_words(&words)
+    }
+
+    /* writes a value to the consecutive cells starting at `address` */
+    pub fn store<T: WordLayout>(&mut self, address: Word, value: &T) {
+        let mut words: Vec<Word> = Vec::with_capacity(T::WORDS);
+        value.to_words(&mut words);
+
+        for (i, word) in words.into_iter().enumerate() {
+            self.write(address.wrapping_add(i as Word), word);
+        }
+    }
 }
 
 impl LinearlyAddressable for HashMemory {
     fn read(&self, address: Word) -> Word {
-        if self.0.contains_key(&address) {
-            self.0.get(&address).copied().unwrap()
-        } else {
-            Default::default()
-        }
+        self.0.get(&address).copied().unwrap_or_default()
     }
 
     fn write(&mut self, address: Word, data: Word) {
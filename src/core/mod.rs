@@ -1,6 +1,44 @@
+#![deny(clippy::unwrap_used)]
+
+pub mod abi;
+pub mod analysis;
+pub mod asm;
+pub mod cache;
 pub mod code;
+pub mod cost;
+pub mod device;
+pub mod diagnostic;
+pub mod diff;
+pub mod dump;
+pub mod equiv;
+pub mod error;
+pub mod event;
+pub mod explain;
+pub mod expr;
+pub mod filter;
+pub mod fuzz;
+pub mod grade;
+pub mod inspect;
 pub mod instruction;
+pub mod invariant;
+pub mod isa;
+pub mod lifecycle;
+pub mod limits;
 pub mod machine;
+pub mod manifest;
 pub mod memory;
+pub mod pipeline;
+pub mod profile;
+pub mod progress;
+pub mod query;
+pub mod region;
+pub mod schema;
+pub mod snapshot;
 pub mod stack;
 pub mod state;
+pub mod stats;
+pub mod superopt;
+pub mod teach;
+pub mod throttle;
+pub mod torture;
+pub mod trace;
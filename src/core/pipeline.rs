@@ -0,0 +1,269 @@
+use crate::common::types::Word;
+use crate::core::cost::is_branch_taken;
+use crate::core::instruction::Instruction;
+use crate::core::state::State;
+
+pub const DEFAULT_PIPELINE_STAGES: usize = 5;
+pub const MIN_PIPELINE_STAGES: usize = 2;
+pub const MAX_PIPELINE_STAGES: usize = 64;
+
+/* stage in which a taken branch's target becomes known */
+const BRANCH_RESOLVE_STAGE: usize = 3;
+
+const RESOURCE_STACK: u8 = 1 << 0;
+const RESOURCE_REG: u8 = 1 << 1;
+const RESOURCE_MEMORY: u8 = 1 << 2;
+const RESOURCES: [u8; 3] = [RESOURCE_STACK, RESOURCE_REG, RESOURCE_MEMORY];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PipelineError {
+    TooFewStages,
+    TooManyStages,
+}
+
+/* the pipeline's schedule for a single executed instruction */
+#[derive(Clone, Copy, Debug)]
+pub struct Slot {
+    pub pc: Word,
+    pub instruction: Instruction,
+    pub fetch: u64,
+    pub data_stalls: u64,
+    pub control_stalls: u64,
+}
+
+/*
+ * An in-order pipeline of N stages with no forwarding. Operands are read in
+ * the second (decode) stage and results become visible after the final
+ * stage, so an instruction reading the stack, register or memory stalls in
+ * decode until the most recent writer of that resource has retired. Taken
+ * branches are resolved in the third stage, and fetch restarts only after
+ * that point.
+ */
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    stages: usize,
+    /* kept only when a timeline is wanted, as it grows with every step */
+    slots: Option<Vec<Slot>>,
+    issued: u64,
+    ready: [u64; RESOURCES.len()],
+    next_fetch: u64,
+    retired: u64,
+    data_stalls: u64,
+    control_stalls: u64,
+}
+
+impl Pipeline {
+    pub fn new(stages: usize) -> Result<Self, PipelineError> {
+        if stages < MIN_PIPELINE_STAGES {
+            return Err(PipelineError::TooFewStages);
+        }
+
+        if stages > MAX_PIPELINE_STAGES {
+            return Err(PipelineError::TooManyStages);
+        }
+
+        Ok(Self {
+            stages,
+            slots: None,
+            issued: 0,
+            ready: [0; RESOURCES.len()],
+            next_fetch: 0,
+            retired: 0,
+            data_stalls: 0,
+            control_stalls: 0,
+        })
+    }
+
+    /* records each instruction's schedule, for `timeline` */
+    pub fn with_timeline(mut self) -> Self {
+        self.slots = Some(vec![]);
+        self
+    }
+
+    pub fn issue(
+        &mut self,
+        old: &State,
+        instruction: Instruction,
+        new: &State,
+    ) {
+        let stages: u64 = self.stages as u64;
+        let (reads, writes): (u8, u8) = effects(instruction);
+        let fetch: u64 = self.next_fetch;
+
+        /* decode must end after every operand's producer has retired */
+        let earliest_decode: u64 = RESOURCES
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| reads & **r != 0)
+            .map(|(i, _)| self.ready[i])
+            .max()
+            .unwrap_or(0);
+        let data_stalls: u64 = earliest_decode.saturating_sub(fetch + 1);
+        let decode: u64 = fetch + 1 + data_stalls;
+        let writeback: u64 = fetch + data_stalls + stages - 1;
+
+        for (i, r) in RESOURCES.iter().enumerate() {
+            if writes & r != 0 {
+                self.ready[i] = writeback + 1;
+            }
+        }
+
+        /* the next fetch happens once this instruction leaves decode */
+        let mut next_fetch: u64 = decode;
+        let mut control_stalls: u64 = 0;
+
+        if is_branch_taken(old, instruction, new) {
+            let resolved: u64 = fetch
+                + data_stalls
+                + (BRANCH_RESOLVE_STAGE.min(self.stages) as u64);
+            control_stalls = resolved.saturating_sub(next_fetch);
+            next_fetch = next_fetch.max(resolved);
+        }
+
+        if let Some(slots) = &mut self.slots {
+            slots.push(Slot {
+                pc: old.pc,
+                instruction,
+                fetch,
+                data_stalls,
+                control_stalls,
+            });
+        }
+
+        self.issued += 1;
+        self.next_fetch = next_fetch;
+        self.retired = writeback + 1;
+        self.data_stalls += data_stalls;
+        self.control_stalls += control_stalls;
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.retired
+    }
+
+    pub fn data_stalls(&self) -> u64 {
+        self.data_stalls
+    }
+
+    pub fn control_stalls(&self) -> u64 {
+        self.control_stalls
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        self.slots.as_deref().unwrap_or(&[])
+    }
+
+    pub fn report(&self) -> String {
+        format!(
+            "pipeline: {} stages, {} instructions, {} cycles, \
+             {} data stall cycles, {} control stall cycles",
+            self.stages,
+            self.issued,
+            self.cycles(),
+            self.data_stalls,
+            self.control_stalls
+        )
+    }
+
+    /*
+     * Renders one row per executed instruction and one column per cycle.
+     * Stages are labelled F(etch), D(ecode), X (execute), M(emory) and
+     * W(riteback), with `*` marking cycles spent stalled in decode. Empty
+     * unless the pipeline was built `with_timeline`.
+     */
+    pub fn timeline(&self) -> String {
+        let labels: Vec<char> = self.stage_labels();
+        let mut out: String = String::new();
+
+        for slot in self.slots() {
+            let mut row: String = " ".repeat(slot.fetch as usize);
+
+            row.push(labels[0]);
+            row.push_str(&"*".repeat(slot.data_stalls as usize));
+            row.extend(labels[1..].iter());
+
+            out.push_str(&format!(
+                "{:>6}  {:<12} |{}\n",
+                slot.pc,
+                format!("{:?}", slot.instruction),
+                row
+            ));
+        }
+
+        out
+    }
+
+    fn stage_labels(&self) -> Vec<char> {
+        (0..self.stages)
+            .map(|i| match i {
+                0 => 'F',
+                _ if i == self.stages - 1 => 'W',
+                1 => 'D',
+                _ if i == self.stages - 2 && self.stages >= 5 => 'M',
+                _ => 'X',
+            })
+            .collect()
+    }
+}
+
+/* the resources an instruction reads and writes respectively */
+fn effects(instruction: Instruction) -> (u8, u8) {
+    match instruction {
+        Instruction::Nop
+        | Instruction::Halt
+        | Instruction::Wrap
+        | Instruction::TraceOn
+        | Instruction::TraceOff => (0, 0),
+        Instruction::Set(_) => (0, RESOURCE_REG),
+        Instruction::Push => (RESOURCE_REG, RESOURCE_STACK),
+        Instruction::Pop => (RESOURCE_STACK, RESOURCE_STACK | RESOURCE_REG),
+        Instruction::Load => (RESOURCE_STACK | RESOURCE_MEMORY, RESOURCE_STACK),
+        Instruction::Store => {
+            (RESOURCE_STACK, RESOURCE_STACK | RESOURCE_MEMORY)
+        }
+        Instruction::Jump | Instruction::JumpIf => (RESOURCE_STACK, 0),
+        Instruction::CasWord | Instruction::FetchAdd => (
+            RESOURCE_STACK | RESOURCE_MEMORY,
+            RESOURCE_STACK | RESOURCE_MEMORY,
+        ),
+        _ => (RESOURCE_STACK, RESOURCE_STACK),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_bounds() {
+        assert!(Pipeline::new(MIN_PIPELINE_STAGES).is_ok());
+        assert!(Pipeline::new(MAX_PIPELINE_STAGES).is_ok());
+        assert_eq!(
+            Pipeline::new(MIN_PIPELINE_STAGES - 1).err(),
+            Some(PipelineError::TooFewStages)
+        );
+        assert_eq!(
+            Pipeline::new(MAX_PIPELINE_STAGES + 1).err(),
+            Some(PipelineError::TooManyStages)
+        );
+    }
+
+    #[test]
+    fn test_slots_kept_only_for_timeline() {
+        let state: State = State::default();
+        let mut plain: Pipeline = Pipeline::new(5).expect("valid");
+        let mut timed: Pipeline =
+            Pipeline::new(5).expect("valid").with_timeline();
+
+        for _ in 0..3 {
+            plain.issue(&state, Instruction::Nop, &state);
+            timed.issue(&state, Instruction::Nop, &state);
+        }
+
+        assert!(plain.slots().is_empty());
+        assert!(plain.timeline().is_empty());
+        assert_eq!(timed.slots().len(), 3);
+        assert_eq!(plain.report(), timed.report());
+        assert_eq!(plain.cycles(), 7);
+    }
+}
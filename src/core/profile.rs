@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use crate::common::types::{word_to_usize, Word};
+use crate::core::code::Program;
+
+/*
+ * A statistical profile of program counter values. Rather than counting
+ * every step, only every `interval`th step is sampled, which keeps the
+ * overhead of profiling very long runs low whilst still producing a
+ * representative distribution of where time is spent.
+ */
+#[derive(Clone, Debug)]
+pub struct Profile {
+    interval: usize,
+    steps: usize,
+    samples: HashMap<Word, usize>,
+}
+
+impl Profile {
+    pub fn new(interval: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            steps: 0,
+            samples: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, pc: Word) {
+        if self.steps.is_multiple_of(self.interval) {
+            *self.samples.entry(pc).or_insert(0) += 1;
+        }
+
+        self.steps += 1;
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn total_samples(&self) -> usize {
+        self.samples.values().sum()
+    }
+
+    /* sampled program counters, most frequently sampled first */
+    pub fn flat(&self) -> Vec<(Word, usize)> {
+        let mut entries: Vec<(Word, usize)> =
+            self.samples.iter().map(|(pc, n)| (*pc, *n)).collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        entries
+    }
+
+    pub fn report(&self, code: &dyn Program) -> String {
+        let total: usize = self.total_samples();
+        let mut out: String = format!(
+            "samples: {} (every {} steps, {} steps total)\n",
+            total, self.interval, self.steps
+        );
+
+        out.push_str(&format!(
+            "{:>8}  {:>8}  {:>7}  instruction\n",
+            "pc", "samples", "%"
+        ));
+
+        for (pc, n) in self.flat() {
+            let instruction: String =
+                match word_to_usize(pc).and_then(|t| code.decoded(t)) {
+                    Some(t) => format!("{:?}", t),
+                    None => "?".to_string(),
+                };
+            let percentage: f64 = 100.0 * (n as f64) / (total as f64);
+
+            out.push_str(&format!(
+                "{:>8}  {:>8}  {:>7.2}  {}\n",
+                pc, n, percentage, instruction
+            ));
+        }
+
+        out
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/* steps between checks of the clock, so that timing stays off the hot path */
+const PROGRESS_CHECK_INTERVAL: u64 = 4096;
+const PROGRESS_REFRESH: Duration = Duration::from_millis(200);
+
+/*
+ * A single status line for long runs, redrawn in place on stderr at most
+ * every `PROGRESS_REFRESH`, showing the number of instructions executed,
+ * the recent execution rate and, if the run is bounded, how many steps
+ * remain.
+ */
+#[derive(Clone, Debug)]
+pub struct Progress {
+    steps: u64,
+    limit: Option<u64>,
+    started: Instant,
+    last_drawn: Instant,
+    last_steps: u64,
+    drawn: bool,
+}
+
+impl Progress {
+    pub fn new(limit: Option<u64>) -> Self {
+        let now: Instant = Instant::now();
+
+        Self {
+            steps: 0,
+            limit,
+            started: now,
+            last_drawn: now,
+            last_steps: 0,
+            drawn: false,
+        }
+    }
+
+    pub fn record(&mut self) {
+        self.steps += 1;
+
+        if !self.steps.is_multiple_of(PROGRESS_CHECK_INTERVAL) {
+            return;
+        }
+
+        let now: Instant = Instant::now();
+        let elapsed: Duration = now.duration_since(self.last_drawn);
+
+        if elapsed >= PROGRESS_REFRESH {
+            let rate: f64 =
+                (self.steps - self.last_steps) as f64 / elapsed.as_secs_f64();
+            self.draw(rate);
+            self.last_drawn = now;
+            self.last_steps = self.steps;
+        }
+    }
+
+    /* draws the final totals and moves off the status line */
+    pub fn finish(&mut self) {
+        if !self.drawn {
+            return;
+        }
+
+        let elapsed: f64 = self.started.elapsed().as_secs_f64();
+        self.draw(self.steps as f64 / elapsed.max(f64::EPSILON));
+        eprintln!();
+    }
+
+    fn draw(&mut self, rate: f64) {
+        let remaining: String = match self.limit {
+            Some(t) => {
+                format!(", {} remaining", t.saturating_sub(self.steps))
+            }
+            None => String::new(),
+        };
+
+        eprint!(
+            "\r\x1b[K{} instructions ({:.0}/s){}",
+            self.steps, rate, remaining
+        );
+        let _ = std::io::stderr().flush();
+        self.drawn = true;
+    }
+}
@@ -0,0 +1,123 @@
+use crate::common::types::word_to_usize;
+use crate::core::filter::{
+    parse_number, tokenise, Filter, FilterParseError, Parser, Token,
+};
+use crate::core::trace::{TraceEntry, TraceRecord};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    Steps,
+    Count,
+}
+
+/*
+ * A query over the steps of a recorded trace:
+ *
+ *     select (steps | count) [where <filter>] [limit <n>]
+ *
+ * where the filter uses the same expression language as `--trace-filter`.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub selection: Selection,
+    pub filter: Option<Filter>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryResult {
+    Steps(Vec<TraceRecord>),
+    Count(usize),
+}
+
+impl Query {
+    pub fn execute(&self, entries: &[TraceEntry]) -> QueryResult {
+        let matching = entries
+            .iter()
+            .filter_map(|t| match t {
+                TraceEntry::Step(record) => Some(record),
+                TraceEntry::Summary { .. } => None,
+            })
+            .filter(|t| match &self.filter {
+                Some(filter) => filter.matches(t),
+                None => true,
+            })
+            .take(self.limit.unwrap_or(usize::MAX));
+
+        match self.selection {
+            Selection::Steps => QueryResult::Steps(matching.copied().collect()),
+            Selection::Count => QueryResult::Count(matching.count()),
+        }
+    }
+}
+
+impl TryFrom<&str> for Query {
+    type Error = FilterParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let tokens: Vec<(usize, Token)> = tokenise(value)?;
+        let mut parser: Parser = Parser::new(tokens);
+
+        expect_keyword(&mut parser, "select")?;
+
+        let selection: Selection = match parser.next()? {
+            (_, Token::Ident(t)) if t.eq_ignore_ascii_case("steps") => {
+                Selection::Steps
+            }
+            (_, Token::Ident(t)) if t.eq_ignore_ascii_case("count") => {
+                Selection::Count
+            }
+            (offset, _) => {
+                return Err(FilterParseError::UnexpectedToken(offset))
+            }
+        };
+
+        let filter: Option<Filter> = if is_keyword(&parser, "where") {
+            parser.pos += 1;
+            Some(Filter::try_from(parser.expr(0)?)?)
+        } else {
+            None
+        };
+
+        let limit: Option<usize> = if is_keyword(&parser, "limit") {
+            parser.pos += 1;
+
+            match parser.next()? {
+                (_, Token::Number(t)) => Some(
+                    word_to_usize(parse_number(&t)?)
+                        .ok_or(FilterParseError::InvalidNumber(t))?,
+                ),
+                (offset, _) => {
+                    return Err(FilterParseError::UnexpectedToken(offset))
+                }
+            }
+        } else {
+            None
+        };
+
+        match parser.tokens.get(parser.pos) {
+            Some((offset, _)) => {
+                Err(FilterParseError::UnexpectedToken(*offset))
+            }
+            None => Ok(Self {
+                selection,
+                filter,
+                limit,
+            }),
+        }
+    }
+}
+
+fn is_keyword(parser: &Parser, keyword: &str) -> bool {
+    matches!(parser.peek(), Some(Token::Ident(t)) if t.eq_ignore_ascii_case(keyword))
+}
+
+fn expect_keyword(
+    parser: &mut Parser,
+    keyword: &str,
+) -> Result<(), FilterParseError> {
+    match parser.next()? {
+        (_, Token::Ident(t)) if t.eq_ignore_ascii_case(keyword) => Ok(()),
+        (offset, _) => Err(FilterParseError::UnexpectedToken(offset)),
+    }
+}
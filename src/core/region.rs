@@ -0,0 +1,137 @@
+use std::fmt;
+
+use crate::common::config::{Config, Value};
+use crate::common::types::Word;
+
+/*
+ * Named ranges of memory, read from TOML of the form:
+ *
+ *     [globals]
+ *     start = 0x1000
+ *     size = 0x100
+ *
+ * so that tooling can show `globals+0x8` rather than a raw address.
+ * Regions may not overlap.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub start: Word,
+    pub size: Word,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Regions(Vec<Region>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegionError {
+    MissingKey(String, String),
+    InvalidValue(String, String),
+    UnknownKey(String, String),
+    Overlap(String, String),
+}
+
+impl Region {
+    pub fn contains(&self, address: Word) -> bool {
+        address >= self.start && address - self.start < self.size
+    }
+}
+
+impl TryFrom<&Config> for Regions {
+    type Error = RegionError;
+
+    fn try_from(config: &Config) -> Result<Self, Self::Error> {
+        let mut regions: Vec<Region> = vec![];
+
+        for (name, table) in &config.0 {
+            /* the root table holds no regions */
+            if name.is_empty() {
+                continue;
+            }
+
+            if let Some(key) = table
+                .keys()
+                .find(|t| !matches!(t.as_str(), "start" | "size"))
+            {
+                return Err(RegionError::UnknownKey(name.clone(), key.clone()));
+            }
+
+            let bound = |key: &str| -> Result<Word, RegionError> {
+                let value: &Value = table.get(key).ok_or_else(|| {
+                    RegionError::MissingKey(name.clone(), key.to_string())
+                })?;
+
+                value
+                    .as_integer()
+                    .and_then(|t| Word::try_from(t).ok())
+                    .ok_or_else(|| {
+                        RegionError::InvalidValue(name.clone(), key.to_string())
+                    })
+            };
+
+            let region: Region = Region {
+                name: name.clone(),
+                start: bound("start")?,
+                size: bound("size")?,
+            };
+
+            /* a region may end at the very top of memory, but not wrap */
+            if region.size == 0
+                || region.start.checked_add(region.size - 1).is_none()
+            {
+                return Err(RegionError::InvalidValue(
+                    name.clone(),
+                    "size".to_string(),
+                ));
+            }
+
+            regions.push(region);
+        }
+
+        regions.sort_unstable_by_key(|t| t.start);
+
+        for pair in regions.windows(2) {
+            if pair[1].start - pair[0].start < pair[0].size {
+                return Err(RegionError::Overlap(
+                    pair[0].name.clone(),
+                    pair[1].name.clone(),
+                ));
+            }
+        }
+
+        Ok(Self(regions))
+    }
+}
+
+impl Regions {
+    pub fn find(&self, address: Word) -> Option<&Region> {
+        self.0.iter().find(|t| t.contains(address))
+    }
+
+    pub fn named(&self, name: &str) -> Option<&Region> {
+        self.0.iter().find(|t| t.name == name)
+    }
+
+    pub fn symbolise(&self, address: Word) -> Symbol<'_> {
+        Symbol {
+            region: self.find(address),
+            address,
+        }
+    }
+}
+
+/* an address, shown relative to the region containing it if there is one */
+#[derive(Clone, Copy, Debug)]
+pub struct Symbol<'a> {
+    region: Option<&'a Region>,
+    address: Word,
+}
+
+impl fmt::Display for Symbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.region {
+            Some(t) => write!(f, "{}+{:#x}", t.name, self.address - t.start),
+            None => write!(f, "{:#x}", self.address),
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use serde_json::{json, Value};
+
+use crate::core::diagnostic::REPORT_VERSION;
+use crate::core::dump::DUMP_VERSION;
+use crate::core::explain::{explanations, ErrorKind};
+use crate::core::grade::SCORE_REPORT_VERSION;
+use crate::core::instruction::Instruction;
+use crate::core::manifest::MANIFEST_VERSION;
+use crate::core::snapshot::SNAPSHOT_VERSION;
+use crate::core::stack::MAX_STACK_DEPTH;
+use crate::core::trace::TRACE_VERSION;
+
+/* every schema `schema` knows, by the name it is looked up under */
+pub const SCHEMAS: [&str; 8] = [
+    "state",
+    "snapshot",
+    "trace",
+    "crash-dump",
+    "verify-report",
+    "manifest",
+    "score-report",
+    "lifecycle",
+];
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/*
+ * The JSON Schema (draft 2020-12) for one of the tool's JSON outputs.
+ * Versioned formats pin their version field to the current version, so
+ * a document written by another release fails validation rather than
+ * being misread.
+ */
+pub fn schema(name: &str) -> Option<Value> {
+    let (title, body): (&str, Value) = match name {
+        "state" => ("Machine state", state()),
+        "snapshot" => ("Saved machine state", snapshot()),
+        "trace" => ("A line of a JSONL trace", trace()),
+        "crash-dump" => ("Crash dump", crash_dump()),
+        "verify-report" => ("verify --format json", verify_report()),
+        "manifest" => ("Run manifest", manifest()),
+        "score-report" => ("grade score report", score_report()),
+        "lifecycle" => ("Lifecycle event", lifecycle()),
+        _ => return None,
+    };
+
+    let mut schema: Value = json!({
+        "$schema": DRAFT,
+        "$id": format!("dreamervm:{}", name),
+        "title": title,
+        "$defs": {
+            "word": word(),
+            "hash": { "type": "string", "pattern": "^[0-9a-f]{16}$" },
+            "instruction": instruction(),
+            "error": machine_error(),
+            "state": state(),
+            "record": record(),
+        },
+    });
+
+    if let (Some(schema), Value::Object(body)) = (schema.as_object_mut(), body)
+    {
+        schema.extend(body);
+    }
+
+    Some(schema)
+}
+
+fn word() -> Value {
+    json!({ "type": "integer", "minimum": 0, "maximum": u64::MAX })
+}
+
+fn nullable(t: &str) -> Value {
+    json!({ "anyOf": [{ "$ref": t }, { "type": "null" }] })
+}
+
+fn version(current: u8) -> Value {
+    json!({ "type": "integer", "const": current })
+}
+
+/* Set serialises with its literal, every other instruction as its name */
+fn instruction() -> Value {
+    let names: Vec<String> = (0..=u8::MAX)
+        .filter_map(|t| Instruction::try_from(&[t][..]).ok())
+        .filter(|t| !matches!(t, Instruction::Set(_)))
+        .map(|t| format!("{:?}", t))
+        .collect();
+
+    json!({
+        "oneOf": [
+            { "type": "string", "enum": names },
+            {
+                "type": "object",
+                "properties": { "Set": { "$ref": "#/$defs/word" } },
+                "required": ["Set"],
+                "additionalProperties": false,
+            },
+        ],
+    })
+}
+
+fn machine_error() -> Value {
+    let names: Vec<String> = explanations()
+        .iter()
+        .filter(|t| matches!(t.kind, ErrorKind::Runtime(_)))
+        .map(|t| t.kind.name())
+        .collect();
+
+    json!({ "type": "string", "enum": names })
+}
+
+fn state() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "pc": { "$ref": "#/$defs/word" },
+            "reg": { "$ref": "#/$defs/word" },
+            "stack": {
+                "type": "array",
+                "items": { "$ref": "#/$defs/word" },
+                "maxItems": MAX_STACK_DEPTH,
+            },
+            /* keyed by address, in decimal */
+            "memory": {
+                "type": "object",
+                "propertyNames": { "pattern": "^[0-9]+$" },
+                "additionalProperties": { "$ref": "#/$defs/word" },
+            },
+            "wrap": { "type": "boolean" },
+        },
+        "required": ["pc", "reg", "stack", "memory"],
+        "additionalProperties": false,
+    })
+}
+
+fn snapshot() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "snapshot_version": version(SNAPSHOT_VERSION),
+            "state": { "$ref": "#/$defs/state" },
+        },
+        "required": ["snapshot_version", "state"],
+        "additionalProperties": false,
+    })
+}
+
+fn record() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "step": { "$ref": "#/$defs/word" },
+            "pc": { "$ref": "#/$defs/word" },
+            "instruction": { "$ref": "#/$defs/instruction" },
+            "address": nullable("#/$defs/word"),
+            "reg": { "$ref": "#/$defs/word" },
+            "depth": { "$ref": "#/$defs/word" },
+        },
+        "required": ["step", "pc", "instruction", "address", "reg", "depth"],
+        "additionalProperties": false,
+    })
+}
+
+fn trace() -> Value {
+    json!({
+        "oneOf": [
+            {
+                "type": "object",
+                "properties": { "trace_version": version(TRACE_VERSION) },
+                "required": ["trace_version"],
+                "additionalProperties": false,
+            },
+            { "$ref": "#/$defs/record" },
+            {
+                "type": "object",
+                "properties": {
+                    "summary": {
+                        "type": "object",
+                        "properties": {
+                            "steps": { "$ref": "#/$defs/word" },
+                            "histogram": {
+                                "type": "object",
+                                "additionalProperties": {
+                                    "$ref": "#/$defs/word",
+                                },
+                            },
+                        },
+                        "required": ["steps", "histogram"],
+                    },
+                },
+                "required": ["summary"],
+                "additionalProperties": false,
+            },
+        ],
+    })
+}
+
+fn crash_dump() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "version": version(DUMP_VERSION),
+            "program": { "type": "string" },
+            "program_hash": { "$ref": "#/$defs/hash" },
+            "time": { "$ref": "#/$defs/word" },
+            "fault": { "$ref": "#/$defs/error" },
+            "steps": { "$ref": "#/$defs/word" },
+            "state": { "$ref": "#/$defs/state" },
+            "history": {
+                "type": "array",
+                "items": { "$ref": "#/$defs/record" },
+            },
+        },
+        "required": [
+            "version", "program", "program_hash", "time", "fault", "steps",
+            "state", "history",
+        ],
+    })
+}
+
+fn verify_report() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "version": version(REPORT_VERSION),
+            "verified": { "type": "boolean" },
+            "passed": { "type": "boolean" },
+            "diagnostics": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "code": {
+                            "type": "string",
+                            "pattern": "^[VW][0-9]{3}$",
+                        },
+                        "severity": { "enum": ["warning", "error"] },
+                        "certain": { "type": "boolean" },
+                        "pc": { "$ref": "#/$defs/word" },
+                        "offset": { "$ref": "#/$defs/word" },
+                        "kind": { "type": "string" },
+                        "message": { "type": "string" },
+                    },
+                    "required": [
+                        "code", "severity", "certain", "pc", "offset",
+                        "kind", "message",
+                    ],
+                },
+            },
+        },
+        "required": ["version", "verified", "passed", "diagnostics"],
+    })
+}
+
+fn manifest() -> Value {
+    let limit: Value = json!({ "anyOf": [word(), { "type": "null" }] });
+
+    json!({
+        "type": "object",
+        "properties": {
+            "version": version(MANIFEST_VERSION),
+            "tool_version": { "type": "string" },
+            "isa_version": { "type": "integer", "minimum": 1 },
+            "program": { "type": "string" },
+            "program_hash": { "$ref": "#/$defs/hash" },
+            "engine": { "enum": ["eager", "lazy"] },
+            "limits": {
+                "type": "object",
+                "properties": {
+                    "steps": limit,
+                    "memory": limit,
+                    "stack": limit,
+                },
+            },
+            "denied": { "type": ["string", "null"] },
+            "initial_state_hash": { "$ref": "#/$defs/hash" },
+            "input_hash": nullable("#/$defs/hash"),
+            "fault": nullable("#/$defs/error"),
+            "final_state_hash": { "$ref": "#/$defs/hash" },
+            "steps": { "$ref": "#/$defs/word" },
+            "cycles": nullable("#/$defs/word"),
+            "energy": nullable("#/$defs/word"),
+        },
+        "required": [
+            "version", "tool_version", "isa_version", "program",
+            "program_hash", "engine", "limits", "initial_state_hash",
+            "final_state_hash", "steps",
+        ],
+    })
+}
+
+fn score_report() -> Value {
+    let words: Value = json!({
+        "type": "array",
+        "items": { "$ref": "#/$defs/word" },
+    });
+
+    json!({
+        "type": "object",
+        "properties": {
+            "version": version(SCORE_REPORT_VERSION),
+            "score": { "$ref": "#/$defs/word" },
+            "total": { "$ref": "#/$defs/word" },
+            "cases": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "passed": { "type": "boolean" },
+                        "points": { "$ref": "#/$defs/word" },
+                        "fault": nullable("#/$defs/error"),
+                        "mismatches": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "output": { "type": "string" },
+                                    "expected": words,
+                                    "actual": words,
+                                },
+                                "required": ["output", "expected", "actual"],
+                            },
+                        },
+                    },
+                    "required": [
+                        "name", "passed", "points", "fault", "mismatches",
+                    ],
+                },
+            },
+        },
+        "required": ["version", "score", "total", "cases"],
+    })
+}
+
+fn lifecycle() -> Value {
+    let event = |name: &str, fields: Value, required: &[&str]| -> Value {
+        let mut properties: Value = json!({ "event": { "const": name } });
+
+        if let (Some(t), Value::Object(fields)) =
+            (properties.as_object_mut(), fields)
+        {
+            t.extend(fields);
+        }
+
+        let mut required: Vec<&str> = required.to_vec();
+        required.insert(0, "event");
+
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    };
+
+    json!({
+        "oneOf": [
+            event("Loaded", json!({}), &[]),
+            event("Started", json!({}), &[]),
+            event(
+                "Checkpointed",
+                json!({ "depth": { "$ref": "#/$defs/word" } }),
+                &["depth"],
+            ),
+            event(
+                "Faulted",
+                json!({
+                    "error": { "$ref": "#/$defs/error" },
+                    "pc": { "$ref": "#/$defs/word" },
+                    "steps": { "$ref": "#/$defs/word" },
+                }),
+                &["error", "pc", "steps"],
+            ),
+            event(
+                "Halted",
+                json!({
+                    "pc": { "$ref": "#/$defs/word" },
+                    "steps": { "$ref": "#/$defs/word" },
+                }),
+                &["pc", "steps"],
+            ),
+        ],
+    })
+}
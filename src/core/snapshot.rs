@@ -0,0 +1,165 @@
+use std::io;
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::core::state::State;
+
+/*
+ * Saved machine states: the state file a run resumes from and the state
+ * an interrupted run stopped in. Version 1, from before snapshots were
+ * versioned, is the bare state; version 2 wraps it as
+ * `{"snapshot_version": 2, "state": {..}}`. Older snapshots are migrated
+ * one version at a time as they are read.
+ */
+pub const SNAPSHOT_VERSION: u8 = 2;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    IOError(io::Error),
+    Malformed,
+    /* this release reads versions 1 to `supported` */
+    UnsupportedVersion { found: u8, supported: u8 },
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(value: io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
+
+/* borrows the state to write it and owns the state it reads */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Snapshot<S> {
+    snapshot_version: u8,
+    state: S,
+}
+
+pub fn read_snapshot<R: Read>(source: R) -> Result<State, SnapshotError> {
+    let mut value: Value = serde_json::from_reader(source)
+        .map_err(|_| SnapshotError::Malformed)?;
+    let version: u8 = match value.get("snapshot_version") {
+        Some(t) => t
+            .as_u64()
+            .and_then(|t| u8::try_from(t).ok())
+            .ok_or(SnapshotError::Malformed)?,
+        None => 1,
+    };
+
+    if version == 0 || version > SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion {
+            found: version,
+            supported: SNAPSHOT_VERSION,
+        });
+    }
+
+    for from in version..SNAPSHOT_VERSION {
+        value = migrate(from, value);
+    }
+
+    let snapshot: Snapshot<State> =
+        serde_json::from_value(value).map_err(|_| SnapshotError::Malformed)?;
+    Ok(snapshot.state)
+}
+
+pub fn write_snapshot<W: Write>(
+    mut sink: W,
+    state: &State,
+) -> Result<(), SnapshotError> {
+    let snapshot: Snapshot<&State> = Snapshot {
+        snapshot_version: SNAPSHOT_VERSION,
+        state,
+    };
+
+    serde_json::to_writer(&mut sink, &snapshot)
+        .map_err(|e| SnapshotError::IOError(e.into()))?;
+    writeln!(sink)?;
+    sink.flush()?;
+    Ok(())
+}
+
+/* upgrades a snapshot of version `from` to version `from + 1` */
+fn migrate(from: u8, value: Value) -> Value {
+    match from {
+        /* the bare state gains its envelope */
+        1 => json!({ "snapshot_version": 2, "state": value }),
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::memory::LinearlyAddressable;
+
+    const V1_STATE: &str =
+        r#"{"pc":3,"reg":7,"stack":[1,2],"memory":{"16":5}}"#;
+
+    #[test]
+    fn test_v1_state_loads_and_resaves_as_current_version() {
+        let state: State =
+            read_snapshot(V1_STATE.as_bytes()).expect("v1 state loads");
+
+        assert_eq!(state.pc, 3);
+        assert_eq!(state.reg, 7);
+        assert_eq!(state.stack.elems(), &[1, 2]);
+        assert_eq!(state.memory.read(16), 5);
+
+        let mut saved: Vec<u8> = vec![];
+        write_snapshot(&mut saved, &state).expect("state saves");
+
+        let value: Value =
+            serde_json::from_slice(&saved).expect("saved as JSON");
+        assert_eq!(value["snapshot_version"], json!(SNAPSHOT_VERSION));
+        assert_eq!(value["state"]["reg"], json!(7));
+
+        let reloaded: State =
+            read_snapshot(saved.as_slice()).expect("saved state loads");
+        assert_eq!(reloaded.digest(), state.digest());
+    }
+
+    #[test]
+    fn test_newer_version_is_unsupported() {
+        let snapshot: String =
+            format!(r#"{{"snapshot_version":3,"state":{}}}"#, V1_STATE);
+
+        assert!(matches!(
+            read_snapshot(snapshot.as_bytes()),
+            Err(SnapshotError::UnsupportedVersion {
+                found: 3,
+                supported: SNAPSHOT_VERSION
+            })
+        ));
+    }
+
+    #[test]
+    fn test_version_zero_is_unsupported() {
+        let snapshot: String =
+            format!(r#"{{"snapshot_version":0,"state":{}}}"#, V1_STATE);
+
+        assert!(matches!(
+            read_snapshot(snapshot.as_bytes()),
+            Err(SnapshotError::UnsupportedVersion { found: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_input_is_rejected() {
+        for input in [
+            "not json",
+            r#"{"snapshot_version":"2","state":{}}"#,
+            r#"{"snapshot_version":2,"state":5}"#,
+            r#"{"snapshot_version":2}"#,
+        ] {
+            assert!(
+                matches!(
+                    read_snapshot(input.as_bytes()),
+                    Err(SnapshotError::Malformed)
+                ),
+                "{}",
+                input
+            );
+        }
+    }
+}
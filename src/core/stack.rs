@@ -19,6 +19,19 @@ impl Default for Stack {
     }
 }
 
+/* builds a stack from its elements, bottom first */
+impl TryFrom<Vec<Word>> for Stack {
+    type Error = StackError;
+
+    fn try_from(elems: Vec<Word>) -> Result<Self, Self::Error> {
+        if elems.len() > MAX_STACK_DEPTH {
+            Err(StackError::Full)
+        } else {
+            Ok(Self(elems))
+        }
+    }
+}
+
 impl Stack {
     pub fn new() -> Self {
         Self(vec![])
@@ -38,7 +51,12 @@ impl Stack {
     }
 
     pub fn peek(&self) -> Option<Word> {
-        self.0.get(0).copied()
+        self.0.last().copied()
+    }
+
+    /* the elements of the stack, bottom first */
+    pub fn elems(&self) -> &[Word] {
+        &self.0
     }
 
     pub fn depth(&self) -> usize {
@@ -53,3 +71,23 @@ impl Stack {
         self.0.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peek_is_top() {
+        let mut stack: Stack = Stack::new();
+        assert_eq!(stack.peek(), None);
+
+        for elem in [1, 2, 3] {
+            stack.push(elem).expect("stack has room");
+            assert_eq!(stack.peek(), Some(elem));
+        }
+
+        stack.pop().expect("stack is not empty");
+        assert_eq!(stack.peek(), Some(2));
+        assert_eq!(stack.depth(), 2);
+    }
+}
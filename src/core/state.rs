@@ -3,33 +3,49 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 use serde_json;
 
-use crate::common::types::Word;
+use crate::common::hash::Fnv1a;
+use crate::common::types::{word_bytes, Word};
+use crate::core::expr::{EvalError, Expr};
 use crate::core::memory::Memory;
 use crate::core::stack::Stack;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct State {
     pub pc: Word,
     pub reg: Word,
     pub stack: Stack,
     pub memory: Memory,
+    /* set by a Wrap prefix for the instruction that follows it */
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub wrap: bool,
 }
 
-impl fmt::Display for State {
+/*
+ * As derived, except that the Wrap prefix flag is only shown when it is
+ * set, so that traces read as they did before the flag existed
+ */
+impl fmt::Debug for State {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let serialised: String = serde_json::to_string(&self).unwrap();
-        write!(f, "{}", serialised)
+        let mut debug: fmt::DebugStruct = f.debug_struct("State");
+        debug
+            .field("pc", &self.pc)
+            .field("reg", &self.reg)
+            .field("stack", &self.stack)
+            .field("memory", &self.memory);
+
+        if self.wrap {
+            debug.field("wrap", &self.wrap);
+        }
+
+        debug.finish()
     }
 }
 
-impl Default for State {
-    fn default() -> Self {
-        Self {
-            pc: Word::default(),
-            reg: Word::default(),
-            stack: Stack::default(),
-            memory: Memory::new(),
-        }
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let serialised: String =
+            serde_json::to_string(&self).map_err(|_| fmt::Error)?;
+        write!(f, "{}", serialised)
     }
 }
 
@@ -41,4 +57,48 @@ impl State {
     pub fn program_counter(&self) -> Word {
         self.pc
     }
+
+    /*
+     * Evaluates an expression such as `stack[0] * 2 + mem[8]` against this
+     * state. Callers evaluating the same expression repeatedly should parse
+     * it once into an `Expr` instead.
+     */
+    pub fn eval(&self, expr: &str) -> Result<Word, EvalError> {
+        Expr::try_from(expr)?.eval(self)
+    }
+
+    /*
+     * The bytes of storage the stack and memory occupy. Each memory cell is
+     * counted as its address and its contents.
+     */
+    pub fn footprint(&self) -> usize {
+        (self.stack.depth() + 2 * self.memory.allocated()) * word_bytes()
+    }
+
+    /*
+     * A stable digest of the state. Memory cells holding zero are skipped,
+     * since they are indistinguishable from cells that were never written.
+     */
+    pub fn digest(&self) -> u64 {
+        let mut hasher: Fnv1a = Fnv1a::new();
+
+        hasher.write_word(self.pc);
+        hasher.write_word(self.reg);
+        hasher.write_word(self.stack.depth() as Word);
+
+        for elem in self.stack.elems() {
+            hasher.write_word(*elem);
+        }
+
+        for (address, data) in self.memory.cells() {
+            hasher.write_word(address);
+            hasher.write_word(data);
+        }
+
+        if self.wrap {
+            hasher.write_word(1);
+        }
+
+        hasher.finish()
+    }
 }
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use crate::core::cost::CostModel;
+use crate::core::instruction::Instruction;
+
+/*
+ * Static statistics for a decoded program. A program without jumps runs
+ * straight through from its first instruction, so its longest possible run
+ * ends at the first HALT (or the end of the program) and can be bounded
+ * without executing it.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgramStats {
+    pub length: usize,
+    pub histogram: BTreeMap<String, u64>,
+    pub max_steps: Option<u64>,
+    pub max_cycles: Option<u64>,
+}
+
+impl ProgramStats {
+    pub fn new(code: &[Instruction], model: Option<&CostModel>) -> Self {
+        let mut histogram: BTreeMap<String, u64> = BTreeMap::new();
+
+        for instruction in code {
+            *histogram
+                .entry(instruction.mnemonic().to_string())
+                .or_insert(0) += 1;
+        }
+
+        let loop_free: bool = !code
+            .iter()
+            .any(|t| matches!(t, Instruction::Jump | Instruction::JumpIf));
+        let path: Option<&[Instruction]> = loop_free.then(|| {
+            match code.iter().position(|t| *t == Instruction::Halt) {
+                Some(t) => &code[..=t],
+                None => code,
+            }
+        });
+
+        Self {
+            length: code.len(),
+            histogram,
+            max_steps: path.map(|t| t.len() as u64),
+            max_cycles: path.and_then(|t| {
+                model.map(|m| t.iter().map(|i| m.static_cost(*i)).sum())
+            }),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out: String = format!("length: {}\n", self.length);
+
+        match self.max_steps {
+            Some(t) => out.push_str(&format!("max steps: {}\n", t)),
+            None => out.push_str("max steps: unbounded (contains jumps)\n"),
+        }
+
+        if let Some(t) = self.max_cycles {
+            out.push_str(&format!("max cycles: {}\n", t));
+        }
+
+        out.push_str(&format!("{:<10}  {:>12}\n", "opcode", "count"));
+
+        for (mnemonic, count) in &self.histogram {
+            out.push_str(&format!("{:<10}  {:>12}\n", mnemonic, count));
+        }
+
+        out
+    }
+}
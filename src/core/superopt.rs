@@ -0,0 +1,171 @@
+use crate::common::types::Word;
+use crate::core::code::{Code, VecCode};
+use crate::core::cost::CostModel;
+use crate::core::equiv::{outcome, Input, Outcome, DEFAULT_EQUIV_STEP_LIMIT};
+use crate::core::instruction::Instruction;
+
+/* instructions a candidate may be built from, other than literals */
+const SEARCH_ALPHABET: [Instruction; 30] = [
+    Instruction::Load,
+    Instruction::Store,
+    Instruction::Push,
+    Instruction::Pop,
+    Instruction::Add,
+    Instruction::Sub,
+    Instruction::Mul,
+    Instruction::Div,
+    Instruction::Mod,
+    Instruction::Cmp,
+    Instruction::And,
+    Instruction::Or,
+    Instruction::Not,
+    Instruction::Xor,
+    Instruction::CasWord,
+    Instruction::FetchAdd,
+    Instruction::Wrap,
+    Instruction::IAdd,
+    Instruction::ISub,
+    Instruction::IMul,
+    Instruction::IDiv,
+    Instruction::IMod,
+    Instruction::SLt,
+    Instruction::MulHi,
+    Instruction::DivRem,
+    Instruction::Rotl,
+    Instruction::Rotr,
+    Instruction::Popcnt,
+    Instruction::Clz,
+    Instruction::Ctz,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SuperoptError {
+    EmptySnippet,
+    UnsupportedInstruction(Instruction),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub code: Vec<Instruction>,
+    pub cost: u64,
+}
+
+/*
+ * Searches for the cheapest straight-line sequence of at most `max_len`
+ * instructions that agrees with `snippet` on every input. Agreement is only
+ * tested, not proven, so results should be checked before being relied
+ * upon. Sequences are enumerated exhaustively over a fixed alphabet plus
+ * SETs of the literals appearing in the snippet (and of 0 and 1), so the
+ * search grows exponentially in `max_len`.
+ */
+pub fn search(
+    snippet: &[Instruction],
+    inputs: &[Input],
+    model: &CostModel,
+    max_len: usize,
+) -> Result<Option<Candidate>, SuperoptError> {
+    if snippet.is_empty() {
+        return Err(SuperoptError::EmptySnippet);
+    }
+
+    /* control flow and I/O have no meaning in a detached snippet */
+    if let Some(t) = snippet.iter().find(|t| {
+        matches!(
+            t,
+            Instruction::Halt
+                | Instruction::Jump
+                | Instruction::JumpIf
+                | Instruction::Read
+                | Instruction::Write
+        )
+    }) {
+        return Err(SuperoptError::UnsupportedInstruction(*t));
+    }
+
+    let alphabet: Vec<Instruction> = alphabet(snippet);
+    let expected: Vec<Outcome> = inputs
+        .iter()
+        .map(|t| {
+            outcome(&VecCode(snippet.to_vec()), t, DEFAULT_EQUIV_STEP_LIMIT)
+        })
+        .collect();
+
+    let mut best: Option<Candidate> = None;
+    let mut bound: u64 = cost(snippet, model);
+
+    for len in 0..=max_len {
+        let mut indices: Vec<usize> = vec![0; len];
+
+        loop {
+            let candidate: Vec<Instruction> =
+                indices.iter().map(|i| alphabet[*i]).collect();
+            let candidate_cost: u64 = cost(&candidate, model);
+
+            /* only bother testing candidates that would be an improvement */
+            if candidate_cost < bound && agrees(&candidate, inputs, &expected) {
+                bound = candidate_cost;
+                best = Some(Candidate {
+                    code: candidate,
+                    cost: candidate_cost,
+                });
+            }
+
+            if !advance(&mut indices, alphabet.len()) {
+                break;
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+pub fn cost(code: &[Instruction], model: &CostModel) -> u64 {
+    code.iter().map(|t| model.static_cost(*t)).sum()
+}
+
+fn alphabet(snippet: &[Instruction]) -> Vec<Instruction> {
+    let mut literals: Vec<Word> = vec![0, 1];
+
+    for instruction in snippet {
+        if let Instruction::Set(x) = instruction {
+            if !literals.contains(x) {
+                literals.push(*x);
+            }
+        }
+    }
+
+    SEARCH_ALPHABET
+        .iter()
+        .copied()
+        .chain(literals.into_iter().map(Instruction::Set))
+        .collect()
+}
+
+/* checks every input, giving up at the first disagreement */
+fn agrees(
+    candidate: &[Instruction],
+    inputs: &[Input],
+    expected: &[Outcome],
+) -> bool {
+    let code: Code = VecCode(candidate.to_vec());
+
+    inputs
+        .iter()
+        .zip(expected)
+        .all(|(input, t)| outcome(&code, input, DEFAULT_EQUIV_STEP_LIMIT) == *t)
+}
+
+/* steps an odometer of indices, returning false once it wraps around */
+fn advance(indices: &mut [usize], base: usize) -> bool {
+    for i in indices.iter_mut().rev() {
+        *i += 1;
+
+        if *i < base {
+            return true;
+        }
+
+        *i = 0;
+    }
+
+    false
+}
@@ -0,0 +1,156 @@
+use crate::common::types::{word_to_usize, Word};
+use crate::core::analysis::{arity, stack_effect};
+use crate::core::code::Program;
+use crate::core::explain::explain;
+use crate::core::instruction::Instruction;
+use crate::core::machine::MachineError;
+use crate::core::state::State;
+
+/* the step limit a teaching run gets unless it is given one */
+pub const TEACH_STEP_LIMIT: u64 = 10_000;
+
+/*
+ * Which instruction pushed each word on the stack, bottom first, so that a
+ * fault can be traced back to where its operands came from. Words that
+ * were on the stack before the run have no producer.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Provenance(Vec<Option<Word>>);
+
+impl Provenance {
+    pub fn new(initial: &State) -> Self {
+        Self(vec![None; initial.stack.depth()])
+    }
+
+    /*
+     * Follows one step. The words pushed are worked out from the change in
+     * depth, since a Read may push a status word as well.
+     */
+    pub fn record(
+        &mut self,
+        old: &State,
+        instruction: Instruction,
+        new: &State,
+    ) {
+        let popped: usize = stack_effect(instruction).0;
+        let kept: usize = old.stack.depth().saturating_sub(popped);
+
+        self.0.truncate(kept);
+        self.0.resize(new.stack.depth(), Some(old.pc));
+    }
+
+    /* the producer of the word `n` places below the top of the stack */
+    pub fn producer(&self, n: usize) -> Option<Word> {
+        self.0.len().checked_sub(n + 1).and_then(|t| self.0[t])
+    }
+}
+
+/*
+ * Guidance for a faulted run, aimed at students: what the faulting
+ * instruction needed, where the operands involved came from, and the
+ * explanation of the error with the rule it breaks.
+ */
+pub fn hint(
+    error: MachineError,
+    state: &State,
+    provenance: &Provenance,
+    code: &dyn Program,
+) -> String {
+    let describe = |pc: Option<Word>| -> String {
+        match pc {
+            Some(pc) => match word_to_usize(pc).and_then(|t| code.decoded(t)) {
+                Some(t) => format!("pushed by {} at pc {}", t.mnemonic(), pc),
+                None => format!("pushed at pc {}", pc),
+            },
+            None => "on the stack before the run began".to_string(),
+        }
+    };
+    let instruction: Option<Instruction> =
+        word_to_usize(state.pc).and_then(|t| code.decoded(t));
+    let mut out: String = String::new();
+
+    match (error, instruction) {
+        (
+            MachineError::InsufficientArguments | MachineError::StackEmpty,
+            Some(t),
+        ) => {
+            out.push_str(&format!(
+                "hint: {} at pc {} needs {} operand(s), but the stack holds \
+                 {}.\n",
+                t.mnemonic(),
+                state.pc,
+                arity(t),
+                state.stack.depth()
+            ));
+
+            for n in 0..state.stack.depth() {
+                out.push_str(&format!(
+                    "  operand {} was {}\n",
+                    n + 1,
+                    describe(provenance.producer(n))
+                ));
+            }
+
+            out.push_str(
+                "  Each operand needs its own Set and Push before the \
+                 instruction that consumes it.\n",
+            );
+        }
+        (MachineError::ArithmeticOverflow, Some(t)) => {
+            /* the divisor is second from the top, or third for DivRem */
+            let divisor: Option<usize> = match t {
+                Instruction::Div
+                | Instruction::Mod
+                | Instruction::IDiv
+                | Instruction::IMod => Some(1),
+                Instruction::DivRem => Some(2),
+                _ => None,
+            };
+            let elems: &[Word] = state.stack.elems();
+            let operand = |n: usize| -> Option<Word> {
+                elems.len().checked_sub(n + 1).map(|t| elems[t])
+            };
+
+            match divisor {
+                Some(n) if operand(n) == Some(0) => {
+                    out.push_str(&format!(
+                        "hint: {} at pc {} divides by zero; the divisor was \
+                         {}.\n",
+                        t.mnemonic(),
+                        state.pc,
+                        describe(provenance.producer(n))
+                    ));
+                }
+                _ => {
+                    out.push_str(&format!(
+                        "hint: {} at pc {} overflowed. Its operands were:\n",
+                        t.mnemonic(),
+                        state.pc
+                    ));
+
+                    for n in 0..arity(t).min(elems.len()) {
+                        out.push_str(&format!(
+                            "  operand {} = {}, {}\n",
+                            n + 1,
+                            operand(n).unwrap_or_default(),
+                            describe(provenance.producer(n))
+                        ));
+                    }
+
+                    out.push_str(
+                        "  Operands are taken top of stack first; a Wrap \
+                         prefix allows the result to wrap around.\n",
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(t) = explain(&format!("{:?}", error)) {
+        out.push('\n');
+        out.push_str(&t.render());
+    }
+
+    out
+}
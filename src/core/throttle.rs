@@ -0,0 +1,131 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/* the most a stall waits for at once, so refills arrive in useful amounts */
+const THROTTLE_REFILL: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitError {
+    Invalid(String),
+}
+
+/*
+ * Fuel that replenishes over time, parsed from `rate=N burst=M` (or
+ * comma-separated): a run may execute `rate` steps a second on average,
+ * and at most `burst` steps without pausing. The burst defaults to one
+ * second's worth.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    pub rate: u64,
+    pub burst: u64,
+}
+
+impl TryFrom<&str> for RateLimit {
+    type Error = RateLimitError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || RateLimitError::Invalid(value.to_string());
+        let mut rate: Option<u64> = None;
+        let mut burst: Option<u64> = None;
+
+        for field in value.split([' ', ',']).filter(|t| !t.is_empty()) {
+            match field.split_once('=') {
+                Some(("rate", t)) => {
+                    rate = Some(t.parse().map_err(|_| invalid())?)
+                }
+                Some(("burst", t)) => {
+                    burst = Some(t.parse().map_err(|_| invalid())?)
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        match (rate, burst.or(rate)) {
+            (Some(rate), Some(burst)) if rate > 0 && burst > 0 => {
+                Ok(Self { rate, burst })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/*
+ * A token bucket of steps. Each step spends a token; only once the bucket
+ * is empty is the clock read to refill it, sleeping if too little time has
+ * passed, so an unthrottled run pays for one comparison a step.
+ */
+#[derive(Clone, Debug)]
+pub struct Throttle {
+    limit: RateLimit,
+    tokens: u64,
+    last_refill: Instant,
+    stalls: u64,
+    throttled: Duration,
+}
+
+impl Throttle {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst,
+            last_refill: Instant::now(),
+            stalls: 0,
+            throttled: Duration::ZERO,
+        }
+    }
+
+    /* spends a token for one step, waiting for the bucket to refill */
+    pub fn record(&mut self) {
+        if self.tokens > 0 {
+            self.tokens -= 1;
+            return;
+        }
+
+        self.refill();
+
+        if self.tokens == 0 {
+            let wanted: u64 = self.limit.burst.min(
+                (self.limit.rate as u128 * THROTTLE_REFILL.as_nanos()
+                    / NANOS_PER_SEC) as u64,
+            );
+            let wait: Duration = Duration::from_nanos(
+                (wanted.max(1) as u128 * NANOS_PER_SEC
+                    / self.limit.rate as u128) as u64,
+            );
+            let started: Instant = Instant::now();
+
+            thread::sleep(wait);
+            self.stalls += 1;
+            self.throttled += started.elapsed();
+            self.refill();
+        }
+
+        self.tokens = self.tokens.saturating_sub(1);
+    }
+
+    fn refill(&mut self) {
+        let now: Instant = Instant::now();
+        let earned: u128 = now.duration_since(self.last_refill).as_nanos()
+            * self.limit.rate as u128
+            / NANOS_PER_SEC;
+
+        /* too soon to earn a whole token leaves the clock where it was */
+        if earned > 0 {
+            self.tokens = earned.min(self.limit.burst as u128) as u64;
+            self.last_refill = now;
+        }
+    }
+
+    pub fn report(&self) -> String {
+        format!(
+            "throttled: {} stalls, {:.3}s (rate {}/s, burst {})",
+            self.stalls,
+            self.throttled.as_secs_f64(),
+            self.limit.rate,
+            self.limit.burst
+        )
+    }
+}
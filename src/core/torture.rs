@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+use crate::common::rng::XorShift;
+use crate::common::types::{word_to_usize, Word};
+use crate::core::asm::disassemble;
+use crate::core::code::{Code, LazyCode, VecCode};
+use crate::core::instruction::Instruction;
+use crate::core::invariant;
+use crate::core::machine::{Machine, MachineError};
+use crate::core::memory::{LinearlyAddressable, Memory};
+use crate::core::stack::Stack;
+use crate::core::state::State;
+
+pub const DEFAULT_TORTURE_STEP_LIMIT: u64 = 10_000;
+
+const MAX_PROGRAM_LEN: u64 = 32;
+const MAX_INPUT_DEPTH: u64 = 4;
+const MAX_INPUT_CELLS: u64 = 4;
+
+/* the highest opcode assigned to an instruction */
+const MAX_OPCODE: u64 = 0x27;
+
+/* the ways of executing a program, which should all agree */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    /* `Machine::run` over fully decoded code, stepping in place */
+    Run,
+    /* `Machine::run_callback`, checking invariants after every step */
+    Callback,
+    /* a loop over the pure `Machine::step` */
+    Step,
+    /* `Machine::run` over code decoded as it is reached */
+    Lazy,
+}
+
+pub const ENGINES: [Engine; 4] =
+    [Engine::Run, Engine::Callback, Engine::Step, Engine::Lazy];
+
+/* how a run ended: its fault, if any, and a digest of its final state */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub fault: Option<MachineError>,
+    pub digest: u64,
+}
+
+/* a random program and the initial state it is run from */
+#[derive(Clone, Debug)]
+pub struct Case {
+    pub seed: u64,
+    pub code: Code,
+    pub reg: Word,
+    pub stack: Vec<Word>,
+    pub memory: Vec<(Word, Word)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Failure {
+    Panic(Case, Engine),
+    Disagreement(Case, Vec<(Engine, Outcome)>),
+}
+
+impl Case {
+    /*
+     * Generates the case for `seed`. Set and Push are favoured, and Set
+     * literals are often small, so that programs get past their first few
+     * instructions and jumps land within them.
+     */
+    pub fn generate(seed: u64) -> Self {
+        let mut rng: XorShift = XorShift::new(seed);
+        let len: u64 = 1 + rng.below(MAX_PROGRAM_LEN);
+        let code: Vec<Instruction> = (0..len)
+            .map(|_| match rng.below(8) {
+                0 | 1 => Instruction::Set(rng.operand()),
+                2 => Instruction::Push,
+                _ => Instruction::try_from(
+                    &[rng.below(MAX_OPCODE + 1) as u8][..],
+                )
+                .unwrap_or(Instruction::Set(rng.operand())),
+            })
+            .collect();
+        let depth: u64 = rng.below(MAX_INPUT_DEPTH + 1);
+        let cells: u64 = rng.below(MAX_INPUT_CELLS + 1);
+
+        Self {
+            seed,
+            code: VecCode(code),
+            reg: rng.operand(),
+            stack: (0..depth).map(|_| rng.operand()).collect(),
+            memory: (0..cells)
+                .map(|_| (rng.operand(), rng.operand()))
+                .collect(),
+        }
+    }
+
+    pub fn initial(&self) -> State {
+        let mut memory: Memory = Memory::default();
+
+        for (address, data) in &self.memory {
+            memory.write(*address, *data);
+        }
+
+        State {
+            reg: self.reg,
+            stack: Stack::try_from(self.stack.clone()).unwrap_or_default(),
+            memory,
+            ..State::default()
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out: String = format!(
+            "seed {}: reg={} stack={:?} memory={:?}\n",
+            self.seed, self.reg, self.stack, self.memory
+        );
+        out.push_str(&disassemble(&self.code));
+        out
+    }
+}
+
+fn outcome(result: Result<State, MachineError>, state: &State) -> Outcome {
+    match result {
+        Ok(t) => Outcome {
+            fault: None,
+            digest: t.digest(),
+        },
+        Err(e) => Outcome {
+            fault: Some(e),
+            digest: state.digest(),
+        },
+    }
+}
+
+/*
+ * The reference interpreter: fetch, count and step, with nothing else in
+ * the way. A failed step leaves the state as it was, as the machine does.
+ */
+fn step_engine(case: &Case, step_limit: u64) -> Outcome {
+    let mut state: State = case.initial();
+    let mut steps: u64 = 0;
+
+    let result: Result<(), MachineError> = loop {
+        let instruction: Instruction = match word_to_usize(state.pc) {
+            Some(t) => match case.code.0.get(t) {
+                Some(t) => *t,
+                None => break Ok(()),
+            },
+            None => break Err(MachineError::OutOfBounds),
+        };
+
+        steps += 1;
+
+        if steps > step_limit {
+            break Err(MachineError::StepLimitExceeded);
+        }
+
+        match Machine::<Code>::step(state.clone(), instruction) {
+            Ok(t) => state = t,
+            Err(e) => break Err(e),
+        }
+
+        if instruction == Instruction::Halt {
+            break Ok(());
+        }
+    };
+
+    outcome(result.map(|_| state.clone()), &state)
+}
+
+fn run_engine(engine: Engine, case: &Case, step_limit: u64) -> Outcome {
+    match engine {
+        Engine::Run => {
+            let mut machine: Machine = Machine::new(case.code.clone())
+                .with_state(case.initial())
+                .with_step_limit(step_limit);
+            let result: Result<State, MachineError> = machine.run();
+            outcome(result, &machine.state)
+        }
+        Engine::Callback => {
+            let mut machine: Machine = Machine::new(case.code.clone())
+                .with_state(case.initial())
+                .with_step_limit(step_limit);
+            let result: Result<State, MachineError> =
+                machine.run_callback(&mut |old, instruction, new| {
+                    /* jumping off the end is a valid way to finish */
+                    invariant::check(old, instruction, new, None)
+                        .map_err(|_| MachineError::InvariantViolation)
+                });
+            outcome(result, &machine.state)
+        }
+        Engine::Step => step_engine(case, step_limit),
+        Engine::Lazy => {
+            let mut machine: Machine<LazyCode> =
+                Machine::new(LazyCode::new(case.code.to_bytes()))
+                    .with_state(case.initial())
+                    .with_step_limit(step_limit);
+            let result: Result<State, MachineError> = machine.run();
+            outcome(result, &machine.state)
+        }
+    }
+}
+
+/* runs a case on every engine, failing on a panic or any disagreement */
+pub fn check(case: &Case, step_limit: u64) -> Result<Outcome, Failure> {
+    let mut outcomes: Vec<(Engine, Outcome)> = vec![];
+
+    for engine in ENGINES {
+        match panic::catch_unwind(AssertUnwindSafe(|| {
+            run_engine(engine, case, step_limit)
+        })) {
+            Ok(t) => outcomes.push((engine, t)),
+            Err(_) => return Err(Failure::Panic(case.clone(), engine)),
+        }
+    }
+
+    let first: Outcome = outcomes[0].1;
+
+    if outcomes.iter().all(|(_, t)| *t == first) {
+        Ok(first)
+    } else {
+        Err(Failure::Disagreement(case.clone(), outcomes))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    pub cases: u64,
+    /* how many cases ended each way, by fault name */
+    pub outcomes: BTreeMap<String, u64>,
+    pub failures: Vec<Failure>,
+}
+
+impl Summary {
+    pub fn record(&mut self, result: Result<Outcome, Failure>) {
+        self.cases += 1;
+
+        match result {
+            Ok(t) => {
+                let name: String = match t.fault {
+                    Some(e) => format!("{:?}", e),
+                    None => "Finished".to_string(),
+                };
+                *self.outcomes.entry(name).or_default() += 1;
+            }
+            Err(e) => self.failures.push(e),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out: String = format!(
+            "{} cases on {} engines, {} failures\n",
+            self.cases,
+            ENGINES.len(),
+            self.failures.len()
+        );
+
+        for (name, count) in &self.outcomes {
+            out.push_str(&format!("  {:<24} {:>10}\n", name, count));
+        }
+
+        for failure in &self.failures {
+            match failure {
+                Failure::Panic(case, engine) => {
+                    out.push_str(&format!("\npanic in {:?} on ", engine));
+                    out.push_str(&case.render());
+                }
+                Failure::Disagreement(case, outcomes) => {
+                    out.push_str("\ndisagreement on ");
+                    out.push_str(&case.render());
+
+                    for (engine, t) in outcomes {
+                        out.push_str(&format!(
+                            "  {:<8} {:?} (state {:016x})\n",
+                            format!("{:?}", engine),
+                            t.fault,
+                            t.digest
+                        ));
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
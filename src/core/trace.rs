@@ -0,0 +1,902 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender, SyncSender, TrySendError};
+use std::thread;
+use std::thread::JoinHandle;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::Word;
+use crate::core::instruction::{Instruction, InstructionParseError};
+use crate::core::state::State;
+
+pub const TRACE_MAGIC: &[u8; 8] = b"DRMTRACE";
+pub const TRACE_VERSION: u8 = 1;
+
+const TAG_STEP: u8 = 0x00;
+const TAG_SUMMARY: u8 = 0x01;
+
+#[derive(Debug)]
+pub enum TraceError {
+    IOError(io::Error),
+    WriterPanicked,
+    UnknownOverflowPolicy(String),
+    Malformed(u64),
+    UnsupportedVersion(u8),
+    InvalidInstruction(InstructionParseError),
+    UnknownFormat(String),
+    InvalidRotation(String),
+    MalformedIndex,
+    InvalidSink(String),
+}
+
+impl From<io::Error> for TraceError {
+    fn from(value: io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    Jsonl,
+    Binary,
+}
+
+impl TryFrom<&str> for TraceFormat {
+    type Error = TraceError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "jsonl" => Ok(Self::Jsonl),
+            "binary" => Ok(Self::Binary),
+            _ => Err(TraceError::UnknownFormat(value.to_string())),
+        }
+    }
+}
+
+/*
+ * A single recorded step. `pc` and `address` describe the instruction as it
+ * was about to execute; `reg` and `depth` describe the state it left
+ * behind.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceRecord {
+    pub step: u64,
+    pub pc: Word,
+    pub instruction: Instruction,
+    pub address: Option<Word>,
+    pub reg: Word,
+    pub depth: u64,
+}
+
+impl TraceRecord {
+    pub fn new(
+        step: u64,
+        old: &State,
+        instruction: Instruction,
+        new: &State,
+    ) -> Self {
+        Self {
+            step,
+            pc: old.pc,
+            instruction,
+            address: if instruction.touches_memory() {
+                old.stack.peek()
+            } else {
+                None
+            },
+            reg: new.reg,
+            depth: new.stack.depth() as u64,
+        }
+    }
+}
+
+/* per-opcode instruction counts, keyed by mnemonic */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraceSummary {
+    pub steps: u64,
+    pub histogram: BTreeMap<String, u64>,
+}
+
+impl TraceSummary {
+    pub fn record(&mut self, instruction: Instruction) {
+        self.steps += 1;
+        *self
+            .histogram
+            .entry(instruction.mnemonic().to_string())
+            .or_insert(0) += 1;
+    }
+
+    /* histogram entries, most frequent first and then by mnemonic */
+    pub fn ranked(&self) -> Vec<(&str, u64)> {
+        let mut rows: Vec<(&str, u64)> = self
+            .histogram
+            .iter()
+            .map(|(mnemonic, count)| (mnemonic.as_str(), *count))
+            .collect();
+        rows.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        rows
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TraceEntry {
+    Summary { summary: TraceSummary },
+    Step(TraceRecord),
+}
+
+/*
+ * When to start a new trace segment and how many to keep, parsed from
+ * `size=SIZE keep=N` (or comma-separated). Sizes may carry a K, M or G
+ * suffix, optionally followed by B, in powers of 1024.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rotation {
+    pub max_bytes: u64,
+    pub keep: usize,
+}
+
+impl TryFrom<&str> for Rotation {
+    type Error = TraceError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || TraceError::InvalidRotation(value.to_string());
+        let mut max_bytes: Option<u64> = None;
+        let mut keep: Option<usize> = None;
+
+        for field in value.split([' ', ',']).filter(|t| !t.is_empty()) {
+            match field.split_once('=') {
+                Some(("size", t)) => max_bytes = Some(parse_size(t)?),
+                Some(("keep", t)) => keep = t.parse().ok(),
+                _ => return Err(invalid()),
+            }
+        }
+
+        match (max_bytes, keep) {
+            (Some(max_bytes), Some(keep)) if max_bytes > 0 && keep > 0 => {
+                Ok(Self { max_bytes, keep })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+fn parse_size(value: &str) -> Result<u64, TraceError> {
+    let digits: &str = value.trim_end_matches(|t: char| t.is_alphabetic());
+    let scale: u64 = match value[digits.len()..].to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => return Err(TraceError::InvalidRotation(value.to_string())),
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|t| t.checked_mul(scale))
+        .ok_or_else(|| TraceError::InvalidRotation(value.to_string()))
+}
+
+/*
+ * Where a trace is streamed, parsed from `file:PATH`, `tcp:HOST:PORT` or
+ * `unix:PATH`; anything without a scheme is a file path.
+ *
+ * Every sink carries exactly the bytes a trace file would hold, so a
+ * consumer frames the stream the way `read_trace` reads a file. A JSONL
+ * stream is one JSON value per line: the `{"trace_version":N}` header,
+ * then a record per step, then a `{"summary":..}` line. A binary stream
+ * is `DRMTRACE` and a version byte, then records each led by a tag byte:
+ * 0x00 for a 51-byte step record, or 0x01 for the summary, which is the
+ * step count, a histogram length `n` and `n` opcode-count pairs. The
+ * summary is always the last record, after which the sink is closed.
+ *
+ * Sinks block when the consumer falls behind, so `--trace-overflow`
+ * decides whether that stalls the machine or drops batches.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceSink {
+    File(PathBuf),
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl TryFrom<&str> for TraceSink {
+    type Error = TraceError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || TraceError::InvalidSink(value.to_string());
+
+        match value.split_once(':') {
+            Some(("file", t)) if !t.is_empty() => Ok(Self::File(t.into())),
+            Some(("tcp", t)) if t.rsplit_once(':').is_some() => {
+                Ok(Self::Tcp(t.to_string()))
+            }
+            Some(("unix", t)) if !t.is_empty() => Ok(Self::Unix(t.into())),
+            Some(("file" | "tcp" | "unix", _)) => Err(invalid()),
+            _ if value.is_empty() => Err(invalid()),
+            _ => Ok(Self::File(value.into())),
+        }
+    }
+}
+
+impl TraceSink {
+    pub fn open(&self) -> Result<Box<dyn Write + Send>, TraceError> {
+        Ok(match self {
+            Self::File(t) => Box::new(BufWriter::new(File::create(t)?)),
+            Self::Tcp(t) => Box::new(BufWriter::new(TcpStream::connect(t)?)),
+            #[cfg(unix)]
+            Self::Unix(t) => Box::new(BufWriter::new(UnixStream::connect(t)?)),
+            #[cfg(not(unix))]
+            Self::Unix(_) => {
+                return Err(TraceError::InvalidSink(
+                    "unix sockets are not supported on this platform"
+                        .to_string(),
+                ))
+            }
+        })
+    }
+}
+
+/*
+ * A sink for hosts embedding the machine: each write is sent as a chunk
+ * over a channel holding at most `bound` chunks, blocking while it is
+ * full. Writes fail once the receiver is dropped.
+ */
+pub struct ChannelSink(SyncSender<Vec<u8>>);
+
+impl ChannelSink {
+    pub fn new(bound: usize) -> (Self, Receiver<Vec<u8>>) {
+        let (sender, receiver): (SyncSender<Vec<u8>>, Receiver<Vec<u8>>) =
+            mpsc::sync_channel(bound);
+        (Self(sender), receiver)
+    }
+}
+
+impl Write for ChannelSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/* where one segment of a rotated trace begins */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    pub path: PathBuf,
+    pub first_step: u64,
+}
+
+/*
+ * The files of a rotated trace: `PATH.0`, `PATH.1` and so on, with the
+ * segments still on disk listed in `PATH.index` so that tooling can find
+ * the one holding a given step. Each segment is a complete trace file in
+ * its own right, and the last one ends with the summary of the whole run.
+ */
+struct Segments {
+    base: PathBuf,
+    rotation: Rotation,
+    count: usize,
+    written: u64,
+    index: Vec<Segment>,
+}
+
+impl Segments {
+    fn path(&self, n: usize) -> PathBuf {
+        let mut path: PathBuf = self.base.clone();
+        path.as_mut_os_string().push(format!(".{}", n));
+        path
+    }
+
+    fn index_path(&self) -> PathBuf {
+        let mut path: PathBuf = self.base.clone();
+        path.as_mut_os_string().push(".index");
+        path
+    }
+
+    /* opens the next segment, dropping the oldest beyond the limit */
+    fn open(
+        &mut self,
+        first_step: u64,
+    ) -> Result<Box<dyn Write + Send>, TraceError> {
+        let path: PathBuf = self.path(self.count);
+        let sink: Box<dyn Write + Send> =
+            Box::new(BufWriter::new(File::create(&path)?));
+
+        self.count += 1;
+        self.written = 0;
+        self.index.push(Segment { path, first_step });
+
+        while self.index.len() > self.rotation.keep {
+            fs::remove_file(self.index.remove(0).path)?;
+        }
+
+        self.write_index()?;
+        Ok(sink)
+    }
+
+    fn write_index(&self) -> Result<(), TraceError> {
+        let mut sink: Box<dyn Write + Send> =
+            Box::new(BufWriter::new(File::create(self.index_path())?));
+
+        for segment in &self.index {
+            write_json_line(&mut sink, segment)?;
+        }
+
+        sink.flush()?;
+        Ok(())
+    }
+}
+
+/* the segments listed in the index of a rotated trace */
+pub fn read_index<R: Read>(source: R) -> Result<Vec<Segment>, TraceError> {
+    BufReader::new(source)
+        .lines()
+        .filter(|t| t.as_ref().map_or(true, |t| !t.trim().is_empty()))
+        .map(|t| {
+            serde_json::from_str(&t?).map_err(|_| TraceError::MalformedIndex)
+        })
+        .collect()
+}
+
+/* the segment holding `step`, if it has not been rotated away */
+pub fn find_segment(index: &[Segment], step: u64) -> Option<&Segment> {
+    match index.first() {
+        Some(t) if step >= t.first_step => {
+            index.iter().rev().find(|t| step >= t.first_step)
+        }
+        _ => None,
+    }
+}
+
+/*
+ * Writes step records in either format, finishing with a summary entry
+ * holding the instruction histogram so that statistics can be read back
+ * without scanning every record.
+ */
+pub struct TraceWriter {
+    sink: Box<dyn Write + Send>,
+    format: TraceFormat,
+    summary: TraceSummary,
+    segments: Option<Segments>,
+}
+
+impl TraceWriter {
+    pub fn new(
+        mut sink: Box<dyn Write + Send>,
+        format: TraceFormat,
+    ) -> Result<Self, TraceError> {
+        write_header(&mut sink, format)?;
+
+        Ok(Self {
+            sink,
+            format,
+            summary: TraceSummary::default(),
+            segments: None,
+        })
+    }
+
+    /* a trace split across files of about `rotation.max_bytes` each */
+    pub fn rotating(
+        base: &Path,
+        format: TraceFormat,
+        rotation: Rotation,
+    ) -> Result<Self, TraceError> {
+        let mut segments: Segments = Segments {
+            base: base.to_path_buf(),
+            rotation,
+            count: 0,
+            written: 0,
+            index: vec![],
+        };
+        let mut writer: Self = Self::new(segments.open(0)?, format)?;
+        writer.segments = Some(segments);
+        Ok(writer)
+    }
+
+    pub fn write(&mut self, record: &TraceRecord) -> Result<(), TraceError> {
+        self.summary.record(record.instruction);
+
+        /* segments only ever split between records */
+        if let Some(segments) = &mut self.segments {
+            if segments.written >= segments.rotation.max_bytes {
+                self.sink.flush()?;
+                self.sink = segments.open(record.step)?;
+                write_header(&mut self.sink, self.format)?;
+            }
+        }
+
+        let buf: Vec<u8> = match self.format {
+            TraceFormat::Jsonl => {
+                let mut buf: Vec<u8> = serde_json::to_vec(record)
+                    .map_err(|e| TraceError::IOError(e.into()))?;
+                buf.push(b'\n');
+                buf
+            }
+            TraceFormat::Binary => {
+                let mut buf: Vec<u8> = vec![TAG_STEP];
+                buf.extend_from_slice(&record.step.to_be_bytes());
+                buf.extend_from_slice(&record.pc.to_be_bytes());
+                buf.push(record.instruction.to_byte());
+                buf.extend_from_slice(
+                    &literal(record.instruction).to_be_bytes(),
+                );
+                buf.push(record.address.is_some() as u8);
+                buf.extend_from_slice(
+                    &record.address.unwrap_or_default().to_be_bytes(),
+                );
+                buf.extend_from_slice(&record.reg.to_be_bytes());
+                buf.extend_from_slice(&record.depth.to_be_bytes());
+                buf
+            }
+        };
+
+        self.sink.write_all(&buf)?;
+
+        if let Some(segments) = &mut self.segments {
+            segments.written += buf.len() as u64;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<TraceSummary, TraceError> {
+        match self.format {
+            TraceFormat::Jsonl => write_json_line(
+                &mut self.sink,
+                &TraceEntry::Summary {
+                    summary: self.summary.clone(),
+                },
+            )?,
+            TraceFormat::Binary => {
+                let mut buf: Vec<u8> = vec![TAG_SUMMARY];
+                buf.extend_from_slice(&self.summary.steps.to_be_bytes());
+                buf.extend_from_slice(
+                    &(self.summary.histogram.len() as u64).to_be_bytes(),
+                );
+
+                for (mnemonic, count) in &self.summary.histogram {
+                    let opcode: u8 = Instruction::from_mnemonic(mnemonic)
+                        .map(|t| t.to_byte())
+                        .unwrap_or_default();
+                    buf.push(opcode);
+                    buf.extend_from_slice(&count.to_be_bytes());
+                }
+
+                self.sink.write_all(&buf)?;
+            }
+        }
+
+        self.sink.flush()?;
+        Ok(self.summary)
+    }
+}
+
+/* records handed to the writer thread at a time */
+pub const TRACE_BATCH_SIZE: usize = 1024;
+
+/* batches that may be queued for the writer thread before the policy applies */
+pub const TRACE_QUEUE_DEPTH: usize = 8;
+
+/*
+ * What to do when the trace sink cannot keep up with execution: `Block`
+ * stalls the machine until the writer catches up, so every record is kept;
+ * `Drop` discards whole batches instead, so a slow sink never slows the run
+ * but the trace may have gaps.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OverflowPolicy {
+    #[default]
+    Block,
+    Drop,
+}
+
+impl TryFrom<&str> for OverflowPolicy {
+    type Error = TraceError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "block" => Ok(Self::Block),
+            "drop" => Ok(Self::Drop),
+            _ => Err(TraceError::UnknownOverflowPolicy(value.to_string())),
+        }
+    }
+}
+
+/* the most memory the batches of a `BackgroundTraceWriter` can occupy */
+pub const TRACE_BUFFER_BYTES: usize =
+    (TRACE_QUEUE_DEPTH + 2) * TRACE_BATCH_SIZE * mem::size_of::<TraceRecord>();
+
+/*
+ * Moves trace output off the execution thread. Records are collected into
+ * fixed-size batches which are handed to a writer thread through a bounded
+ * queue, and the emptied buffers are sent back to be reused, so no more
+ * than `TRACE_QUEUE_DEPTH + 2` batches are ever allocated however long the
+ * run or slow the sink. Write failures end the writer thread and are
+ * reported by `finish`.
+ */
+pub struct BackgroundTraceWriter {
+    batch: Vec<TraceRecord>,
+    queue: Option<SyncSender<Vec<TraceRecord>>>,
+    recycled: Receiver<Vec<TraceRecord>>,
+    worker: Option<JoinHandle<Result<TraceSummary, TraceError>>>,
+    policy: OverflowPolicy,
+    dropped: u64,
+}
+
+impl BackgroundTraceWriter {
+    pub fn new(mut writer: TraceWriter, policy: OverflowPolicy) -> Self {
+        let (queue, batches): (
+            SyncSender<Vec<TraceRecord>>,
+            Receiver<Vec<TraceRecord>>,
+        ) = mpsc::sync_channel(TRACE_QUEUE_DEPTH);
+        let (recycle, recycled): (
+            Sender<Vec<TraceRecord>>,
+            Receiver<Vec<TraceRecord>>,
+        ) = mpsc::channel();
+
+        let worker: JoinHandle<Result<TraceSummary, TraceError>> =
+            thread::spawn(move || {
+                for mut batch in batches {
+                    for record in &batch {
+                        writer.write(record)?;
+                    }
+
+                    batch.clear();
+                    /* the execution thread may already have finished */
+                    let _ = recycle.send(batch);
+                }
+
+                writer.finish()
+            });
+
+        Self {
+            batch: Vec::with_capacity(TRACE_BATCH_SIZE),
+            queue: Some(queue),
+            recycled,
+            worker: Some(worker),
+            policy,
+            dropped: 0,
+        }
+    }
+
+    pub fn write(&mut self, record: &TraceRecord) {
+        self.batch.push(*record);
+
+        if self.batch.len() < TRACE_BATCH_SIZE {
+            return;
+        }
+
+        let next: Vec<TraceRecord> = self
+            .recycled
+            .try_recv()
+            .unwrap_or_else(|_| Vec::with_capacity(TRACE_BATCH_SIZE));
+        let full: Vec<TraceRecord> = mem::replace(&mut self.batch, next);
+
+        if let Some(queue) = &self.queue {
+            match self.policy {
+                /* a send only fails once the writer has given up */
+                OverflowPolicy::Block => {
+                    let _ = queue.send(full);
+                }
+                OverflowPolicy::Drop => match queue.try_send(full) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(mut t)) => {
+                        self.dropped += t.len() as u64;
+                        t.clear();
+                        self.batch = t;
+                    }
+                    Err(TrySendError::Disconnected(_)) => {}
+                },
+            }
+        }
+    }
+
+    /* the number of records discarded under the `Drop` policy */
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /* flushes everything outstanding and waits for the writer to finish */
+    pub fn finish(mut self) -> Result<TraceSummary, TraceError> {
+        if let Some(queue) = self.queue.take() {
+            let _ = queue.send(mem::take(&mut self.batch));
+        }
+
+        match self.worker.take().map(|t| t.join()) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(TraceError::WriterPanicked),
+            None => Ok(TraceSummary::default()),
+        }
+    }
+}
+
+fn write_header(
+    sink: &mut Box<dyn Write + Send>,
+    format: TraceFormat,
+) -> Result<(), TraceError> {
+    match format {
+        TraceFormat::Binary => {
+            sink.write_all(TRACE_MAGIC)?;
+            sink.write_all(&[TRACE_VERSION])?;
+            Ok(())
+        }
+        TraceFormat::Jsonl => write_json_line(
+            sink,
+            &JsonlHeader {
+                trace_version: TRACE_VERSION,
+            },
+        ),
+    }
+}
+
+fn write_json_line<T: Serialize>(
+    sink: &mut Box<dyn Write + Send>,
+    value: &T,
+) -> Result<(), TraceError> {
+    let line: String = serde_json::to_string(value)
+        .map_err(|e| TraceError::IOError(e.into()))?;
+    writeln!(sink, "{}", line)?;
+    Ok(())
+}
+
+fn literal(instruction: Instruction) -> Word {
+    match instruction {
+        Instruction::Set(t) => t,
+        _ => Word::default(),
+    }
+}
+
+/*
+ * Reads back every entry of a trace, detecting the format from the binary
+ * header.
+ */
+pub fn read_trace<R: Read>(source: R) -> Result<Vec<TraceEntry>, TraceError> {
+    let mut reader: BufReader<R> = BufReader::new(source);
+    let is_binary: bool = reader.fill_buf()?.starts_with(TRACE_MAGIC);
+
+    if is_binary {
+        read_binary(reader)
+    } else {
+        read_jsonl(reader)
+    }
+}
+
+/*
+ * The first line of a JSONL trace. Traces written before it was introduced
+ * have none, and are read as version 1.
+ */
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonlHeader {
+    trace_version: u8,
+}
+
+fn read_jsonl<R: BufRead>(reader: R) -> Result<Vec<TraceEntry>, TraceError> {
+    let mut entries: Vec<TraceEntry> = vec![];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line: String = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if i == 0 {
+            if let Ok(t) = serde_json::from_str::<JsonlHeader>(&line) {
+                if t.trace_version != TRACE_VERSION {
+                    return Err(TraceError::UnsupportedVersion(
+                        t.trace_version,
+                    ));
+                }
+
+                continue;
+            }
+        }
+
+        match serde_json::from_str(&line) {
+            Ok(t) => entries.push(t),
+            Err(_) => return Err(TraceError::Malformed(i as u64 + 1)),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn read_binary<R: Read>(mut reader: R) -> Result<Vec<TraceEntry>, TraceError> {
+    let mut header: [u8; 9] = [0; 9];
+    reader.read_exact(&mut header)?;
+
+    if header[8] != TRACE_VERSION {
+        return Err(TraceError::UnsupportedVersion(header[8]));
+    }
+
+    let mut entries: Vec<TraceEntry> = vec![];
+    let mut offset: u64 = header.len() as u64;
+    let mut tag: [u8; 1] = [0];
+
+    loop {
+        if reader.read(&mut tag)? == 0 {
+            break;
+        }
+
+        offset += 1;
+
+        /* a trace cut short mid-record yields every complete record */
+        let entry: Result<TraceEntry, TraceError> =
+            read_binary_entry(&mut reader, tag[0], &mut offset);
+
+        match entry {
+            Ok(t) => entries.push(t),
+            Err(TraceError::IOError(e))
+                if e.kind() == io::ErrorKind::UnexpectedEof =>
+            {
+                break
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn read_binary_entry<R: Read>(
+    reader: &mut R,
+    tag: u8,
+    offset: &mut u64,
+) -> Result<TraceEntry, TraceError> {
+    match tag {
+        TAG_STEP => {
+            let step: u64 = read_word(reader)?;
+            let pc: Word = read_word(reader)?;
+            let opcode: u8 = read_byte(reader)?;
+            let literal: Word = read_word(reader)?;
+            let has_address: u8 = read_byte(reader)?;
+            let address: Word = read_word(reader)?;
+            let reg: Word = read_word(reader)?;
+            let depth: u64 = read_word(reader)?;
+
+            *offset += 50;
+
+            Ok(TraceEntry::Step(TraceRecord {
+                step,
+                pc,
+                instruction: decode_opcode(opcode, literal)?,
+                address: (has_address != 0).then_some(address),
+                reg,
+                depth,
+            }))
+        }
+        TAG_SUMMARY => {
+            let mut summary: TraceSummary = TraceSummary {
+                steps: read_word(reader)?,
+                ..Default::default()
+            };
+            let count: u64 = read_word(reader)?;
+
+            for _ in 0..count {
+                let opcode: u8 = read_byte(reader)?;
+                let n: u64 = read_word(reader)?;
+                let instruction: Instruction =
+                    decode_opcode(opcode, Word::default())?;
+                summary
+                    .histogram
+                    .insert(instruction.mnemonic().to_string(), n);
+            }
+
+            *offset += 16 + 9 * count;
+
+            Ok(TraceEntry::Summary { summary })
+        }
+        _ => Err(TraceError::Malformed(*offset - 1)),
+    }
+}
+
+fn decode_opcode(opcode: u8, literal: Word) -> Result<Instruction, TraceError> {
+    match opcode {
+        0x06 => Ok(Instruction::Set(literal)),
+        _ => Instruction::try_from(&[opcode][..])
+            .map_err(TraceError::InvalidInstruction),
+    }
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> Result<u8, TraceError> {
+    let mut buf: [u8; 1] = [0];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_word<R: Read>(reader: &mut R) -> Result<Word, TraceError> {
+    let mut buf: [u8; 8] = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(Word::from_be_bytes(buf))
+}
+
+/*
+ * Statistics for a trace: the recorded summary if one is present (i.e. the
+ * trace was closed cleanly), otherwise a histogram computed from the step
+ * records themselves.
+ */
+pub fn summarise(entries: &[TraceEntry]) -> TraceSummary {
+    for entry in entries.iter().rev() {
+        if let TraceEntry::Summary { summary } = entry {
+            return summary.clone();
+        }
+    }
+
+    let mut summary: TraceSummary = TraceSummary::default();
+
+    for entry in entries {
+        if let TraceEntry::Step(record) = entry {
+            summary.record(record.instruction);
+        }
+    }
+
+    summary
+}
+
+/*
+ * Writes the step records of a trace as CSV, one row per step, for loading
+ * into external analysis tools. Absent addresses are left empty.
+ */
+pub fn write_steps_csv<W: Write>(
+    sink: &mut W,
+    entries: &[TraceEntry],
+) -> Result<(), TraceError> {
+    writeln!(sink, "step,pc,opcode,mnemonic,literal,address,reg,depth")?;
+
+    for entry in entries {
+        if let TraceEntry::Step(record) = entry {
+            writeln!(
+                sink,
+                "{},{},{},{},{},{},{},{}",
+                record.step,
+                record.pc,
+                record.instruction.to_byte(),
+                record.instruction.mnemonic(),
+                literal(record.instruction),
+                record.address.map(|t| t.to_string()).unwrap_or_default(),
+                record.reg,
+                record.depth
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/* writes a per-opcode summary as CSV, most frequent first */
+pub fn write_stats_csv<W: Write>(
+    sink: &mut W,
+    summary: &TraceSummary,
+) -> Result<(), TraceError> {
+    writeln!(sink, "mnemonic,count,percent")?;
+
+    for (mnemonic, count) in summary.ranked() {
+        writeln!(
+            sink,
+            "{},{},{:.4}",
+            mnemonic,
+            count,
+            100.0 * (count as f64) / (summary.steps.max(1) as f64)
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod cli;
+pub mod cmd;
+pub mod common;
+pub mod core;
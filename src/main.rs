@@ -1,21 +1,61 @@
-use clap::Parser;
+use std::process;
 
-use crate::cli::Opts;
-use crate::cmd::CommandError;
+use clap::Parser;
 
-pub mod cli;
-pub mod cmd;
-pub mod common;
-pub mod core;
+use dreamervm::cli::{Opts, TraceCommand};
+use dreamervm::cmd::{self, CommandError};
+use dreamervm::common;
 
-fn main() -> Result<(), CommandError> {
+fn main() {
     let opts: Opts = Opts::parse();
 
-    match opts {
-        Opts::Run {
+    let result: Result<(), CommandError> = match opts {
+        Opts::Run(run_opts) => {
+            common::signal::install();
+            cmd::run(run_opts)
+        }
+        Opts::Trace(TraceCommand::Segment { index, step }) => {
+            cmd::trace_segment(index, step)
+        }
+        Opts::Trace(TraceCommand::Stats { path }) => cmd::trace_stats(path),
+        Opts::Trace(TraceCommand::Query { path, query }) => {
+            cmd::trace_query(path, query)
+        }
+        Opts::Trace(TraceCommand::Export {
             path,
-            trace,
+            stats,
             output,
-        } => cmd::run(path, trace, output),
+        }) => cmd::trace_export(path, stats, output),
+        Opts::Sweep(sweep_opts) => cmd::sweep(sweep_opts),
+        Opts::Batch(batch_opts) => cmd::batch(batch_opts),
+        Opts::Pipeline(pipeline_opts) => {
+            common::signal::install();
+            cmd::pipeline(pipeline_opts)
+        }
+        Opts::Equiv(equiv_opts) => cmd::equiv(equiv_opts),
+        Opts::Superopt(superopt_opts) => cmd::superopt(superopt_opts),
+        Opts::FuzzInputs(fuzz_opts) => cmd::fuzz_inputs(fuzz_opts),
+        Opts::Grade(grade_opts) => cmd::grade(grade_opts),
+        Opts::Torture(torture_opts) => {
+            common::signal::install();
+            cmd::torture(torture_opts)
+        }
+        Opts::Verify(verify_opts) => cmd::verify(verify_opts),
+        Opts::Bindiff { old, new } => cmd::bindiff(old, new),
+        Opts::Asm { source, output } => cmd::asm(source, output),
+        Opts::Disasm { path } => cmd::disasm(path),
+        Opts::InspectDump { path } => cmd::inspect_dump(path),
+        Opts::Attach { socket, mem } => cmd::attach(socket, mem),
+        Opts::ExplainError { error } => cmd::explain_error(error),
+        Opts::Isa { format, cost_model } => cmd::isa(format, cost_model),
+        Opts::Schema { name } => cmd::schema(name),
+    };
+
+    if let Err(e) = result {
+        match e.dreamer_error() {
+            Some(t) => eprintln!("Error: {}", t.render()),
+            None => eprintln!("Error: {:?}", e),
+        }
+        process::exit(e.exit_code());
     }
 }